@@ -0,0 +1,206 @@
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::{DateTime, Duration, Utc};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use shared::{Role, UserInfo};
+use std::sync::Arc;
+use warp::Filter;
+use crate::config::AuthConfig;
+use crate::db::DbStore;
+
+pub const MIN_PASSWORD_LEN: usize = 8;
+// A year; far below where chrono::Duration::hours overflows
+pub const MAX_TOKEN_TTL_HOURS: i64 = 24 * 365;
+
+// An account as stored in RocksDB
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredUser {
+    pub username: String,
+    // Argon2id PHC string, salt included
+    pub password_hash: String,
+    pub created_at: DateTime<Utc>,
+    #[serde(default = "legacy_role")]
+    pub role: Role,
+}
+
+// Accounts created before roles existed could already do everything; keep it that way
+fn legacy_role() -> Role {
+    Role::Admin
+}
+
+impl StoredUser {
+    pub fn info(&self) -> UserInfo {
+        UserInfo { username: self.username.clone(), role: self.role }
+    }
+}
+
+pub fn hash_password(password: &str) -> Result<String, String> {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|e| e.to_string())
+}
+
+pub fn verify_password(password: &str, hash: &str) -> bool {
+    PasswordHash::new(hash)
+        .map(|parsed| Argon2::default().verify_password(password.as_bytes(), &parsed).is_ok())
+        .unwrap_or(false)
+}
+
+pub fn validate_new_user(username: &str, password: &str) -> Result<(), String> {
+    if username.is_empty() || username.len() > 64 {
+        return Err("Username must be 1 to 64 characters".to_string());
+    }
+    if !username.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-')) {
+        return Err("Username may only contain letters, digits, '.', '_' and '-'".to_string());
+    }
+    if password.len() < MIN_PASSWORD_LEN {
+        return Err(format!("Password must be at least {} characters", MIN_PASSWORD_LEN));
+    }
+    Ok(())
+}
+
+#[derive(Serialize, Deserialize)]
+struct Claims {
+    sub: String,
+    // Unix seconds
+    exp: i64,
+}
+
+// Who a request is acting as, once its token checks out
+#[derive(Debug, Clone)]
+pub struct Session {
+    pub user: StoredUser,
+}
+
+// Why a request was turned away; rendered as a 401 by the rejection handler
+#[derive(Debug)]
+pub struct Unauthorized(pub &'static str);
+
+impl warp::reject::Reject for Unauthorized {}
+
+// Signed in, but the role does not allow the route; rendered as a 403
+#[derive(Debug)]
+pub struct Forbidden(pub Role);
+
+impl warp::reject::Reject for Forbidden {}
+
+// Issues and checks session tokens: `base64url(claims) "." base64url(HMAC-SHA256(claims))`
+pub struct TokenSigner {
+    key: Vec<u8>,
+    ttl: Duration,
+}
+
+impl TokenSigner {
+    pub fn new(config: &AuthConfig) -> Self {
+        let key = match &config.secret {
+            Some(secret) => secret.as_bytes().to_vec(),
+            None => {
+                log::warn!("auth.secret is not set; sessions will not survive a restart");
+                let mut key = vec![0u8; 32];
+                OsRng.fill_bytes(&mut key);
+                key
+            }
+        };
+        Self { key, ttl: Duration::hours(config.token_ttl_hours) }
+    }
+
+    fn mac(&self) -> Hmac<Sha256> {
+        Hmac::<Sha256>::new_from_slice(&self.key).expect("HMAC accepts keys of any length")
+    }
+
+    pub fn issue(&self, username: &str) -> (String, DateTime<Utc>) {
+        let expires_at = Utc::now() + self.ttl;
+        let claims = Claims { sub: username.to_string(), exp: expires_at.timestamp() };
+        let payload = URL_SAFE_NO_PAD.encode(serde_json::to_vec(&claims).expect("claims serialize"));
+        let mut mac = self.mac();
+        mac.update(payload.as_bytes());
+        let signature = URL_SAFE_NO_PAD.encode(mac.finalize().into_bytes());
+        (format!("{}.{}", payload, signature), expires_at)
+    }
+
+    // The username a token was issued to, if the signature holds and it has not expired
+    pub fn verify(&self, token: &str) -> Result<String, Unauthorized> {
+        const INVALID: Unauthorized = Unauthorized("Invalid session token");
+        let (payload, signature) = token.split_once('.').ok_or(INVALID)?;
+        let signature = URL_SAFE_NO_PAD.decode(signature).map_err(|_| INVALID)?;
+        let mut mac = self.mac();
+        mac.update(payload.as_bytes());
+        mac.verify_slice(&signature).map_err(|_| INVALID)?;
+
+        let claims: Claims = URL_SAFE_NO_PAD.decode(payload).ok()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .ok_or(INVALID)?;
+        if claims.exp <= Utc::now().timestamp() {
+            return Err(Unauthorized("Session expired, please log in again"));
+        }
+        Ok(claims.sub)
+    }
+}
+
+// Resolves `Authorization: Bearer <token>` to a Session. The account is looked up on every
+// request so deleting a user ends their sessions immediately.
+pub fn authenticated(
+    signer: Arc<TokenSigner>,
+    db: Arc<DbStore>,
+) -> impl Filter<Extract = (Session,), Error = warp::Rejection> + Clone {
+    warp::header::optional::<String>("authorization").and_then(move |header: Option<String>| {
+        let token = header.as_deref().and_then(|h| h.strip_prefix("Bearer ")).map(|t| t.trim().to_string());
+        resolve(signer.clone(), db.clone(), token)
+    })
+}
+
+#[derive(Deserialize)]
+struct TokenQuery {
+    token: Option<String>,
+}
+
+// `authenticated` for `?token=<token>`, since browsers cannot set headers on a WebSocket handshake
+pub fn authenticated_query(
+    signer: Arc<TokenSigner>,
+    db: Arc<DbStore>,
+) -> impl Filter<Extract = (Session,), Error = warp::Rejection> + Clone {
+    warp::query::<TokenQuery>().and_then(move |query: TokenQuery| resolve(signer.clone(), db.clone(), query.token))
+}
+
+async fn resolve(signer: Arc<TokenSigner>, db: Arc<DbStore>, token: Option<String>) -> Result<Session, warp::Rejection> {
+    let token = token.ok_or_else(|| warp::reject::custom(Unauthorized("Missing session token")))?;
+    let username = signer.verify(&token).map_err(warp::reject::custom)?;
+    match db.get_user(&username) {
+        Ok(Some(user)) => Ok(Session { user }),
+        Ok(None) => Err(warp::reject::custom(Unauthorized("Account no longer exists"))),
+        Err(e) => {
+            log::error!("Loading user {}: {}", username, e);
+            Err(warp::reject::custom(Unauthorized("Could not verify session")))
+        }
+    }
+}
+
+// Narrows `session` to roles for which `allowed` holds. Put it after the method and path
+// filters so other routes still get a chance at the request.
+pub fn authorized(
+    session: impl Filter<Extract = (Session,), Error = warp::Rejection> + Clone,
+    allowed: fn(Role) -> bool,
+) -> impl Filter<Extract = (Session,), Error = warp::Rejection> + Clone {
+    session.and_then(move |session: Session| async move {
+        if allowed(session.user.role) {
+            Ok(session)
+        } else {
+            Err(warp::reject::custom(Forbidden(session.user.role)))
+        }
+    })
+}
+
+// `authorized` for handlers that do not need to know who is asking
+pub fn allow(
+    session: impl Filter<Extract = (Session,), Error = warp::Rejection> + Clone,
+    allowed: fn(Role) -> bool,
+) -> impl Filter<Extract = (), Error = warp::Rejection> + Clone {
+    authorized(session, allowed).map(|_| ()).untuple_one()
+}
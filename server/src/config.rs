@@ -0,0 +1,303 @@
+use chrono::NaiveTime;
+use serde::Deserialize;
+use std::env;
+use std::fmt;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use crate::auth::{MAX_TOKEN_TTL_HOURS, MIN_PASSWORD_LEN};
+use crate::scheduler::WorkingHours;
+
+// Used when CONFIG_FILE is not set. Unlike an explicit path, it is fine for it to be missing.
+const DEFAULT_CONFIG_FILE: &str = "config.toml";
+
+// Everything the server reads at startup. Loaded from a TOML file, then environment
+// variables (including those from .env) override individual settings.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub server: ServerConfig,
+    pub storage: StorageConfig,
+    pub llm: LlmConfig,
+    pub schedule: ScheduleConfig,
+    pub auth: AuthConfig,
+    pub log: LogConfig,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    pub bind: String,
+    // Origins allowed to call the API from a browser; "*" allows any
+    pub allowed_origins: Vec<String>,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            bind: "127.0.0.1:8081".to_string(),
+            allowed_origins: vec!["*".to_string()],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StorageConfig {
+    // RocksDB directory; legacy `<data_dir>_inventory` / `_presets` siblings are migrated from
+    pub data_dir: PathBuf,
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        Self { data_dir: PathBuf::from("./_data_rocksdb") }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LlmConfig {
+    // gemini | openai | stub
+    pub provider: String,
+    // Provider-specific default when unset
+    pub model: Option<String>,
+    // Only used by the openai provider
+    pub base_url: Option<String>,
+    pub api_key: Option<String>,
+}
+
+impl Default for LlmConfig {
+    fn default() -> Self {
+        Self { provider: "gemini".to_string(), model: None, base_url: None, api_key: None }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ScheduleConfig {
+    // "HH:MM", UTC
+    pub work_day_start: String,
+    pub work_day_end: String,
+}
+
+impl Default for ScheduleConfig {
+    fn default() -> Self {
+        Self { work_day_start: "08:00".to_string(), work_day_end: "17:00".to_string() }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuthConfig {
+    // Key that signs session tokens. When unset a random one is generated at startup,
+    // which logs everyone out on every restart.
+    pub secret: Option<String>,
+    pub token_ttl_hours: i64,
+    // Admin account created on startup when there are no users yet
+    pub bootstrap_username: Option<String>,
+    pub bootstrap_password: Option<String>,
+}
+
+impl Default for AuthConfig {
+    fn default() -> Self {
+        Self { secret: None, token_ttl_hours: 12, bootstrap_username: None, bootstrap_password: None }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LogConfig {
+    // error | warn | info | debug | trace | off
+    pub level: String,
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self { level: "info".to_string() }
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Read { path: PathBuf, error: std::io::Error },
+    Parse { path: PathBuf, error: String },
+    Invalid(Vec<String>),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Read { path, error } => write!(f, "cannot read {}: {}", path.display(), error),
+            ConfigError::Parse { path, error } => write!(f, "cannot parse {}: {}", path.display(), error),
+            ConfigError::Invalid(problems) => {
+                write!(f, "invalid configuration:")?;
+                for p in problems {
+                    write!(f, "\n  - {}", p)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Config {
+    // Reads CONFIG_FILE (default config.toml), applies environment overrides and validates.
+    pub fn load() -> Result<Self, ConfigError> {
+        let explicit = env::var("CONFIG_FILE").ok();
+        let path = PathBuf::from(explicit.as_deref().unwrap_or(DEFAULT_CONFIG_FILE));
+        let mut config = match std::fs::read_to_string(&path) {
+            Ok(text) => toml::from_str(&text).map_err(|e| ConfigError::Parse { path: path.clone(), error: e.to_string() })?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound && explicit.is_none() => Config::default(),
+            Err(error) => return Err(ConfigError::Read { path, error }),
+        };
+        config.apply_env();
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn apply_env(&mut self) {
+        let var = |name: &str| env::var(name).ok().filter(|v| !v.trim().is_empty());
+        if let Some(v) = var("BIND_ADDR") {
+            self.server.bind = v;
+        }
+        if let Some(v) = var("CORS_ORIGINS") {
+            self.server.allowed_origins = v.split(',').map(|o| o.trim().to_string()).filter(|o| !o.is_empty()).collect();
+        }
+        if let Some(v) = var("DATA_DIR") {
+            self.storage.data_dir = PathBuf::from(v);
+        }
+        if let Some(v) = var("LLM_PROVIDER") {
+            self.llm.provider = v;
+        }
+        if let Some(v) = var("LLM_MODEL") {
+            self.llm.model = Some(v);
+        }
+        if let Some(v) = var("LLM_BASE_URL") {
+            self.llm.base_url = Some(v);
+        }
+        // GOOGLE_API_KEY predates the provider switch and still works for gemini
+        if let Some(v) = var("LLM_API_KEY").or_else(|| var("GOOGLE_API_KEY").filter(|_| self.llm.provider.eq_ignore_ascii_case("gemini"))) {
+            self.llm.api_key = Some(v);
+        }
+        if let Some(v) = var("WORK_DAY_START") {
+            self.schedule.work_day_start = v;
+        }
+        if let Some(v) = var("WORK_DAY_END") {
+            self.schedule.work_day_end = v;
+        }
+        if let Some(v) = var("AUTH_SECRET") {
+            self.auth.secret = Some(v);
+        }
+        if let Some(v) = var("AUTH_TOKEN_TTL_HOURS") {
+            // Left unparsable on purpose so validate() reports it
+            self.auth.token_ttl_hours = v.trim().parse().unwrap_or(-1);
+        }
+        if let Some(v) = var("AUTH_BOOTSTRAP_USERNAME") {
+            self.auth.bootstrap_username = Some(v);
+        }
+        if let Some(v) = var("AUTH_BOOTSTRAP_PASSWORD") {
+            self.auth.bootstrap_password = Some(v);
+        }
+        if let Some(v) = var("LOG_LEVEL") {
+            self.log.level = v;
+        }
+    }
+
+    // Collects every problem rather than stopping at the first, so one restart fixes them all
+    fn validate(&mut self) -> Result<(), ConfigError> {
+        let mut problems = Vec::new();
+
+        if let Err(e) = self.server.bind.parse::<SocketAddr>() {
+            problems.push(format!("server.bind '{}' is not an address like 127.0.0.1:8081 ({})", self.server.bind, e));
+        }
+        if self.server.allowed_origins.is_empty() {
+            problems.push("server.allowed_origins must not be empty (use [\"*\"] to allow any)".to_string());
+        }
+        for origin in &self.server.allowed_origins {
+            if origin != "*" && !is_origin(origin) {
+                problems.push(format!("server.allowed_origins: '{}' must look like http://host[:port] with no path", origin));
+            }
+        }
+        if self.server.allowed_origins.len() > 1 && self.server.allowed_origins.iter().any(|o| o == "*") {
+            problems.push("server.allowed_origins: '*' cannot be combined with specific origins".to_string());
+        }
+
+        if self.storage.data_dir.as_os_str().is_empty() {
+            problems.push("storage.data_dir must not be empty".to_string());
+        }
+
+        self.llm.provider = self.llm.provider.trim().to_lowercase();
+        if !matches!(self.llm.provider.as_str(), "gemini" | "openai" | "stub") {
+            problems.push(format!("llm.provider '{}' must be gemini, openai or stub", self.llm.provider));
+        }
+        if let Some(url) = &self.llm.base_url {
+            if !(url.starts_with("http://") || url.starts_with("https://")) {
+                problems.push(format!("llm.base_url '{}' must start with http:// or https://", url));
+            }
+        }
+
+        match self.working_hours() {
+            Ok(hours) if hours.start >= hours.end => {
+                problems.push("schedule.work_day_start must be before schedule.work_day_end".to_string());
+            }
+            Ok(_) => {}
+            Err(e) => problems.push(e),
+        }
+
+        if let Some(secret) = &self.auth.secret {
+            if secret.len() < 32 {
+                problems.push("auth.secret must be at least 32 characters".to_string());
+            }
+        }
+        if self.auth.token_ttl_hours <= 0 {
+            problems.push("auth.token_ttl_hours must be a positive number of hours".to_string());
+        } else if self.auth.token_ttl_hours > MAX_TOKEN_TTL_HOURS {
+            problems.push(format!("auth.token_ttl_hours must be at most {} (a year)", MAX_TOKEN_TTL_HOURS));
+        }
+        match (&self.auth.bootstrap_username, &self.auth.bootstrap_password) {
+            (Some(_), None) | (None, Some(_)) => {
+                problems.push("auth.bootstrap_username and auth.bootstrap_password must be set together".to_string());
+            }
+            (Some(_), Some(password)) if password.len() < MIN_PASSWORD_LEN => {
+                problems.push(format!("auth.bootstrap_password must be at least {} characters", MIN_PASSWORD_LEN));
+            }
+            _ => {}
+        }
+
+        if self.log_level().is_none() {
+            problems.push(format!("log.level '{}' must be one of off, error, warn, info, debug, trace", self.log.level));
+        }
+
+        if problems.is_empty() { Ok(()) } else { Err(ConfigError::Invalid(problems)) }
+    }
+
+    pub fn bind_addr(&self) -> SocketAddr {
+        self.server.bind.parse().expect("validated in Config::load")
+    }
+
+    pub fn allows_any_origin(&self) -> bool {
+        self.server.allowed_origins.iter().any(|o| o == "*")
+    }
+
+    pub fn working_hours(&self) -> Result<WorkingHours, String> {
+        let parse = |key: &str, value: &str| {
+            NaiveTime::parse_from_str(value, "%H:%M")
+                .map_err(|e| format!("schedule.{} must be HH:MM, got '{}': {}", key, value, e))
+        };
+        Ok(WorkingHours {
+            start: parse("work_day_start", &self.schedule.work_day_start)?,
+            end: parse("work_day_end", &self.schedule.work_day_end)?,
+        })
+    }
+
+    pub fn log_level(&self) -> Option<log::LevelFilter> {
+        self.log.level.trim().parse().ok()
+    }
+}
+
+// scheme://host[:port], which is what browsers send in the Origin header
+fn is_origin(origin: &str) -> bool {
+    let Some((scheme, rest)) = origin.split_once("://") else {
+        return false;
+    };
+    matches!(scheme, "http" | "https") && !rest.is_empty() && !rest.contains('/')
+}
@@ -1,59 +1,767 @@
-use rocksdb::{DB, Options, IteratorMode};
-use shared::{Task, InventoryItem};
-use std::sync::Arc;
+use rocksdb::{ColumnFamilyDescriptor, DB, Options, IteratorMode, WriteBatch};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use shared::{AuditAction, AuditEntity, AuditEntry, Task, InventoryItem, Job, MaterialConsumption, Quantity, Machine, TaskPage, TaskPreset, Worker};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
+use crate::auth::StoredUser;
+use crate::repository::{DbError, Repository};
+use crate::scheduler;
 
-// Wrapper for thread-safe DB access
+pub const CF_TASKS: &str = "tasks";
+pub const CF_INVENTORY: &str = "inventory";
+pub const CF_PRESETS: &str = "presets";
+pub const CF_AUDIT: &str = "audit";
+pub const CF_USERS: &str = "users";
+pub const CF_WORKERS: &str = "workers";
+pub const CF_MACHINES: &str = "machines";
+pub const CF_JOBS: &str = "jobs";
+const COLUMN_FAMILIES: [&str; 8] = [CF_TASKS, CF_INVENTORY, CF_PRESETS, CF_AUDIT, CF_USERS, CF_WORKERS, CF_MACHINES, CF_JOBS];
+
+// Wrapper for thread-safe DB access: one RocksDB, one column family per entity kind
 pub struct DbStore {
     db: Arc<DB>,
+    pub tasks: Repository<Task>,
+    // A second copy of every task keyed `<start micros>/<id>`, so time ranges are one scan.
+    // Only written alongside `tasks`, in the same batch.
+    tasks_by_start: Repository<Task>,
+    pub inventory: Repository<InventoryItem>,
+    // Lives next to the stock it describes so both change in one batch
+    pub consumption: Repository<MaterialConsumption>,
+    pub presets: Repository<TaskPreset>,
+    pub users: Repository<StoredUser>,
+    pub workers: Repository<Worker>,
+    pub machines: Repository<Machine>,
+    pub jobs: Repository<Job>,
+    // Append-only; written in the same batch as the change it describes
+    pub audit: Repository<AuditEntry>,
+    // Tie-breaker for audit ids written within the same microsecond
+    audit_seq: AtomicU64,
+    // Audit entries of committed changes, for live updates
+    events: broadcast::Sender<AuditEntry>,
+    // Serializes read-modify-write sequences so concurrent adjustments don't lose updates
+    write_lock: Mutex<()>,
+}
+
+// Committed changes a slow subscriber may fall behind by before it has to resync
+const EVENT_BUFFER: usize = 256;
+
+// Writes for one operation and the audit entries describing them, committed together
+#[derive(Default)]
+struct Change {
+    batch: WriteBatch,
+    entries: Vec<AuditEntry>,
+}
+
+pub enum AdjustError {
+    NotFound,
+    UnitMismatch { expected: String },
+    WouldGoNegative { available: Quantity },
+    Db(DbError),
+}
+
+pub enum ConsumeError {
+    AlreadyConsumed,
+    Stale(Option<u64>),
+    Invalid(Vec<String>),
+    Db(DbError),
+}
+
+// The write was based on a version that is no longer stored. Holds the version there is now,
+// None if the record was deleted.
+pub enum SaveError {
+    Stale(Option<u64>),
+    Db(DbError),
+}
+
+pub enum CreateUserError {
+    AlreadyExists,
+    Db(DbError),
+}
+
+pub enum DeleteTaskError {
+    NotFound,
+    // Its materials were taken from stock; reopening the task puts them back
+    Consumed,
+    Db(DbError),
+}
+
+pub enum RestoreError {
+    UnitChanged(String),
+    Stale(Option<u64>),
+    Db(DbError),
 }
 
 impl DbStore {
-    pub fn new(path: &str) -> Self {
+    pub fn open(path: &Path) -> Result<Self, DbError> {
         let mut opts = Options::default();
         opts.create_if_missing(true);
-        let db = DB::open(&opts, path).expect("Failed to open RocksDB");
-        Self { db: Arc::new(db) }
+        opts.create_missing_column_families(true);
+        let descriptors = COLUMN_FAMILIES.iter().map(|name| ColumnFamilyDescriptor::new(*name, Options::default()));
+        let db = Arc::new(DB::open_cf_descriptors(&opts, path, descriptors)?);
+
+        let store = Self {
+            tasks: Repository::new(db.clone(), CF_TASKS, "task/"),
+            tasks_by_start: Repository::new(db.clone(), CF_TASKS, "by_start/"),
+            inventory: Repository::new(db.clone(), CF_INVENTORY, "item/"),
+            consumption: Repository::new(db.clone(), CF_INVENTORY, "consumption/"),
+            presets: Repository::new(db.clone(), CF_PRESETS, "preset/"),
+            users: Repository::new(db.clone(), CF_USERS, "user/"),
+            workers: Repository::new(db.clone(), CF_WORKERS, "worker/"),
+            machines: Repository::new(db.clone(), CF_MACHINES, "machine/"),
+            jobs: Repository::new(db.clone(), CF_JOBS, "job/"),
+            audit: Repository::new(db.clone(), CF_AUDIT, "entry/"),
+            audit_seq: AtomicU64::new(0),
+            events: broadcast::channel(EVENT_BUFFER).0,
+            db,
+            write_lock: Mutex::new(()),
+        };
+        store.migrate_legacy(path)?;
+        store.index_tasks()?;
+        Ok(store)
+    }
+
+    // Databases from before the start-time index have tasks but no index entries; fill it once
+    fn index_tasks(&self) -> Result<(), DbError> {
+        if !self.tasks_by_start.scan_from(None, None, 1, |_| true)?.records.is_empty() {
+            return Ok(());
+        }
+        let tasks = self.get_all_tasks()?;
+        if tasks.is_empty() {
+            return Ok(());
+        }
+        let mut batch = WriteBatch::default();
+        for task in &tasks {
+            self.tasks_by_start.put_in(&mut batch, &start_key(task), task)?;
+        }
+        self.db.write(batch)?;
+        log::info!("Indexed {} tasks by start time", tasks.len());
+        Ok(())
+    }
+
+    // Earlier versions kept tasks in the default column family of `path` and inventory and
+    // presets in their own `<path>_inventory` / `<path>_presets` databases. Move them over once.
+    fn migrate_legacy(&self, path: &Path) -> Result<(), DbError> {
+        let mut batch = WriteBatch::default();
+        for item in self.db.iterator(IteratorMode::Start) {
+            let (key, value) = item?;
+            let id = String::from_utf8_lossy(&key).into_owned();
+            match serde_json::from_slice::<Task>(&value) {
+                Ok(task) => {
+                    self.tasks.put_in(&mut batch, &id, &task)?;
+                    batch.delete(&key);
+                }
+                Err(e) => log::warn!("Legacy task {} left in default column family: {}", id, e),
+            }
+        }
+
+        let inventory_path = format!("{}_inventory", path.display());
+        if Path::new(&inventory_path).exists() {
+            let legacy = DB::open(&Options::default(), &inventory_path)?;
+            for item in legacy.iterator(IteratorMode::Start) {
+                let (key, value) = item?;
+                let key = String::from_utf8_lossy(&key).into_owned();
+                let migrated = match key.strip_prefix("consumption/") {
+                    Some(task_id) => serde_json::from_slice::<MaterialConsumption>(&value)
+                        .map_err(|e| e.to_string())
+                        .and_then(|record| self.consumption.put_in(&mut batch, task_id, &record).map_err(|e| e.to_string())),
+                    None => serde_json::from_slice::<InventoryItem>(&value)
+                        .map_err(|e| e.to_string())
+                        .and_then(|item| self.inventory.put_in(&mut batch, &key, &item).map_err(|e| e.to_string())),
+                };
+                if let Err(e) = migrated {
+                    log::warn!("Legacy inventory record {} not migrated: {}", key, e);
+                }
+            }
+        }
+
+        let presets_path = format!("{}_presets", path.display());
+        if Path::new(&presets_path).exists() {
+            let legacy = DB::open(&Options::default(), &presets_path)?;
+            for item in legacy.iterator(IteratorMode::Start) {
+                let (key, value) = item?;
+                let op_id = String::from_utf8_lossy(&key).into_owned();
+                match serde_json::from_slice::<TaskPreset>(&value) {
+                    Ok(preset) => self.presets.put_in(&mut batch, &op_id, &preset)?,
+                    Err(e) => log::warn!("Legacy preset {} not migrated: {}", op_id, e),
+                }
+            }
+        }
+
+        if batch.is_empty() {
+            return Ok(());
+        }
+        self.db.write(batch)?;
+        // Keep the old directories around, but out of the way
+        for legacy in [inventory_path, presets_path] {
+            if Path::new(&legacy).exists() {
+                std::fs::rename(&legacy, format!("{}.migrated", legacy))
+                    .unwrap_or_else(|e| log::warn!("Could not rename {}: {}", legacy, e));
+            }
+        }
+        log::info!("Migrated legacy RocksDB data into column families");
+        Ok(())
     }
 
-    pub fn add_task(&self, task: Task) -> Result<(), String> {
-        let key = task.id.as_bytes();
-        let value = serde_json::to_string(&task).map_err(|e| e.to_string())?;
-        self.db.put(key, value.as_bytes()).map_err(|e| e.to_string())
+    // Adds `task` or overwrites the one with its id, if `task.version` is still the stored one.
+    // On success `task.version` is the version it was stored under.
+    pub fn add_task(&self, actor: &str, task: &mut Task) -> Result<(), SaveError> {
+        let _guard = self.write_lock.lock().unwrap();
+        let before = self.tasks.get(&task.id).map_err(SaveError::Db)?;
+        task.version = next_version(before.as_ref().map(|t| t.version), task.version).map_err(SaveError::Stale)?;
+        let mut change = Change::default();
+        self.put_task_in(&mut change, before.as_ref(), task).map_err(SaveError::Db)?;
+        self.audit_in(&mut change, actor, AuditEntity::Task, &task.id, before.as_ref(), Some(&*task))
+            .map_err(SaveError::Db)?;
+        self.commit(change).map_err(SaveError::Db)?;
+        Ok(())
     }
 
-    pub fn add_inventory(&self, item: InventoryItem) -> Result<(), String> {
-        let key = item.name.as_bytes();
-        let value = serde_json::to_string(&item).map_err(|e| e.to_string())?;
-        self.db.put(key, value.as_bytes()).map_err(|e| e.to_string())
+    // Writes `task` over `before` (the stored version, if any), keeping the start-time index in step
+    fn put_task_in(&self, change: &mut Change, before: Option<&Task>, task: &Task) -> Result<(), DbError> {
+        if let Some(before) = before.filter(|b| start_key(b) != start_key(task)) {
+            self.tasks_by_start.delete_in(&mut change.batch, &start_key(before))?;
+        }
+        self.tasks.put_in(&mut change.batch, &task.id, task)?;
+        self.tasks_by_start.put_in(&mut change.batch, &start_key(task), task)
     }
 
-    pub fn delete_task(&self, id: &str) -> Result<(), String> {
-        self.db.delete(id.as_bytes()).map_err(|e| e.to_string())
+    pub fn get_task(&self, id: &str) -> Result<Option<Task>, DbError> {
+        self.tasks.get(id)
     }
 
-    pub fn get_all_tasks(&self) -> Vec<Task> {
-        let mut tasks = Vec::new();
-        let iter = self.db.iterator(IteratorMode::Start);
-        for item in iter {
-            if let Ok((_, value)) = item {
-                if let Ok(task) = serde_json::from_slice::<Task>(&value) {
-                    tasks.push(task);
-                }
+    // Tasks starting in [`from`, `to`) that pass `keep`, earliest first, at most `limit` of them.
+    // `cursor` is the `next_cursor` of the previous page.
+    pub fn query_tasks(
+        &self,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+        cursor: Option<&str>,
+        limit: usize,
+        keep: impl FnMut(&Task) -> bool,
+    ) -> Result<TaskPage, DbError> {
+        let lower = match (from.map(time_key), cursor) {
+            (Some(from), Some(cursor)) => Some(from.max(cursor.to_string())),
+            (from, cursor) => from.or(cursor.map(str::to_string)),
+        };
+        let upper = to.map(time_key);
+        // One more than asked for tells whether there is a next page, and where it starts
+        let mut records = self.tasks_by_start.scan_from(lower.as_deref(), upper.as_deref(), limit + 1, keep)?.reported();
+        let next_cursor = if records.len() > limit { records.pop().map(|(key, _)| key) } else { None };
+        Ok(TaskPage { tasks: records.into_iter().map(|(_, task)| task).collect(), next_cursor })
+    }
+
+    // Refuses tasks that still hold consumed stock, which would otherwise never be restored
+    pub fn delete_task(&self, actor: &str, id: &str) -> Result<(), DeleteTaskError> {
+        let _guard = self.write_lock.lock().unwrap();
+        let Some(before) = self.tasks.get(id).map_err(DeleteTaskError::Db)? else {
+            return Err(DeleteTaskError::NotFound);
+        };
+        if self.consumption.get(id).map_err(DeleteTaskError::Db)?.is_some() {
+            return Err(DeleteTaskError::Consumed);
+        }
+        self.remove_task(actor, &before).map_err(DeleteTaskError::Db)
+    }
+
+    // Deletes `task` along with every reference to it
+    fn remove_task(&self, actor: &str, task: &Task) -> Result<(), DbError> {
+        let id = task.id.as_str();
+        let mut change = Change::default();
+        self.delete_tasks_in(&mut change, actor, std::slice::from_ref(task))?;
+        // Jobs that routed through it skip it now
+        let routed = self.jobs.scan()?.reported().into_iter().filter(|(_, job)| job.task_ids.iter().any(|t| t == id));
+        for (_, before) in routed {
+            let mut job = before.clone();
+            job.task_ids.retain(|t| t != id);
+            self.jobs.put_in(&mut change.batch, &job.id, &job)?;
+            self.audit_in(&mut change, actor, AuditEntity::Job, &job.id, Some(&before), Some(&job))?;
+        }
+        self.commit(change)?;
+        Ok(())
+    }
+
+    // Deletes `doomed` and unhooks the remaining tasks that waited for any of them
+    fn delete_tasks_in(&self, change: &mut Change, actor: &str, doomed: &[Task]) -> Result<(), DbError> {
+        let is_doomed = |id: &str| doomed.iter().any(|t| t.id == id);
+        for task in doomed {
+            self.tasks.delete_in(&mut change.batch, &task.id)?;
+            self.tasks_by_start.delete_in(&mut change.batch, &start_key(task))?;
+            self.audit_in(change, actor, AuditEntity::Task, &task.id, Some(task), None)?;
+        }
+        let waiting = self.get_all_tasks()?.into_iter()
+            .filter(|t| !is_doomed(&t.id) && t.depends_on.iter().any(|d| is_doomed(&d.task_id)));
+        for before in waiting {
+            let mut task = before.clone();
+            task.depends_on.retain(|d| !is_doomed(&d.task_id));
+            task.version += 1;
+            self.put_task_in(change, Some(&before), &task)?;
+            self.audit_in(change, actor, AuditEntity::Task, &task.id, Some(&before), Some(&task))?;
+        }
+        Ok(())
+    }
+
+    // Pushes back the planned tasks that, directly or through others, depend on `task_id` and
+    // would now start before it is done. Returns them as stored.
+    pub fn push_dependents(&self, actor: &str, task_id: &str, now: DateTime<Utc>) -> Result<Vec<Task>, DbError> {
+        let _guard = self.write_lock.lock().unwrap();
+        let tasks = self.get_all_tasks()?;
+        let mut moved = scheduler::cascade(&tasks, task_id, now);
+        if moved.is_empty() {
+            return Ok(moved);
+        }
+        let mut change = Change::default();
+        for task in &mut moved {
+            let before = tasks.iter().find(|t| t.id == task.id);
+            task.version += 1;
+            self.put_task_in(&mut change, before, task)?;
+            self.audit_in(&mut change, actor, AuditEntity::Task, &task.id, before, Some(&*task))?;
+        }
+        self.commit(change)?;
+        Ok(moved)
+    }
+
+    pub fn get_all_tasks(&self) -> Result<Vec<Task>, DbError> {
+        Ok(self.tasks.scan()?.reported().into_iter().map(|(_, task)| task).collect())
+    }
+
+    // Same version rules as `add_task`
+    pub fn add_inventory(&self, actor: &str, item: &mut InventoryItem) -> Result<(), SaveError> {
+        let _guard = self.write_lock.lock().unwrap();
+        let current = self.inventory.get(&item.name).map_err(SaveError::Db)?.map(|i| i.version);
+        item.version = next_version(current, item.version).map_err(SaveError::Stale)?;
+        self.put_inventory(actor, item).map_err(SaveError::Db)
+    }
+
+    // Caller holds the write lock and has set the new version
+    fn put_inventory(&self, actor: &str, item: &InventoryItem) -> Result<(), DbError> {
+        let before = self.inventory.get(&item.name)?;
+        let mut change = Change::default();
+        self.inventory.put_in(&mut change.batch, &item.name, item)?;
+        self.audit_in(&mut change, actor, AuditEntity::InventoryItem, &item.name, before.as_ref(), Some(item))?;
+        self.commit(change)?;
+        Ok(())
+    }
+
+    pub fn get_inventory(&self, name: &str) -> Result<Option<InventoryItem>, DbError> {
+        self.inventory.get(name)
+    }
+
+    pub fn get_all_inventory(&self) -> Result<Vec<InventoryItem>, DbError> {
+        Ok(self.inventory.scan()?.reported().into_iter().map(|(_, item)| item).collect())
+    }
+
+    // Overwrites `old_name` with `item`, moving the record in one batch if the name changed.
+    // A rename is logged as an update of the new name whose `before` still shows the old one.
+    // `item.version` must match the record under `old_name`, as in `add_task`.
+    pub fn replace_inventory(&self, actor: &str, old_name: &str, item: &mut InventoryItem) -> Result<(), SaveError> {
+        let _guard = self.write_lock.lock().unwrap();
+        let before = self.inventory.get(old_name).map_err(SaveError::Db)?;
+        item.version = next_version(before.as_ref().map(|i| i.version), item.version).map_err(SaveError::Stale)?;
+        let mut change = Change::default();
+        if old_name != item.name {
+            self.inventory.delete_in(&mut change.batch, old_name).map_err(SaveError::Db)?;
+        }
+        self.inventory.put_in(&mut change.batch, &item.name, item).map_err(SaveError::Db)?;
+        self.audit_in(&mut change, actor, AuditEntity::InventoryItem, &item.name, before.as_ref(), Some(&*item))
+            .map_err(SaveError::Db)?;
+        self.commit(change).map_err(SaveError::Db)?;
+        Ok(())
+    }
+
+    pub fn delete_inventory(&self, actor: &str, name: &str) -> Result<(), DbError> {
+        let _guard = self.write_lock.lock().unwrap();
+        let Some(before) = self.inventory.get(name)? else {
+            return Ok(());
+        };
+        let mut change = Change::default();
+        self.inventory.delete_in(&mut change.batch, name)?;
+        self.audit_in(&mut change, actor, AuditEntity::InventoryItem, name, Some(&before), None)?;
+        self.commit(change)?;
+        Ok(())
+    }
+
+    // Adds `delta` (negative to consume) to an item's quantity under the write lock.
+    // `delta` may use any unit convertible to the one the item is stocked in.
+    pub fn adjust_inventory(&self, actor: &str, name: &str, delta: &Quantity) -> Result<InventoryItem, AdjustError> {
+        let _guard = self.write_lock.lock().unwrap();
+        let mut item = self.get_inventory(name).map_err(AdjustError::Db)?.ok_or(AdjustError::NotFound)?;
+        let Some(updated) = item.quantity.checked_add(delta).map(Quantity::settled) else {
+            return Err(AdjustError::UnitMismatch { expected: item.quantity.unit });
+        };
+        if updated.is_short() {
+            return Err(AdjustError::WouldGoNegative { available: item.quantity });
+        }
+        item.quantity = updated;
+        item.version += 1;
+        self.put_inventory(actor, &item).map_err(AdjustError::Db)?;
+        Ok(item)
+    }
+
+    pub fn get_consumption(&self, task_id: &str) -> Result<Option<MaterialConsumption>, DbError> {
+        self.consumption.get(task_id)
+    }
+
+    // Subtracts every material of `task` from stock, stores what was taken and saves `task`,
+    // all in one batch. Nothing is written unless every material exists, matches and is in stock
+    // and `task.version` is still the stored one; it is then bumped like in `add_task`.
+    pub fn consume_materials(&self, actor: &str, task: &mut Task) -> Result<MaterialConsumption, ConsumeError> {
+        let _guard = self.write_lock.lock().unwrap();
+        if self.get_consumption(&task.id).map_err(ConsumeError::Db)?.is_some() {
+            return Err(ConsumeError::AlreadyConsumed);
+        }
+        let previous_task = self.tasks.get(&task.id).map_err(ConsumeError::Db)?;
+        let version = next_version(previous_task.as_ref().map(|t| t.version), task.version)
+            .map_err(ConsumeError::Stale)?;
+
+        let mut problems = Vec::new();
+        let mut before = Vec::new();
+        let mut updated = Vec::new();
+        let mut consumed = Vec::new();
+        for (name, required) in &task.materials {
+            let Some(mut item) = self.get_inventory(name).map_err(ConsumeError::Db)? else {
+                problems.push(format!("{}: not in inventory", name));
+                continue;
+            };
+            // Recorded in the stock unit so restoring is exact even if the task is edited later
+            let Some(amount) = required.convert_to(&item.quantity.unit) else {
+                problems.push(format!("{}: unit mismatch, stock is in '{}', task uses '{}'", name, item.quantity.unit, required.unit));
+                continue;
+            };
+            let remaining = Quantity::new(item.quantity.value - amount.value, item.quantity.unit.clone()).settled();
+            if remaining.is_short() {
+                problems.push(format!("{}: need {}, only {} available", name, required, item.quantity));
+                continue;
             }
+            before.push(item.clone());
+            item.quantity = remaining;
+            item.version += 1;
+            updated.push(item);
+            consumed.push(InventoryItem { name: name.clone(), quantity: amount, version: 0 });
         }
-        tasks
+        if !problems.is_empty() {
+            problems.sort();
+            return Err(ConsumeError::Invalid(problems));
+        }
+
+        let record = MaterialConsumption {
+            task_id: task.id.clone(),
+            consumed_at: chrono::Utc::now(),
+            items: consumed,
+        };
+        task.version = version;
+        let mut change = Change::default();
+        for (old, item) in before.iter().zip(&updated) {
+            self.inventory.put_in(&mut change.batch, &item.name, item).map_err(ConsumeError::Db)?;
+            self.audit_in(&mut change, actor, AuditEntity::InventoryItem, &item.name, Some(old), Some(item))
+                .map_err(ConsumeError::Db)?;
+        }
+        self.consumption.put_in(&mut change.batch, &task.id, &record).map_err(ConsumeError::Db)?;
+        self.put_task_in(&mut change, previous_task.as_ref(), task).map_err(ConsumeError::Db)?;
+        self.audit_in(&mut change, actor, AuditEntity::Task, &task.id, previous_task.as_ref(), Some(&*task))
+            .map_err(ConsumeError::Db)?;
+        self.commit(change).map_err(ConsumeError::Db)?;
+        Ok(record)
     }
 
-    pub fn get_all_inventory(&self) -> Vec<InventoryItem> {
-        let mut items = Vec::new();
-        let iter = self.db.iterator(IteratorMode::Start);
-        for item in iter {
-            if let Ok((_, value)) = item {
-                if let Ok(inv) = serde_json::from_slice::<InventoryItem>(&value) {
-                    items.push(inv);
-                }
+    // Puts back whatever was consumed for `task` and saves `task` in the same batch.
+    // Items deleted in the meantime are recreated. Versions are checked and bumped like in `add_task`.
+    pub fn restore_consumption(&self, actor: &str, task: &mut Task) -> Result<Option<MaterialConsumption>, RestoreError> {
+        let _guard = self.write_lock.lock().unwrap();
+        let previous_task = self.tasks.get(&task.id).map_err(RestoreError::Db)?;
+        task.version = next_version(previous_task.as_ref().map(|t| t.version), task.version)
+            .map_err(RestoreError::Stale)?;
+        let record = self.get_consumption(&task.id).map_err(RestoreError::Db)?;
+
+        let mut change = Change::default();
+        if let Some(record) = &record {
+            for used in &record.items {
+                let existing = self.get_inventory(&used.name).map_err(RestoreError::Db)?;
+                let item = match existing.clone() {
+                    Some(mut item) => match item.quantity.checked_add(&used.quantity) {
+                        Some(restored) => {
+                            item.quantity = restored;
+                            item.version += 1;
+                            item
+                        }
+                        None => {
+                            return Err(RestoreError::UnitChanged(format!(
+                                "{} is now stored in '{}', cannot restore {}", item.name, item.quantity.unit, used.quantity
+                            )));
+                        }
+                    },
+                    None => InventoryItem { version: 1, ..used.clone() },
+                };
+                self.inventory.put_in(&mut change.batch, &item.name, &item).map_err(RestoreError::Db)?;
+                self.audit_in(&mut change, actor, AuditEntity::InventoryItem, &item.name, existing.as_ref(), Some(&item))
+                    .map_err(RestoreError::Db)?;
             }
+            self.consumption.delete_in(&mut change.batch, &task.id).map_err(RestoreError::Db)?;
         }
-        items
+        self.put_task_in(&mut change, previous_task.as_ref(), task).map_err(RestoreError::Db)?;
+        self.audit_in(&mut change, actor, AuditEntity::Task, &task.id, previous_task.as_ref(), Some(&*task))
+            .map_err(RestoreError::Db)?;
+        self.commit(change).map_err(RestoreError::Db)?;
+        Ok(record)
+    }
+
+    pub fn add_preset(&self, actor: &str, op_id: &str, preset: &TaskPreset) -> Result<(), DbError> {
+        let _guard = self.write_lock.lock().unwrap();
+        let before = self.presets.get(op_id)?;
+        let mut change = Change::default();
+        self.presets.put_in(&mut change.batch, op_id, preset)?;
+        self.audit_in(&mut change, actor, AuditEntity::Preset, op_id, before.as_ref(), Some(preset))?;
+        self.commit(change)?;
+        Ok(())
+    }
+
+    pub fn get_preset(&self, op_id: &str) -> Result<Option<TaskPreset>, DbError> {
+        self.presets.get(op_id)
     }
-}
\ No newline at end of file
+
+    pub fn delete_preset(&self, actor: &str, op_id: &str) -> Result<(), DbError> {
+        let _guard = self.write_lock.lock().unwrap();
+        let Some(before) = self.presets.get(op_id)? else {
+            return Ok(());
+        };
+        let mut change = Change::default();
+        self.presets.delete_in(&mut change.batch, op_id)?;
+        self.audit_in(&mut change, actor, AuditEntity::Preset, op_id, Some(&before), None)?;
+        self.commit(change)?;
+        Ok(())
+    }
+
+    // Stores only the presets whose operation id is not on the server yet; returns the ids written
+    pub fn import_presets(&self, actor: &str, presets: HashMap<String, TaskPreset>) -> Result<Vec<String>, DbError> {
+        let _guard = self.write_lock.lock().unwrap();
+        let mut change = Change::default();
+        let mut imported = Vec::new();
+        for (op_id, preset) in presets {
+            if self.get_preset(&op_id)?.is_some() {
+                continue;
+            }
+            self.presets.put_in(&mut change.batch, &op_id, &preset)?;
+            self.audit_in(&mut change, actor, AuditEntity::Preset, &op_id, None, Some(&preset))?;
+            imported.push(op_id);
+        }
+        self.commit(change)?;
+        imported.sort();
+        Ok(imported)
+    }
+
+    // Adds `worker` or overwrites the one with its id
+    pub fn put_worker(&self, actor: &str, worker: &Worker) -> Result<(), DbError> {
+        let _guard = self.write_lock.lock().unwrap();
+        let before = self.workers.get(&worker.id)?;
+        let mut change = Change::default();
+        self.workers.put_in(&mut change.batch, &worker.id, worker)?;
+        self.audit_in(&mut change, actor, AuditEntity::Worker, &worker.id, before.as_ref(), Some(worker))?;
+        self.commit(change)?;
+        Ok(())
+    }
+
+    pub fn get_worker(&self, id: &str) -> Result<Option<Worker>, DbError> {
+        self.workers.get(id)
+    }
+
+    // Tasks assigned to the worker keep their `user_id`
+    pub fn delete_worker(&self, actor: &str, id: &str) -> Result<(), DbError> {
+        let _guard = self.write_lock.lock().unwrap();
+        let Some(before) = self.workers.get(id)? else {
+            return Ok(());
+        };
+        let mut change = Change::default();
+        self.workers.delete_in(&mut change.batch, id)?;
+        self.audit_in(&mut change, actor, AuditEntity::Worker, id, Some(&before), None)?;
+        self.commit(change)?;
+        Ok(())
+    }
+
+    // Adds `machine` or overwrites the one with its id
+    pub fn put_machine(&self, actor: &str, machine: &Machine) -> Result<(), DbError> {
+        let _guard = self.write_lock.lock().unwrap();
+        let before = self.machines.get(&machine.id)?;
+        let mut change = Change::default();
+        self.machines.put_in(&mut change.batch, &machine.id, machine)?;
+        self.audit_in(&mut change, actor, AuditEntity::Machine, &machine.id, before.as_ref(), Some(machine))?;
+        self.commit(change)?;
+        Ok(())
+    }
+
+    pub fn get_machine(&self, id: &str) -> Result<Option<Machine>, DbError> {
+        self.machines.get(id)
+    }
+
+    // Tasks that need the machine keep its id in `machine_ids`
+    pub fn delete_machine(&self, actor: &str, id: &str) -> Result<(), DbError> {
+        let _guard = self.write_lock.lock().unwrap();
+        let Some(before) = self.machines.get(id)? else {
+            return Ok(());
+        };
+        let mut change = Change::default();
+        self.machines.delete_in(&mut change.batch, id)?;
+        self.audit_in(&mut change, actor, AuditEntity::Machine, id, Some(&before), None)?;
+        self.commit(change)?;
+        Ok(())
+    }
+
+    // Stores a new job together with its routing tasks, which must not exist yet
+    pub fn add_job(&self, actor: &str, job: &Job, tasks: &mut [Task]) -> Result<(), DbError> {
+        let _guard = self.write_lock.lock().unwrap();
+        let mut change = Change::default();
+        for task in tasks.iter_mut() {
+            task.version = 1;
+            self.put_task_in(&mut change, None, task)?;
+            self.audit_in(&mut change, actor, AuditEntity::Task, &task.id, None, Some(&*task))?;
+        }
+        self.jobs.put_in(&mut change.batch, &job.id, job)?;
+        self.audit_in(&mut change, actor, AuditEntity::Job, &job.id, None, Some(job))?;
+        self.commit(change)?;
+        Ok(())
+    }
+
+    pub fn get_job(&self, id: &str) -> Result<Option<Job>, DbError> {
+        self.jobs.get(id)
+    }
+
+    // Overwrites the stored job; the caller keeps its routing
+    pub fn put_job(&self, actor: &str, job: &Job) -> Result<(), DbError> {
+        let _guard = self.write_lock.lock().unwrap();
+        let before = self.jobs.get(&job.id)?;
+        let mut change = Change::default();
+        self.jobs.put_in(&mut change.batch, &job.id, job)?;
+        self.audit_in(&mut change, actor, AuditEntity::Job, &job.id, before.as_ref(), Some(job))?;
+        self.commit(change)?;
+        Ok(())
+    }
+
+    // Deletes the job and the tasks of its routing
+    // Same rule as `delete_task`: refused while any of them holds consumed stock
+    pub fn delete_job(&self, actor: &str, id: &str) -> Result<(), DeleteTaskError> {
+        let _guard = self.write_lock.lock().unwrap();
+        let Some(before) = self.jobs.get(id).map_err(DeleteTaskError::Db)? else {
+            return Err(DeleteTaskError::NotFound);
+        };
+        let mut doomed = Vec::new();
+        for task_id in &before.task_ids {
+            if self.consumption.get(task_id).map_err(DeleteTaskError::Db)?.is_some() {
+                return Err(DeleteTaskError::Consumed);
+            }
+            doomed.extend(self.tasks.get(task_id).map_err(DeleteTaskError::Db)?);
+        }
+        let mut change = Change::default();
+        self.delete_tasks_in(&mut change, actor, &doomed).map_err(DeleteTaskError::Db)?;
+        self.jobs.delete_in(&mut change.batch, id).map_err(DeleteTaskError::Db)?;
+        self.audit_in(&mut change, actor, AuditEntity::Job, id, Some(&before), None).map_err(DeleteTaskError::Db)?;
+        self.commit(change).map_err(DeleteTaskError::Db)
+    }
+
+    // Adds an audit entry for `entity_id` going from `before` to `after` to `change`.
+    // Writes that leave the record unchanged are not logged.
+    fn audit_in<T: Serialize>(
+        &self,
+        change: &mut Change,
+        actor: &str,
+        entity: AuditEntity,
+        entity_id: &str,
+        before: Option<&T>,
+        after: Option<&T>,
+    ) -> Result<(), DbError> {
+        let to_json = |value: Option<&T>| {
+            value.map(serde_json::to_value).transpose().map_err(|e| DbError::Serialize(e.to_string()))
+        };
+        let (before, after) = (to_json(before)?, to_json(after)?);
+        let action = match (&before, &after) {
+            (None, None) => return Ok(()),
+            (Some(b), Some(a)) if b == a => return Ok(()),
+            (None, Some(_)) => AuditAction::Create,
+            (Some(_), Some(_)) => AuditAction::Update,
+            (Some(_), None) => AuditAction::Delete,
+        };
+        let at = Utc::now();
+        let seq = self.audit_seq.fetch_add(1, Ordering::Relaxed) % 1_000_000;
+        let entry = AuditEntry {
+            id: format!("{}-{:06}", time_key(at), seq),
+            at,
+            actor: actor.to_string(),
+            entity,
+            entity_id: entity_id.to_string(),
+            action,
+            before,
+            after,
+        };
+        self.audit.put_in(&mut change.batch, &entry.id, &entry)?;
+        change.entries.push(entry);
+        Ok(())
+    }
+
+    // Writes the batch, then tells subscribers what changed
+    fn commit(&self, change: Change) -> Result<(), DbError> {
+        self.db.write(change.batch)?;
+        for entry in change.entries {
+            // Only fails when nobody is listening
+            let _ = self.events.send(entry);
+        }
+        Ok(())
+    }
+
+    // Every audit entry committed from now on
+    pub fn subscribe(&self) -> broadcast::Receiver<AuditEntry> {
+        self.events.subscribe()
+    }
+
+    // Newest first, between `since` (inclusive) and `until`/`before` (exclusive), at most `limit`
+    // entries that pass `keep`. `before` is the id of the last entry of the previous page.
+    pub fn audit_log(
+        &self,
+        since: Option<DateTime<Utc>>,
+        until: Option<DateTime<Utc>>,
+        before: Option<&str>,
+        limit: usize,
+        keep: impl FnMut(&AuditEntry) -> bool,
+    ) -> Result<Vec<AuditEntry>, DbError> {
+        let lower = since.map(time_key);
+        let upper = match (until.map(time_key), before) {
+            (Some(until), Some(before)) => Some(until.min(before.to_string())),
+            (until, before) => until.or(before.map(str::to_string)),
+        };
+        let scan = self.audit.scan_rev(lower.as_deref(), upper.as_deref(), limit, keep)?;
+        Ok(scan.reported().into_iter().map(|(_, entry)| entry).collect())
+    }
+
+    pub fn get_user(&self, username: &str) -> Result<Option<StoredUser>, DbError> {
+        self.users.get(username)
+    }
+
+    // Usernames are unique; checked under the write lock so two signups can't race
+    pub fn create_user(&self, user: StoredUser) -> Result<(), CreateUserError> {
+        let _guard = self.write_lock.lock().unwrap();
+        match self.users.get(&user.username) {
+            Ok(Some(_)) => Err(CreateUserError::AlreadyExists),
+            Ok(None) => self.users.put(&user.username, &user).map_err(CreateUserError::Db),
+            Err(e) => Err(CreateUserError::Db(e)),
+        }
+    }
+}
+
+// Version to store for a write based on `expected` when `current` is stored (None if nothing
+// is, in which case `expected` must be 0). Err holds `current` if someone else wrote first.
+fn next_version(current: Option<u64>, expected: u64) -> Result<u64, Option<u64>> {
+    match current {
+        Some(version) if version == expected => Ok(version + 1),
+        None if expected == 0 => Ok(1),
+        current => Err(current),
+    }
+}
+
+// Zero-padded so audit ids and index keys sort by time as strings
+fn time_key(at: DateTime<Utc>) -> String {
+    format!("{:020}", at.timestamp_micros().max(0))
+}
+
+// Where a task sits in `tasks_by_start`
+fn start_key(task: &Task) -> String {
+    format!("{}/{}", time_key(task.start_time), task.id)
+}
@@ -0,0 +1,67 @@
+use shared::{ApiError, ErrorCode};
+use std::convert::Infallible;
+use std::fmt::Display;
+use warp::http::StatusCode;
+use warp::Reply;
+use crate::auth::{Forbidden, Unauthorized};
+
+pub fn status_of(code: ErrorCode) -> StatusCode {
+    match code {
+        ErrorCode::InvalidRequest | ErrorCode::UnitMismatch => StatusCode::BAD_REQUEST,
+        ErrorCode::Unauthorized => StatusCode::UNAUTHORIZED,
+        ErrorCode::Forbidden => StatusCode::FORBIDDEN,
+        ErrorCode::NotFound => StatusCode::NOT_FOUND,
+        ErrorCode::MethodNotAllowed => StatusCode::METHOD_NOT_ALLOWED,
+        ErrorCode::AlreadyExists
+        | ErrorCode::TaskConflict
+        | ErrorCode::InvalidTransition
+        | ErrorCode::VersionConflict
+        | ErrorCode::InsufficientStock => StatusCode::CONFLICT,
+        ErrorCode::NoSlotAvailable => StatusCode::UNPROCESSABLE_ENTITY,
+        ErrorCode::Internal | ErrorCode::Unknown => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+// JSON body with the status that belongs to its code
+pub fn reply(error: ApiError) -> warp::reply::Response {
+    let status = status_of(error.code);
+    warp::reply::with_status(warp::reply::json(&error), status).into_response()
+}
+
+pub fn error_reply(code: ErrorCode, message: impl Into<String>) -> warp::reply::Response {
+    reply(ApiError::new(code, message))
+}
+
+pub fn not_found(what: impl Display) -> ApiError {
+    ApiError::new(ErrorCode::NotFound, format!("{} not found", what))
+}
+
+// Logs the real cause and hands the client a generic message
+pub fn internal(context: impl Display, e: impl Display) -> ApiError {
+    log::error!("{}: {}", context, e);
+    ApiError::new(ErrorCode::Internal, "Internal server error")
+}
+
+// Turns warp's own rejections (unknown route, bad JSON body, ...) into the same JSON shape
+pub async fn handle_rejection(err: warp::Rejection) -> Result<warp::reply::Response, Infallible> {
+    let error = if let Some(e) = err.find::<Unauthorized>() {
+        ApiError::new(ErrorCode::Unauthorized, e.0)
+    } else if let Some(Forbidden(role)) = err.find::<Forbidden>() {
+        ApiError::new(ErrorCode::Forbidden, format!("The {} role is not allowed to do this", role.label().to_lowercase()))
+    } else if err.is_not_found() {
+        ApiError::new(ErrorCode::NotFound, "No such endpoint")
+    } else if let Some(e) = err.find::<warp::filters::body::BodyDeserializeError>() {
+        ApiError::new(ErrorCode::InvalidRequest, format!("Invalid body: {}", e))
+    } else if let Some(e) = err.find::<warp::reject::InvalidQuery>() {
+        ApiError::new(ErrorCode::InvalidRequest, format!("Invalid query: {}", e))
+    } else if let Some(e) = err.find::<warp::reject::UnsupportedMediaType>() {
+        ApiError::new(ErrorCode::InvalidRequest, e.to_string())
+    } else if let Some(e) = err.find::<warp::reject::PayloadTooLarge>() {
+        ApiError::new(ErrorCode::InvalidRequest, e.to_string())
+    } else if err.find::<warp::reject::MethodNotAllowed>().is_some() {
+        ApiError::new(ErrorCode::MethodNotAllowed, "Method not allowed")
+    } else {
+        internal("Unhandled rejection", format!("{:?}", err))
+    };
+    Ok(reply(error))
+}
@@ -0,0 +1,37 @@
+use futures::{SinkExt, StreamExt};
+use shared::{AuditEntry, ServerEvent};
+use tokio::sync::broadcast::{error::RecvError, Receiver};
+use warp::ws::{Message, WebSocket};
+
+// Forwards committed changes to one browser until either side goes away.
+// Anything the browser sends is ignored; it only listens.
+pub async fn forward(socket: WebSocket, mut changes: Receiver<AuditEntry>) {
+    let (mut outgoing, mut incoming) = socket.split();
+    loop {
+        let event = tokio::select! {
+            change = changes.recv() => match change {
+                Ok(entry) => ServerEvent::Changed(entry),
+                Err(RecvError::Lagged(missed)) => {
+                    log::warn!("Live update client fell behind by {} events, asking it to resync", missed);
+                    ServerEvent::Resync
+                }
+                Err(RecvError::Closed) => break,
+            },
+            message = incoming.next() => match message {
+                Some(Ok(message)) if !message.is_close() => continue,
+                _ => break,
+            },
+        };
+        let text = match serde_json::to_string(&event) {
+            Ok(text) => text,
+            Err(e) => {
+                log::error!("Serializing live update: {}", e);
+                continue;
+            }
+        };
+        if outgoing.send(Message::text(text)).await.is_err() {
+            break;
+        }
+    }
+    let _ = outgoing.close().await;
+}
@@ -0,0 +1,47 @@
+use shared::{ForecastPoint, InventoryForecast, InventoryItem, Quantity, Task, TaskStatus};
+use std::collections::{HashMap, HashSet};
+
+// Walks every task whose materials have not been consumed yet, earliest start first, and
+// subtracts its materials (converted to the stock unit) from a running copy of the stock.
+// Materials missing from the inventory start at zero, so the first task needing them shows up
+// as a shortage.
+pub fn project(tasks: &[Task], inventory: &[InventoryItem]) -> InventoryForecast {
+    let mut pending: Vec<&Task> = tasks.iter()
+        .filter(|t| matches!(t.status, TaskStatus::Planned | TaskStatus::InProgress))
+        .collect();
+    pending.sort_by(|a, b| a.start_time.cmp(&b.start_time).then_with(|| a.id.cmp(&b.id)));
+
+    let mut stock: HashMap<&str, Quantity> = inventory.iter()
+        .map(|i| (i.name.as_str(), i.quantity.clone()))
+        .collect();
+    let mut forecast = InventoryForecast::default();
+    let mut short: HashSet<String> = HashSet::new();
+
+    for task in pending {
+        let mut materials: Vec<(&String, &Quantity)> = task.materials.iter().collect();
+        materials.sort_by(|a, b| a.0.cmp(b.0));
+        for (name, required) in materials {
+            let level = stock.entry(name.as_str()).or_insert_with(|| Quantity::new(0.0, required.unit.clone()));
+            let Some(next) = level.checked_sub(required) else {
+                forecast.warnings.push(format!(
+                    "{} ({}): task uses '{}' but stock is in '{}'", name, task.id, required.unit, level.unit
+                ));
+                continue;
+            };
+            *level = next.settled();
+
+            let point = ForecastPoint {
+                task_id: task.id.clone(),
+                start_time: task.start_time,
+                material: name.clone(),
+                required: required.clone(),
+                projected: level.clone(),
+            };
+            if point.projected.is_short() && short.insert(name.clone()) {
+                forecast.shortages.push(point.clone());
+            }
+            forecast.points.push(point);
+        }
+    }
+    forecast
+}
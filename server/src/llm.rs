@@ -1,39 +1,153 @@
+use async_trait::async_trait;
+use chrono::{Duration, DurationRound, Utc};
 use shared::{Task, ScheduleSuggestion};
 use serde_json::json;
-use std::env;
-
-pub async fn suggest_time_slot(tasks: Vec<Task>, requirement: String) -> Result<ScheduleSuggestion, String> {
-    let api_key = env::var("GOOGLE_API_KEY").map_err(|_| "API Key not set")?;
-    let url = format!(
-        "https://generativelanguage.googleapis.com/v1beta/models/gemini-1.5-flash:generateContent?key={}", 
-        api_key
-    );
-
-    // Prompt engineering
-    let task_summary = serde_json::to_string(&tasks).unwrap_or_default();
-    let prompt = format!(
-        "You are a scheduling assistant. Here are existing tasks: {}. 
-        User wants to schedule: '{}'. 
-        Suggest a start time (ISO 8601 format) that does not overlap and a brief reason. 
+use crate::config::LlmConfig;
+use std::sync::Arc;
+
+// Anything that can turn the current schedule plus a free-text requirement into a suggestion.
+#[async_trait]
+pub trait SchedulingModel: Send + Sync {
+    fn name(&self) -> &'static str;
+    async fn suggest_time_slot(&self, tasks: Vec<Task>, requirement: String) -> Result<ScheduleSuggestion, String>;
+}
+
+// Builds the provider named in [llm] (gemini | openai | stub).
+pub fn from_config(config: &LlmConfig) -> Result<Arc<dyn SchedulingModel>, String> {
+    match config.provider.as_str() {
+        "gemini" => Ok(Arc::new(GeminiModel {
+            api_key: config.api_key.clone(),
+            model: config.model.clone().unwrap_or_else(|| "gemini-1.5-flash".to_string()),
+        })),
+        "openai" => Ok(Arc::new(OpenAiCompatModel {
+            // llama.cpp's server listens on :8080, Ollama exposes the same API under :11434/v1
+            base_url: config.base_url.clone().unwrap_or_else(|| "http://localhost:8080/v1".to_string()),
+            model: config.model.clone().unwrap_or_else(|| "local".to_string()),
+            api_key: config.api_key.clone(),
+        })),
+        "stub" => Ok(Arc::new(StubModel)),
+        other => Err(format!("Unknown LLM provider '{}', expected gemini, openai or stub", other)),
+    }
+}
+
+fn build_prompt(tasks: &[Task], requirement: &str) -> String {
+    let task_summary = serde_json::to_string(tasks).unwrap_or_default();
+    format!(
+        "You are a scheduling assistant. Here are existing tasks: {}.
+        User wants to schedule: '{}'.
+        Suggest a start time (ISO 8601 format) that does not overlap and a brief reason.
         Return ONLY valid JSON format: {{ \"suggested_start_time\": \"...\", \"reason\": \"...\" }}",
         task_summary, requirement
-    );
-
-    let client = reqwest::Client::new();
-    let res = client.post(&url)
-        .json(&json!({ "contents": [{ "parts": [{ "text": prompt }] }] }))
-        .send()
-        .await
-        .map_err(|e| e.to_string())?;
-
-    let body: serde_json::Value = res.json().await.map_err(|e| e.to_string())?;
-    
-    // Extract JSON from Gemini response (simplified parsing)
-    let text = body["candidates"][0]["content"]["parts"][0]["text"]
-        .as_str().ok_or("No content")?;
-    
+    )
+}
+
+fn parse_suggestion(text: &str) -> Result<ScheduleSuggestion, String> {
     // Clean markdown code blocks if present
     let clean_json = text.replace("```json", "").replace("```", "");
-    
-    serde_json::from_str(&clean_json).map_err(|e| format!("Parse error: {}", e))
-}
\ No newline at end of file
+    serde_json::from_str(clean_json.trim()).map_err(|e| format!("Parse error: {}", e))
+}
+
+pub struct GeminiModel {
+    api_key: Option<String>,
+    model: String,
+}
+
+#[async_trait]
+impl SchedulingModel for GeminiModel {
+    fn name(&self) -> &'static str {
+        "gemini"
+    }
+
+    async fn suggest_time_slot(&self, tasks: Vec<Task>, requirement: String) -> Result<ScheduleSuggestion, String> {
+        let api_key = self.api_key.as_ref().ok_or("API Key not set")?;
+        let url = format!(
+            "https://generativelanguage.googleapis.com/v1beta/models/{}:generateContent?key={}",
+            self.model, api_key
+        );
+
+        let prompt = build_prompt(&tasks, &requirement);
+        let client = reqwest::Client::new();
+        let res = client.post(&url)
+            .json(&json!({ "contents": [{ "parts": [{ "text": prompt }] }] }))
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+
+        let body: serde_json::Value = res.json().await.map_err(|e| e.to_string())?;
+
+        // Extract JSON from Gemini response (simplified parsing)
+        let text = body["candidates"][0]["content"]["parts"][0]["text"]
+            .as_str().ok_or("No content")?;
+
+        parse_suggestion(text)
+    }
+}
+
+// Any server speaking the OpenAI chat completions API (llama.cpp, Ollama, vLLM...).
+pub struct OpenAiCompatModel {
+    base_url: String,
+    model: String,
+    api_key: Option<String>,
+}
+
+#[async_trait]
+impl SchedulingModel for OpenAiCompatModel {
+    fn name(&self) -> &'static str {
+        "openai"
+    }
+
+    async fn suggest_time_slot(&self, tasks: Vec<Task>, requirement: String) -> Result<ScheduleSuggestion, String> {
+        let url = format!("{}/chat/completions", self.base_url.trim_end_matches('/'));
+        let prompt = build_prompt(&tasks, &requirement);
+
+        let client = reqwest::Client::new();
+        let mut req = client.post(&url).json(&json!({
+            "model": self.model,
+            "messages": [{ "role": "user", "content": prompt }],
+            "temperature": 0,
+        }));
+        if let Some(key) = &self.api_key {
+            req = req.bearer_auth(key);
+        }
+        let res = req.send().await.map_err(|e| e.to_string())?;
+
+        let body: serde_json::Value = res.json().await.map_err(|e| e.to_string())?;
+        let text = body["choices"][0]["message"]["content"]
+            .as_str().ok_or("No content")?;
+
+        parse_suggestion(text)
+    }
+}
+
+// Offline provider: always proposes the first moment after the last scheduled task ends,
+// or the next full hour when the schedule is empty. Same input, same answer.
+pub struct StubModel;
+
+#[async_trait]
+impl SchedulingModel for StubModel {
+    fn name(&self) -> &'static str {
+        "stub"
+    }
+
+    async fn suggest_time_slot(&self, tasks: Vec<Task>, _requirement: String) -> Result<ScheduleSuggestion, String> {
+        let last_end = tasks.iter()
+            .map(|t| t.start_time + Duration::minutes(t.expected_duration_minutes))
+            .max();
+
+        let suggestion = match last_end {
+            Some(end) => ScheduleSuggestion {
+                suggested_start_time: end,
+                reason: "Offline stub: first slot after the last scheduled task".to_string(),
+            },
+            None => {
+                let next_hour = Utc::now().duration_trunc(Duration::hours(1)).map_err(|e| e.to_string())?
+                    + Duration::hours(1);
+                ScheduleSuggestion {
+                    suggested_start_time: next_hour,
+                    reason: "Offline stub: schedule is empty, next full hour".to_string(),
+                }
+            }
+        };
+        Ok(suggestion)
+    }
+}
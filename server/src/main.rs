@@ -1,113 +1,1388 @@
+mod auth;
+mod config;
 mod db;
+mod error;
+mod events;
+mod forecast;
 mod llm;
+mod repository;
+mod scheduler;
 
 use warp::Filter;
-use shared::{Task, InventoryItem};
+use shared::{ApiError, AuditEntity, AuditEntry, ErrorCode, FieldError, Job, JobView, NewJob, LoginRequest, LoginResponse, MAX_TASK_MINUTES, NewUser, Role, Task, TaskDependency, TaskStatus, InventoryItem, Machine, Quantity, ScheduleSuggestion, TaskPreset, Worker};
+use std::collections::HashMap;
+use chrono::{DateTime, Duration, Utc};
+use warp::hyper::body::Bytes;
+use std::convert::Infallible;
+use std::fmt::Display;
 use std::sync::Arc;
-use db::DbStore;
+use serde::Deserialize;
+use warp::Reply;
+use db::{AdjustError, ConsumeError, CreateUserError, DbStore, DeleteTaskError, RestoreError, SaveError};
+use repository::{DbError, Scan};
+use llm::SchedulingModel;
+use scheduler::WorkingHours;
+use config::Config;
+use error::{error_reply, internal, not_found};
+use auth::{Session, StoredUser, TokenSigner};
 
 #[tokio::main]
 async fn main() {
     dotenv::dotenv().ok();
-    
+
+    let config = match Config::load() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("Configuration error: {}", e);
+            std::process::exit(1);
+        }
+    };
+    env_logger::Builder::new()
+        .filter_level(config.log_level().expect("validated in Config::load"))
+        .init();
+
     // Initialize DB
-    let db = Arc::new(DbStore::new("./_data_rocksdb"));
+    let db = Arc::new(DbStore::open(&config.storage.data_dir).expect("Failed to open RocksDB"));
+    bootstrap_user(&db, &config.auth);
+
+    let signer = Arc::new(TokenSigner::new(&config.auth));
+    let session = auth::authenticated(signer.clone(), db.clone());
+    let ws_session = auth::authenticated_query(signer.clone(), db.clone());
+    let signer_filter = warp::any().map(move || signer.clone());
     let db_filter = warp::any().map(move || db.clone());
-    
-    let inv_db = Arc::new(DbStore::new("./_data_rocksdb_inventory"));
-    let inv_db_filter = warp::any().map(move || inv_db.clone());
+
+    let model = llm::from_config(&config.llm).expect("Invalid LLM configuration");
+    log::info!("Scheduling model: {}", model.name());
+    let model_filter = warp::any().map(move || model.clone());
+
+    let hours = config.working_hours().expect("validated in Config::load");
+    let hours_filter = warp::any().map(move || hours);
 
     // CORS for frontend
-    let cors = warp::cors().allow_any_origin()
-        .allow_headers(vec!["content-type"])
-        .allow_methods(vec!["GET", "POST", "DELETE"]);
+    let cors = warp::cors();
+    let cors = if config.allows_any_origin() {
+        cors.allow_any_origin()
+    } else {
+        cors.allow_origins(config.server.allowed_origins.iter().map(String::as_str))
+    };
+    let cors = cors
+        .allow_headers(vec!["content-type", "authorization"])
+        .allow_methods(vec!["GET", "POST", "PUT", "PATCH", "DELETE"]);
+
+    // Auth Routes
+    let login = warp::post()
+        .and(warp::path!("auth" / "login"))
+        .and(warp::body::json())
+        .and(db_filter.clone())
+        .and(signer_filter)
+        .and_then(handle_login);
+
+    let me = warp::get()
+        .and(warp::path!("auth" / "me"))
+        .and(session.clone())
+        .map(|session: Session| warp::reply::json(&session.user.info()));
+
+    let get_users = warp::get()
+        .and(warp::path("users"))
+        .and(warp::path::end())
+        .and(auth::allow(session.clone(), Role::can_administer))
+        .and(db_filter.clone())
+        .map(|db: Arc<DbStore>| scan_reply(db.users.scan(), |records| {
+            warp::reply::json(&records.iter().map(|(_, user)| user.info()).collect::<Vec<_>>())
+        }));
+
+    let add_user = warp::post()
+        .and(warp::path("users"))
+        .and(warp::path::end())
+        .and(auth::allow(session.clone(), Role::can_administer))
+        .and(warp::body::json())
+        .and(db_filter.clone())
+        .and_then(handle_add_user);
+
+    // Routes. Everyone signed in may read; writes are narrowed to the roles allowed to make them.
+    let any_role = auth::allow(session.clone(), |_| true);
 
-    // Routes
     let get_tasks = warp::get()
         .and(warp::path("tasks"))
+        .and(warp::path::end())
+        .and(any_role.clone())
+        .and(warp::query::<TaskQuery>())
         .and(db_filter.clone())
-        .map(|db: Arc<DbStore>| warp::reply::json(&db.get_all_tasks()));
+        .map(handle_get_tasks);
 
-    let add_task = warp::post()
-        .and(warp::path("tasks"))
-        .and(warp::body::json())
+    let get_task = warp::get()
+        .and(warp::path!("tasks" / String))
+        .and(any_role.clone())
         .and(db_filter.clone())
-        .map(|task: Task, db: Arc<DbStore>| {
-            match db.add_task(task) {
-                Ok(_) => warp::reply::with_status("Added", warp::http::StatusCode::CREATED),
-                Err(_) => warp::reply::with_status("Error", warp::http::StatusCode::INTERNAL_SERVER_ERROR),
-            }
+        .map(|id: String, db: Arc<DbStore>| match load_task(&db, &id) {
+            Ok(task) => warp::reply::json(&task).into_response(),
+            Err(e) => error::reply(e),
         });
 
+    let create_task = warp::post()
+        .and(warp::path("tasks"))
+        .and(warp::path::end())
+        .and(auth::authorized(session.clone(), Role::can_plan))
+        .and(warp::query::<WriteOptions>())
+        .and(warp::body::bytes())
+        .and(db_filter.clone())
+        .map(handle_create_task);
+
+    let update_task = warp::put()
+        .and(warp::path!("tasks" / String))
+        .and(auth::authorized(session.clone(), Role::can_plan))
+        .and(warp::query::<WriteOptions>())
+        .and(warp::body::bytes())
+        .and(db_filter.clone())
+        .map(handle_update_task);
+
     let delete_task = warp::delete()
         .and(warp::path!("tasks" / String))
+        .and(auth::authorized(session.clone(), Role::can_plan))
         .and(db_filter.clone())
-        .map(|id: String, db: Arc<DbStore>| {
-            match db.delete_task(&id) {
-                Ok(_) => warp::reply::with_status("Deleted", warp::http::StatusCode::OK),
-                Err(_) => warp::reply::with_status("Error", warp::http::StatusCode::INTERNAL_SERVER_ERROR),
+        .map(|id: String, session: Session, db: Arc<DbStore>| {
+            match db.delete_task(&session.user.username, &id) {
+                Ok(_) => warp::reply::with_status("Deleted", warp::http::StatusCode::OK).into_response(),
+                Err(DeleteTaskError::NotFound) => error::reply(not_found(format!("Task {}", id))),
+                Err(DeleteTaskError::Consumed) => error_reply(
+                    ErrorCode::InvalidTransition,
+                    format!("Task {} has used up stock; reopen it first to put its materials back", id),
+                ),
+                Err(DeleteTaskError::Db(e)) => error::reply(internal(format!("Deleting task {}", id), e)),
             }
         });
 
+    let start_task = warp::post()
+        .and(warp::path!("tasks" / String / "start"))
+        .and(auth::authorized(session.clone(), Role::can_run_tasks))
+        .and(warp::body::bytes())
+        .and(db_filter.clone())
+        .map(handle_start_task);
+
+    let finish_task = warp::post()
+        .and(warp::path!("tasks" / String / "finish"))
+        .and(auth::authorized(session.clone(), Role::can_run_tasks))
+        .and(warp::body::bytes())
+        .and(db_filter.clone())
+        .map(handle_finish_task);
+
+    let reopen_task = warp::post()
+        .and(warp::path!("tasks" / String / "reopen"))
+        .and(auth::authorized(session.clone(), Role::can_plan))
+        .and(db_filter.clone())
+        .map(handle_reopen_task);
+
+    let cancel_task = warp::post()
+        .and(warp::path!("tasks" / String / "cancel"))
+        .and(auth::authorized(session.clone(), Role::can_plan))
+        .and(db_filter.clone())
+        .map(handle_cancel_task);
+
     let llm_suggest = warp::post()
         .and(warp::path("suggest"))
-        .and(warp::body::json()) // Expects { "requirement": "..." }
+        .and(auth::allow(session.clone(), Role::can_plan))
+        .and(warp::body::json()) // Expects { "requirement": "...", "user_id"?: "...", "duration_minutes"?: 60 }
         .and(db_filter.clone())
+        .and(model_filter.clone())
+        .and(hours_filter)
         .and_then(handle_suggestion);
 
     // Inventory Routes
     let get_inventory = warp::get()
         .and(warp::path("inventory"))
-        .and(inv_db_filter.clone())
-        .map(|db: Arc<DbStore>| {
-            // Reusing get_all_tasks logic since it just iterates all values, 
-            // but casting to InventoryItem. 
-            // Note: In a real app, we'd make DbStore generic or use different methods.
-            // Here we assume the separate DB folder ensures type safety.
-            let items: Vec<InventoryItem> = db.get_all_tasks().into_iter().map(|t| {
-                // Hack: We are using the same DB struct which expects Task. 
-                // But DbStore::get_all_tasks deserializes as Task. 
-                // We need to modify DbStore or just use a generic get_all.
-                // For this specific request, let's just use a raw route handler here or modify DbStore.
-                // To keep it simple without changing DbStore signature too much:
-                // We will rely on the fact that we are using a separate DB instance.
-                // However, DbStore::get_all_tasks returns Vec<Task>. 
-                // We need to modify DbStore to be generic or add get_all_inventory.
-                // Let's modify DbStore in the next file.
-                InventoryItem { name: "Error".to_string(), quantity: 0.0, unit: "".to_string()} 
-            }).collect();
-            // Actually, let's fix DbStore properly.
-            warp::reply::json(&db.get_all_inventory())
-        });
+        .and(warp::path::end())
+        .and(any_role.clone())
+        .and(db_filter.clone())
+        .map(|db: Arc<DbStore>| scan_reply(db.inventory.scan(), |records| {
+            warp::reply::json(&records.into_iter().map(|(_, item)| item).collect::<Vec<_>>())
+        }));
 
     let add_inventory = warp::post()
         .and(warp::path("inventory"))
+        .and(auth::authorized(session.clone(), Role::can_administer))
+        .and(warp::body::json())
+        .and(db_filter.clone())
+        .map(|session: Session, mut item: InventoryItem, db: Arc<DbStore>| {
+            if let Err(msg) = validate_inventory_item(&item) {
+                return error_reply(ErrorCode::InvalidRequest, msg);
+            }
+            match db.add_inventory(&session.user.username, &mut item) {
+                Ok(_) => warp::reply::with_status(warp::reply::json(&item), warp::http::StatusCode::CREATED).into_response(),
+                Err(SaveError::Stale(current)) => stale_reply(format!("Inventory item '{}'", item.name), current),
+                Err(SaveError::Db(e)) => error::reply(internal(format!("Saving inventory item '{}'", item.name), e)),
+            }
+        });
+
+    let get_inventory_item = warp::get()
+        .and(warp::path!("inventory" / String))
+        .and(any_role.clone())
+        .and(db_filter.clone())
+        .map(|name: String, db: Arc<DbStore>| {
+            let name = decode_segment(&name);
+            match db.get_inventory(&name) {
+                Ok(Some(item)) => warp::reply::json(&item).into_response(),
+                Ok(None) => error::reply(not_found(format!("Inventory item '{}'", name))),
+                Err(e) => error::reply(internal(format!("Loading inventory item '{}'", name), e)),
+            }
+        });
+
+    let replace_inventory = warp::put()
+        .and(warp::path!("inventory" / String))
+        .and(auth::authorized(session.clone(), Role::can_administer))
+        .and(warp::body::json())
+        .and(db_filter.clone())
+        .map(handle_replace_inventory);
+
+    let adjust_inventory = warp::patch()
+        .and(warp::path!("inventory" / String))
+        .and(auth::authorized(session.clone(), Role::can_administer))
+        .and(warp::body::json())
+        .and(db_filter.clone())
+        .map(handle_adjust_inventory);
+
+    let delete_inventory = warp::delete()
+        .and(warp::path!("inventory" / String))
+        .and(auth::authorized(session.clone(), Role::can_administer))
+        .and(db_filter.clone())
+        .map(|name: String, session: Session, db: Arc<DbStore>| {
+            let name = decode_segment(&name);
+            let result = match db.get_inventory(&name) {
+                Ok(Some(_)) => db.delete_inventory(&session.user.username, &name),
+                Ok(None) => return error::reply(not_found(format!("Inventory item '{}'", name))),
+                Err(e) => Err(e),
+            };
+            match result {
+                Ok(_) => warp::reply::with_status("Deleted", warp::http::StatusCode::OK).into_response(),
+                Err(e) => error::reply(internal(format!("Deleting inventory item '{}'", name), e)),
+            }
+        });
+
+    let get_forecast = warp::get()
+        .and(warp::path("forecast"))
+        .and(warp::path::end())
+        .and(any_role.clone())
+        .and(db_filter.clone())
+        .map(|db: Arc<DbStore>| {
+            match (db.get_all_tasks(), db.get_all_inventory()) {
+                (Ok(tasks), Ok(inventory)) => warp::reply::json(&forecast::project(&tasks, &inventory)).into_response(),
+                (Err(e), _) | (_, Err(e)) => error::reply(internal("Building forecast", e)),
+            }
+        });
+
+    // Preset Routes
+    let get_presets = warp::get()
+        .and(warp::path("presets"))
+        .and(warp::path::end())
+        .and(any_role.clone())
+        .and(db_filter.clone())
+        .map(|db: Arc<DbStore>| scan_reply(db.presets.scan(), |records| {
+            warp::reply::json(&records.into_iter().collect::<HashMap<_, _>>())
+        }));
+
+    let get_preset = warp::get()
+        .and(warp::path!("presets" / String))
+        .and(any_role.clone())
+        .and(db_filter.clone())
+        .map(|op_id: String, db: Arc<DbStore>| {
+            let op_id = decode_segment(&op_id);
+            match db.get_preset(&op_id) {
+                Ok(Some(preset)) => warp::reply::json(&preset).into_response(),
+                Ok(None) => error::reply(not_found(format!("Preset '{}'", op_id))),
+                Err(e) => error::reply(internal(format!("Loading preset '{}'", op_id), e)),
+            }
+        });
+
+    let put_preset = warp::put()
+        .and(warp::path!("presets" / String))
+        .and(auth::authorized(session.clone(), Role::can_administer))
+        .and(warp::body::json())
+        .and(db_filter.clone())
+        .map(|op_id: String, session: Session, preset: TaskPreset, db: Arc<DbStore>| {
+            let op_id = decode_segment(&op_id);
+            if op_id.trim().is_empty() {
+                return error_reply(ErrorCode::InvalidRequest, "Operation id must not be empty");
+            }
+            let errors = preset.validate();
+            if !errors.is_empty() {
+                return error::reply(ApiError::invalid_fields(errors));
+            }
+            match db.add_preset(&session.user.username, &op_id, &preset) {
+                Ok(_) => warp::reply::with_status("Saved", warp::http::StatusCode::OK).into_response(),
+                Err(e) => error::reply(internal(format!("Saving preset '{}'", op_id), e)),
+            }
+        });
+
+    let delete_preset = warp::delete()
+        .and(warp::path!("presets" / String))
+        .and(auth::authorized(session.clone(), Role::can_administer))
+        .and(db_filter.clone())
+        .map(|op_id: String, session: Session, db: Arc<DbStore>| {
+            let op_id = decode_segment(&op_id);
+            match db.delete_preset(&session.user.username, &op_id) {
+                Ok(_) => warp::reply::with_status("Deleted", warp::http::StatusCode::OK).into_response(),
+                Err(e) => error::reply(internal(format!("Deleting preset '{}'", op_id), e)),
+            }
+        });
+
+    // One-time migration of presets that used to live in the browser's LocalStorage
+    let import_presets = warp::post()
+        .and(warp::path!("presets" / "import"))
+        .and(auth::authorized(session.clone(), Role::can_administer))
         .and(warp::body::json())
-        .and(inv_db_filter.clone())
-        .map(|item: InventoryItem, db: Arc<DbStore>| {
-            match db.add_inventory(item) {
-                Ok(_) => warp::reply::with_status("Added", warp::http::StatusCode::CREATED),
-                Err(_) => warp::reply::with_status("Error", warp::http::StatusCode::INTERNAL_SERVER_ERROR),
+        .and(db_filter.clone())
+        .map(handle_import_presets);
+
+    // Worker Routes
+    let get_workers = warp::get()
+        .and(warp::path("workers"))
+        .and(warp::path::end())
+        .and(any_role.clone())
+        .and(db_filter.clone())
+        .map(|db: Arc<DbStore>| scan_reply(db.workers.scan(), |records| {
+            warp::reply::json(&records.into_iter().map(|(_, worker)| worker).collect::<Vec<_>>())
+        }));
+
+    let get_worker = warp::get()
+        .and(warp::path!("workers" / String))
+        .and(any_role.clone())
+        .and(db_filter.clone())
+        .map(|id: String, db: Arc<DbStore>| {
+            let id = decode_segment(&id);
+            match db.get_worker(&id) {
+                Ok(Some(worker)) => warp::reply::json(&worker).into_response(),
+                Ok(None) => error::reply(not_found(format!("Worker '{}'", id))),
+                Err(e) => error::reply(internal(format!("Loading worker '{}'", id), e)),
+            }
+        });
+
+    let create_worker = warp::post()
+        .and(warp::path("workers"))
+        .and(warp::path::end())
+        .and(auth::authorized(session.clone(), Role::can_administer))
+        .and(warp::body::json())
+        .and(db_filter.clone())
+        .map(handle_create_worker);
+
+    let update_worker = warp::put()
+        .and(warp::path!("workers" / String))
+        .and(auth::authorized(session.clone(), Role::can_administer))
+        .and(warp::body::json())
+        .and(db_filter.clone())
+        .map(handle_update_worker);
+
+    let delete_worker = warp::delete()
+        .and(warp::path!("workers" / String))
+        .and(auth::authorized(session.clone(), Role::can_administer))
+        .and(db_filter.clone())
+        .map(|id: String, session: Session, db: Arc<DbStore>| {
+            let id = decode_segment(&id);
+            match db.delete_worker(&session.user.username, &id) {
+                Ok(_) => warp::reply::with_status("Deleted", warp::http::StatusCode::OK).into_response(),
+                Err(e) => error::reply(internal(format!("Deleting worker '{}'", id), e)),
+            }
+        });
+
+    // Machine Routes
+    let get_machines = warp::get()
+        .and(warp::path("machines"))
+        .and(warp::path::end())
+        .and(any_role.clone())
+        .and(db_filter.clone())
+        .map(|db: Arc<DbStore>| scan_reply(db.machines.scan(), |records| {
+            warp::reply::json(&records.into_iter().map(|(_, machine)| machine).collect::<Vec<_>>())
+        }));
+
+    let get_machine = warp::get()
+        .and(warp::path!("machines" / String))
+        .and(any_role.clone())
+        .and(db_filter.clone())
+        .map(|id: String, db: Arc<DbStore>| {
+            let id = decode_segment(&id);
+            match db.get_machine(&id) {
+                Ok(Some(machine)) => warp::reply::json(&machine).into_response(),
+                Ok(None) => error::reply(not_found(format!("Machine '{}'", id))),
+                Err(e) => error::reply(internal(format!("Loading machine '{}'", id), e)),
+            }
+        });
+
+    let create_machine = warp::post()
+        .and(warp::path("machines"))
+        .and(warp::path::end())
+        .and(auth::authorized(session.clone(), Role::can_administer))
+        .and(warp::body::json())
+        .and(db_filter.clone())
+        .map(handle_create_machine);
+
+    let update_machine = warp::put()
+        .and(warp::path!("machines" / String))
+        .and(auth::authorized(session.clone(), Role::can_administer))
+        .and(warp::body::json())
+        .and(db_filter.clone())
+        .map(handle_update_machine);
+
+    let delete_machine = warp::delete()
+        .and(warp::path!("machines" / String))
+        .and(auth::authorized(session.clone(), Role::can_administer))
+        .and(db_filter.clone())
+        .map(|id: String, session: Session, db: Arc<DbStore>| {
+            let id = decode_segment(&id);
+            match db.delete_machine(&session.user.username, &id) {
+                Ok(_) => warp::reply::with_status("Deleted", warp::http::StatusCode::OK).into_response(),
+                Err(e) => error::reply(internal(format!("Deleting machine '{}'", id), e)),
             }
         });
 
-    let routes = get_tasks.or(add_task).or(delete_task).or(llm_suggest)
-        .or(get_inventory).or(add_inventory)
-        .with(cors);
+    // Job Routes
+    let get_jobs = warp::get()
+        .and(warp::path("jobs"))
+        .and(warp::path::end())
+        .and(any_role.clone())
+        .and(db_filter.clone())
+        .map(|db: Arc<DbStore>| {
+            let jobs = match db.jobs.scan() {
+                Ok(scan) => scan.reported(),
+                Err(e) => return error::reply(internal("Loading jobs", e)),
+            };
+            let views: Result<Vec<JobView>, ApiError> = jobs.into_iter().map(|(_, job)| view_job(&db, job)).collect();
+            match views {
+                Ok(views) => warp::reply::json(&views).into_response(),
+                Err(e) => error::reply(e),
+            }
+        });
 
-    println!("Server started at http://localhost:8081");
-    warp::serve(routes).run(([127, 0, 0, 1], 8081)).await;
+    let get_job = warp::get()
+        .and(warp::path!("jobs" / String))
+        .and(any_role.clone())
+        .and(db_filter.clone())
+        .map(|id: String, db: Arc<DbStore>| {
+            match load_job(&db, &id).and_then(|job| view_job(&db, job)) {
+                Ok(view) => warp::reply::json(&view).into_response(),
+                Err(e) => error::reply(e),
+            }
+        });
+
+    let create_job = warp::post()
+        .and(warp::path("jobs"))
+        .and(warp::path::end())
+        .and(auth::authorized(session.clone(), Role::can_plan))
+        .and(warp::query::<WriteOptions>())
+        .and(warp::body::json())
+        .and(db_filter.clone())
+        .map(handle_create_job);
+
+    let update_job = warp::put()
+        .and(warp::path!("jobs" / String))
+        .and(auth::authorized(session.clone(), Role::can_plan))
+        .and(warp::body::json())
+        .and(db_filter.clone())
+        .map(handle_update_job);
+
+    let delete_job = warp::delete()
+        .and(warp::path!("jobs" / String))
+        .and(auth::authorized(session.clone(), Role::can_plan))
+        .and(db_filter.clone())
+        .map(|id: String, session: Session, db: Arc<DbStore>| {
+            let id = decode_segment(&id);
+            match db.delete_job(&session.user.username, &id) {
+                Ok(_) => warp::reply::with_status("Deleted", warp::http::StatusCode::OK).into_response(),
+                Err(DeleteTaskError::NotFound) => error::reply(not_found(format!("Job {}", id))),
+                Err(DeleteTaskError::Consumed) => error_reply(
+                    ErrorCode::InvalidTransition,
+                    format!("Job {} has finished tasks that used up stock; reopen them first to put their materials back", id),
+                ),
+                Err(DeleteTaskError::Db(e)) => error::reply(internal(format!("Deleting job {}", id), e)),
+            }
+        });
+
+    // Audit Routes
+    let get_audit = warp::get()
+        .and(warp::path("audit"))
+        .and(warp::path::end())
+        .and(any_role.clone())
+        .and(warp::query::<AuditQuery>())
+        .and(db_filter.clone())
+        .map(handle_get_audit);
+
+    // Live updates: every committed change, pushed over a WebSocket
+    let live_events = warp::path("events")
+        .and(warp::path::end())
+        .and(ws_session)
+        .and(warp::ws())
+        .and(db_filter.clone())
+        .map(|_: Session, ws: warp::ws::Ws, db: Arc<DbStore>| {
+            let changes = db.subscribe();
+            ws.on_upgrade(move |socket| events::forward(socket, changes))
+        });
+
+    let routes = login.or(me).or(get_users).or(add_user)
+        .or(get_tasks).or(get_task).or(create_task).or(update_task).or(delete_task).or(llm_suggest)
+        .or(start_task).or(finish_task).or(reopen_task).or(cancel_task)
+        .or(get_inventory).or(add_inventory).or(get_inventory_item)
+        .or(replace_inventory).or(adjust_inventory).or(delete_inventory).or(get_forecast)
+        .or(get_presets).or(get_preset).or(put_preset).or(delete_preset).or(import_presets)
+        .or(get_workers).or(get_worker).or(create_worker).or(update_worker).or(delete_worker)
+        .or(get_machines).or(get_machine).or(create_machine).or(update_machine).or(delete_machine)
+        .or(get_jobs).or(get_job).or(create_job).or(update_job).or(delete_job)
+        .or(get_audit).or(live_events)
+        .recover(error::handle_rejection)
+        .with(cors)
+        .with(warp::log("server"));
+
+    let addr = config.bind_addr();
+    log::info!("Server started at http://{}", addr);
+    warp::serve(routes).run(addr).await;
+}
+
+// Creates the configured first account so a fresh install can be logged into
+fn bootstrap_user(db: &DbStore, config: &config::AuthConfig) {
+    let (Some(username), Some(password)) = (&config.bootstrap_username, &config.bootstrap_password) else {
+        return;
+    };
+    match db.users.scan() {
+        Ok(scan) if scan.records.is_empty() && scan.corrupt.is_empty() => {}
+        Ok(_) => return,
+        Err(e) => {
+            log::error!("Checking for existing users: {}", e);
+            return;
+        }
+    }
+    if let Err(e) = auth::validate_new_user(username, password) {
+        log::error!("Bootstrap user not created: {}", e);
+        return;
+    }
+    let user = match auth::hash_password(password) {
+        Ok(password_hash) => StoredUser { username: username.clone(), password_hash, created_at: Utc::now(), role: Role::Admin },
+        Err(e) => {
+            log::error!("Hashing bootstrap password: {}", e);
+            return;
+        }
+    };
+    match db.create_user(user) {
+        Ok(()) => log::info!("Created bootstrap user '{}'", username),
+        Err(CreateUserError::AlreadyExists) => {}
+        Err(CreateUserError::Db(e)) => log::error!("Creating bootstrap user '{}': {}", username, e),
+    }
+}
+
+async fn handle_login(req: LoginRequest, db: Arc<DbStore>, signer: Arc<TokenSigner>) -> Result<warp::reply::Response, Infallible> {
+    let user = match db.get_user(req.username.trim()) {
+        Ok(user) => user,
+        Err(e) => return Ok(error::reply(internal(format!("Loading user '{}'", req.username), e))),
+    };
+    // Argon2 is deliberately slow; keep it off the async workers
+    let verified = match user {
+        Some(user) => {
+            let password = req.password;
+            tokio::task::spawn_blocking(move || auth::verify_password(&password, &user.password_hash).then_some(user))
+                .await
+                .unwrap_or(None)
+        }
+        None => None,
+    };
+    let Some(user) = verified else {
+        return Ok(error_reply(ErrorCode::Unauthorized, "Invalid username or password"));
+    };
+    let (token, expires_at) = signer.issue(&user.username);
+    log::info!("User '{}' logged in", user.username);
+    Ok(warp::reply::json(&LoginResponse { token, expires_at, user: user.info() }).into_response())
+}
+
+async fn handle_add_user(new_user: NewUser, db: Arc<DbStore>) -> Result<warp::reply::Response, Infallible> {
+    let username = new_user.username.trim().to_string();
+    if let Err(msg) = auth::validate_new_user(&username, &new_user.password) {
+        return Ok(error_reply(ErrorCode::InvalidRequest, msg));
+    }
+    let password = new_user.password;
+    let hashed = tokio::task::spawn_blocking(move || auth::hash_password(&password)).await;
+    let password_hash = match hashed {
+        Ok(Ok(hash)) => hash,
+        Ok(Err(e)) => return Ok(error::reply(internal("Hashing password", e))),
+        Err(e) => return Ok(error::reply(internal("Hashing password", e))),
+    };
+    let user = StoredUser { username: username.clone(), password_hash, created_at: Utc::now(), role: new_user.role };
+    let info = user.info();
+    match db.create_user(user) {
+        Ok(()) => Ok(warp::reply::with_status(warp::reply::json(&info), warp::http::StatusCode::CREATED).into_response()),
+        Err(CreateUserError::AlreadyExists) => Ok(error_reply(ErrorCode::AlreadyExists, format!("User '{}' already exists", username))),
+        Err(CreateUserError::Db(e)) => Ok(error::reply(internal(format!("Creating user '{}'", username), e))),
+    }
+}
+
+// Replies with the decoded records of a scan. Corrupt records are logged and counted in
+// an X-Corrupt-Records header rather than silently dropped.
+fn scan_reply<T, R: Reply>(scan: Result<Scan<T>, DbError>, render: impl FnOnce(Vec<(String, T)>) -> R) -> warp::reply::Response {
+    match scan {
+        Ok(scan) => {
+            let corrupt = scan.corrupt.len();
+            let reply = render(scan.reported());
+            if corrupt > 0 {
+                warp::reply::with_header(reply, "X-Corrupt-Records", corrupt.to_string()).into_response()
+            } else {
+                reply.into_response()
+            }
+        }
+        Err(e) => error::reply(internal("Scan failed", e)),
+    }
+}
+
+// Path segments arrive percent-encoded ("Steel%20Sheet")
+fn decode_segment(raw: &str) -> String {
+    percent_encoding::percent_decode_str(raw).decode_utf8_lossy().into_owned()
+}
+
+fn validate_inventory_item(item: &InventoryItem) -> Result<(), String> {
+    if item.name.trim().is_empty() {
+        return Err("Item name must not be empty".to_string());
+    }
+    if item.quantity.unit != item.quantity.unit.trim() {
+        return Err(format!("Unit '{}' must not have surrounding whitespace", item.quantity.unit));
+    }
+    if !item.quantity.value.is_finite() || item.quantity.value < 0.0 {
+        return Err(format!("Quantity for '{}' must be a non-negative number", item.name));
+    }
+    Ok(())
+}
+
+#[derive(Deserialize)]
+struct InventoryAdjustment {
+    delta: f64,
+    // Any unit convertible to the one the item is stocked in
+    unit: String,
+}
+
+fn handle_replace_inventory(name: String, session: Session, mut item: InventoryItem, db: Arc<DbStore>) -> warp::reply::Response {
+    let name = decode_segment(&name);
+    if let Err(msg) = validate_inventory_item(&item) {
+        return error_reply(ErrorCode::InvalidRequest, msg);
+    }
+    let failed = |e: DbError| error::reply(internal(format!("Replacing inventory item '{}'", name), e));
+    match db.get_inventory(&name) {
+        Ok(Some(_)) => {}
+        Ok(None) => return error::reply(not_found(format!("Inventory item '{}'", name))),
+        Err(e) => return failed(e),
+    }
+    if item.name != name {
+        match db.get_inventory(&item.name) {
+            Ok(None) => {}
+            Ok(Some(_)) => {
+                let msg = format!("An item named '{}' already exists", item.name);
+                return error_reply(ErrorCode::AlreadyExists, msg);
+            }
+            Err(e) => return failed(e),
+        }
+    }
+    match db.replace_inventory(&session.user.username, &name, &mut item) {
+        Ok(_) => warp::reply::json(&item).into_response(),
+        Err(SaveError::Stale(current)) => stale_reply(format!("Inventory item '{}'", name), current),
+        Err(SaveError::Db(e)) => failed(e),
+    }
+}
+
+fn handle_adjust_inventory(name: String, session: Session, adj: InventoryAdjustment, db: Arc<DbStore>) -> warp::reply::Response {
+    if !adj.delta.is_finite() {
+        return error_reply(ErrorCode::InvalidRequest, "Delta must be a finite number");
+    }
+    let delta = Quantity::new(adj.delta, adj.unit.trim());
+    match db.adjust_inventory(&session.user.username, &decode_segment(&name), &delta) {
+        Ok(item) => warp::reply::json(&item).into_response(),
+        Err(AdjustError::NotFound) => error::reply(not_found(format!("Inventory item '{}'", decode_segment(&name)))),
+        Err(AdjustError::UnitMismatch { expected }) => {
+            let msg = format!("Unit mismatch: item is stored in '{}', '{}' does not convert to it", expected, delta.unit);
+            error_reply(ErrorCode::UnitMismatch, msg)
+        }
+        Err(AdjustError::WouldGoNegative { available }) => {
+            let msg = format!("Only {} available, cannot subtract {}", available, Quantity::new(-delta.value, delta.unit));
+            error_reply(ErrorCode::InsufficientStock, msg)
+        }
+        Err(AdjustError::Db(e)) => error::reply(internal(format!("Adjusting inventory item '{}'", name), e)),
+    }
+}
+
+// POST /presets/import: all or nothing, with the same checks as PUT /presets/{op_id}. Field
+// errors are keyed `{index}.{field}`, counting the operations in id order.
+fn handle_import_presets(session: Session, presets: HashMap<String, TaskPreset>, db: Arc<DbStore>) -> warp::reply::Response {
+    let mut op_ids: Vec<&String> = presets.keys().collect();
+    op_ids.sort();
+    let mut errors = Vec::new();
+    for (index, op_id) in op_ids.into_iter().enumerate() {
+        let mut problems = presets[op_id].validate();
+        if op_id.trim().is_empty() {
+            problems.insert(0, FieldError::new("operation_id", "Operation id must not be empty"));
+        }
+        errors.extend(problems.into_iter().map(|e| {
+            FieldError::new(format!("{}.{}", index, e.field), format!("{}: {}", op_id, e.message))
+        }));
+    }
+    if !errors.is_empty() {
+        return error::reply(ApiError::invalid_fields(errors));
+    }
+    match db.import_presets(&session.user.username, presets) {
+        Ok(imported) => warp::reply::json(&imported).into_response(),
+        Err(e) => error::reply(internal("Importing presets", e)),
+    }
+}
+
+fn clean_worker(worker: &mut Worker) -> Result<(), ApiError> {
+    worker.id = worker.id.trim().to_string();
+    worker.display_name = worker.display_name.trim().to_string();
+    for skill in &mut worker.skills {
+        *skill = skill.trim().to_string();
+    }
+    let errors = worker.validate();
+    if errors.is_empty() { Ok(()) } else { Err(ApiError::invalid_fields(errors)) }
+}
+
+fn handle_create_worker(session: Session, mut worker: Worker, db: Arc<DbStore>) -> warp::reply::Response {
+    if let Err(e) = clean_worker(&mut worker) {
+        return error::reply(e);
+    }
+    match db.get_worker(&worker.id) {
+        Ok(None) => {}
+        Ok(Some(_)) => return error_reply(ErrorCode::AlreadyExists, format!("A worker with id '{}' already exists", worker.id)),
+        Err(e) => return error::reply(internal(format!("Loading worker '{}'", worker.id), e)),
+    }
+    match db.put_worker(&session.user.username, &worker) {
+        Ok(_) => warp::reply::with_status(warp::reply::json(&worker), warp::http::StatusCode::CREATED).into_response(),
+        Err(e) => error::reply(internal(format!("Saving worker '{}'", worker.id), e)),
+    }
+}
+
+// The id comes from the path and cannot be changed
+fn handle_update_worker(id: String, session: Session, mut worker: Worker, db: Arc<DbStore>) -> warp::reply::Response {
+    worker.id = decode_segment(&id);
+    if let Err(e) = clean_worker(&mut worker) {
+        return error::reply(e);
+    }
+    match db.get_worker(&worker.id) {
+        Ok(Some(_)) => {}
+        Ok(None) => return error::reply(not_found(format!("Worker '{}'", worker.id))),
+        Err(e) => return error::reply(internal(format!("Loading worker '{}'", worker.id), e)),
+    }
+    match db.put_worker(&session.user.username, &worker) {
+        Ok(_) => warp::reply::json(&worker).into_response(),
+        Err(e) => error::reply(internal(format!("Saving worker '{}'", worker.id), e)),
+    }
+}
+
+fn clean_machine(machine: &mut Machine) -> Result<(), ApiError> {
+    machine.id = machine.id.trim().to_string();
+    machine.name = machine.name.trim().to_string();
+    let errors = machine.validate();
+    if errors.is_empty() { Ok(()) } else { Err(ApiError::invalid_fields(errors)) }
+}
+
+fn handle_create_machine(session: Session, mut machine: Machine, db: Arc<DbStore>) -> warp::reply::Response {
+    if let Err(e) = clean_machine(&mut machine) {
+        return error::reply(e);
+    }
+    match db.get_machine(&machine.id) {
+        Ok(None) => {}
+        Ok(Some(_)) => return error_reply(ErrorCode::AlreadyExists, format!("A machine with id '{}' already exists", machine.id)),
+        Err(e) => return error::reply(internal(format!("Loading machine '{}'", machine.id), e)),
+    }
+    match db.put_machine(&session.user.username, &machine) {
+        Ok(_) => warp::reply::with_status(warp::reply::json(&machine), warp::http::StatusCode::CREATED).into_response(),
+        Err(e) => error::reply(internal(format!("Saving machine '{}'", machine.id), e)),
+    }
+}
+
+// The id comes from the path and cannot be changed
+fn handle_update_machine(id: String, session: Session, mut machine: Machine, db: Arc<DbStore>) -> warp::reply::Response {
+    machine.id = decode_segment(&id);
+    if let Err(e) = clean_machine(&mut machine) {
+        return error::reply(e);
+    }
+    match db.get_machine(&machine.id) {
+        Ok(Some(_)) => {}
+        Ok(None) => return error::reply(not_found(format!("Machine '{}'", machine.id))),
+        Err(e) => return error::reply(internal(format!("Loading machine '{}'", machine.id), e)),
+    }
+    match db.put_machine(&session.user.username, &machine) {
+        Ok(_) => warp::reply::json(&machine).into_response(),
+        Err(e) => error::reply(internal(format!("Saving machine '{}'", machine.id), e)),
+    }
+}
+
+// Optional body of /start and /finish; an empty POST means "now"
+#[derive(Deserialize, Default)]
+struct ExecutionUpdate {
+    at: Option<DateTime<Utc>>,
+    actual_duration_minutes: Option<i64>,
+}
+
+fn parse_execution_update(body: &[u8]) -> Result<ExecutionUpdate, ApiError> {
+    if body.iter().all(|b| b.is_ascii_whitespace()) {
+        return Ok(ExecutionUpdate::default());
+    }
+    serde_json::from_slice(body).map_err(|e| ApiError::new(ErrorCode::InvalidRequest, format!("Invalid body: {}", e)))
+}
+
+fn load_task(db: &DbStore, id: &str) -> Result<Task, ApiError> {
+    match db.get_task(id) {
+        Ok(Some(task)) => Ok(task),
+        Ok(None) => Err(not_found(format!("Task {}", id))),
+        Err(e) => Err(internal(format!("Loading task {}", id), e)),
+    }
+}
+
+// Operators may only run the tasks assigned to them
+fn check_assignee(session: &Session, task: &Task) -> Result<(), ApiError> {
+    if session.user.role.can_execute(task, &session.user.username) {
+        Ok(())
+    } else {
+        Err(ApiError::new(ErrorCode::Forbidden, format!("Task {} is assigned to {}, not you", task.id, task.user_id)))
+    }
+}
+
+fn invalid_transition(task: &Task, action: &str) -> warp::reply::Response {
+    let msg = format!("Cannot {} a task that is {}", action, task.status.label().to_lowercase());
+    error_reply(ErrorCode::InvalidTransition, msg)
+}
+
+// 409 for a write based on a version of `what` that has since been replaced or deleted
+fn stale_reply(what: impl Display, current: Option<u64>) -> warp::reply::Response {
+    let msg = match current {
+        Some(version) => format!("{} was changed by someone else (now at version {}), reload it and try again", what, version),
+        None => format!("{} no longer exists", what),
+    };
+    error_reply(ErrorCode::VersionConflict, msg)
+}
+
+// Tasks waiting on `task` move back if it now ends later. The write that moved it has already
+// succeeded by then, so a failure here is only logged.
+fn push_dependents(db: &DbStore, session: &Session, task: &Task) {
+    if let Err(e) = db.push_dependents(&session.user.username, &task.id, Utc::now()) {
+        log::error!("Rescheduling tasks that depend on {}: {}", task.id, e);
+    }
+}
+
+fn save_task_reply(db: &DbStore, session: &Session, mut task: Task) -> warp::reply::Response {
+    match db.add_task(&session.user.username, &mut task) {
+        Ok(_) => {
+            push_dependents(db, session, &task);
+            warp::reply::json(&task).into_response()
+        }
+        Err(SaveError::Stale(current)) => stale_reply(format!("Task {}", task.id), current),
+        Err(SaveError::Db(e)) => error::reply(internal(format!("Saving task {}", task.id), e)),
+    }
+}
+
+// Planned -> InProgress, stamping actual_start_time
+fn handle_start_task(id: String, session: Session, body: Bytes, db: Arc<DbStore>) -> warp::reply::Response {
+    let update = match parse_execution_update(&body) {
+        Ok(update) => update,
+        Err(e) => return error::reply(e),
+    };
+    let mut task = match load_task(&db, &id) {
+        Ok(task) => task,
+        Err(e) => return error::reply(e),
+    };
+    if let Err(e) = check_assignee(&session, &task) {
+        return error::reply(e);
+    }
+    if task.status != TaskStatus::Planned {
+        return invalid_transition(&task, "start");
+    }
+
+    task.status = TaskStatus::InProgress;
+    task.actual_start_time = Some(update.at.unwrap_or_else(Utc::now));
+    save_task_reply(&db, &session, task)
+}
+
+// InProgress -> Done, recording the actual duration and taking the materials out of stock
+fn handle_finish_task(id: String, session: Session, body: Bytes, db: Arc<DbStore>) -> warp::reply::Response {
+    let update = match parse_execution_update(&body) {
+        Ok(update) => update,
+        Err(e) => return error::reply(e),
+    };
+    let mut task = match load_task(&db, &id) {
+        Ok(task) => task,
+        Err(e) => return error::reply(e),
+    };
+    if let Err(e) = check_assignee(&session, &task) {
+        return error::reply(e);
+    }
+    let Some(started) = task.actual_start_time.filter(|_| task.status == TaskStatus::InProgress) else {
+        return invalid_transition(&task, "finish");
+    };
+
+    let duration = update.actual_duration_minutes
+        .unwrap_or_else(|| (update.at.unwrap_or_else(Utc::now) - started).num_minutes());
+    if duration < 0 {
+        return error_reply(ErrorCode::InvalidRequest, "Task cannot finish before it started");
+    }
+    if duration > MAX_TASK_MINUTES {
+        return error_reply(ErrorCode::InvalidRequest, "Task cannot have run for more than a year");
+    }
+
+    task.status = TaskStatus::Done;
+    task.actual_duration_minutes = Some(duration);
+    // The task itself is saved in the same batch as the stock changes
+    match db.consume_materials(&session.user.username, &mut task) {
+        Ok(_) => {
+            push_dependents(&db, &session, &task);
+            warp::reply::json(&task).into_response()
+        }
+        Err(ConsumeError::AlreadyConsumed) => {
+            error_reply(ErrorCode::InvalidTransition, "Materials were already consumed for this task")
+        }
+        Err(ConsumeError::Stale(current)) => stale_reply(format!("Task {}", id), current),
+        Err(ConsumeError::Invalid(problems)) => error_reply(ErrorCode::InsufficientStock, problems.join("\n")),
+        Err(ConsumeError::Db(e)) => error::reply(internal(format!("Consuming materials for task {}", id), e)),
+    }
+}
+
+// Done -> InProgress (materials go back to stock), Cancelled -> Planned
+fn handle_reopen_task(id: String, session: Session, db: Arc<DbStore>) -> warp::reply::Response {
+    let mut task = match load_task(&db, &id) {
+        Ok(task) => task,
+        Err(e) => return error::reply(e),
+    };
+    match task.status {
+        TaskStatus::Done => {
+            task.status = TaskStatus::InProgress;
+            task.actual_duration_minutes = None;
+            match db.restore_consumption(&session.user.username, &mut task) {
+                Ok(_) => {
+                    push_dependents(&db, &session, &task);
+                    warp::reply::json(&task).into_response()
+                }
+                Err(RestoreError::UnitChanged(msg)) => error_reply(ErrorCode::UnitMismatch, msg),
+                Err(RestoreError::Stale(current)) => stale_reply(format!("Task {}", id), current),
+                Err(RestoreError::Db(e)) => error::reply(internal(format!("Restoring materials for task {}", id), e)),
+            }
+        }
+        TaskStatus::Cancelled => {
+            task.status = TaskStatus::Planned;
+            save_task_reply(&db, &session, task)
+        }
+        _ => invalid_transition(&task, "reopen"),
+    }
+}
+
+fn handle_cancel_task(id: String, session: Session, db: Arc<DbStore>) -> warp::reply::Response {
+    let mut task = match load_task(&db, &id) {
+        Ok(task) => task,
+        Err(e) => return error::reply(e),
+    };
+    if !matches!(task.status, TaskStatus::Planned | TaskStatus::InProgress) {
+        return invalid_transition(&task, "cancel");
+    }
+    task.status = TaskStatus::Cancelled;
+    save_task_reply(&db, &session, task)
+}
+
+#[derive(Deserialize)]
+struct WriteOptions {
+    // Write even if the worker is already booked in that window
+    #[serde(default)]
+    force: bool,
+}
+
+// Parses a task body and checks its fields. Quantities that fail to parse are reported
+// against their material rather than as one error for the whole body.
+fn parse_task(body: &[u8]) -> Result<Task, ApiError> {
+    let task: Task = serde_json::from_slice(body).map_err(|e| {
+        let materials = serde_json::from_slice::<serde_json::Value>(body).ok()
+            .and_then(|value| value.get("materials")?.as_object().cloned())
+            .unwrap_or_default();
+        let errors: Vec<FieldError> = materials.into_iter()
+            .filter_map(|(name, raw)| {
+                let e = serde_json::from_value::<Quantity>(raw).err()?;
+                Some(FieldError::new(format!("materials.{}", name), format!("{}: {}", name, e)))
+            })
+            .collect();
+        if errors.is_empty() {
+            ApiError::new(ErrorCode::InvalidRequest, format!("Invalid body: {}", e))
+        } else {
+            ApiError::invalid_fields(errors)
+        }
+    })?;
+    let errors = task.validate();
+    if !errors.is_empty() {
+        return Err(ApiError::invalid_fields(errors));
+    }
+    Ok(task)
+}
+
+// The operation must be in the catalog. The assigned worker must be registered, have the skills
+// it needs and be on shift, and every machine registered, allowed for it and available, for the
+// whole task.
+fn check_resources(db: &DbStore, task: &Task) -> Result<Worker, ApiError> {
+    let (start, end) = (task.start_time, task.end_time());
+    let mut errors = Vec::new();
+    let operation = match db.get_preset(&task.operation_id) {
+        Ok(operation) => operation,
+        Err(e) => return Err(internal(format!("Loading operation '{}'", task.operation_id), e)),
+    };
+    let worker = match db.get_worker(&task.user_id) {
+        Ok(worker) => worker,
+        Err(e) => return Err(internal(format!("Loading worker '{}'", task.user_id), e)),
+    };
+    match &operation {
+        None => errors.push(FieldError::new("operation_id", format!("No operation '{}' in the catalog", task.operation_id))),
+        Some(operation) => errors.extend(scheduler::operation_problems(task, worker.as_ref(), operation)),
+    }
+    match &worker {
+        None => errors.push(FieldError::new("user_id", format!("No worker '{}' is registered", task.user_id))),
+        Some(worker) if !worker.on_shift(start, end) => {
+            errors.push(FieldError::new("start_time", format!("{} is not on shift for the whole task", worker.display_name)));
+        }
+        Some(_) => {}
+    }
+    for id in &task.machine_ids {
+        match db.get_machine(id) {
+            Ok(Some(machine)) if !machine.available(start, end) => {
+                errors.push(FieldError::new("machine_ids", format!("{} is not available for the whole task", machine.name)));
+            }
+            Ok(Some(_)) => {}
+            Ok(None) => errors.push(FieldError::new("machine_ids", format!("No machine '{}' is registered", id))),
+            Err(e) => return Err(internal(format!("Loading machine '{}'", id), e)),
+        }
+    }
+    match worker {
+        Some(worker) if errors.is_empty() => Ok(worker),
+        _ => Err(ApiError::invalid_fields(errors)),
+    }
+}
+
+// Every task `task` depends on must exist and not lead back to it, and a planned task may not
+// start before they are done
+fn check_dependencies(db: &DbStore, task: &Task) -> Result<(), ApiError> {
+    if task.depends_on.is_empty() {
+        return Ok(());
+    }
+    let tasks = db.get_all_tasks().map_err(|e| internal("Loading tasks", e))?;
+    let mut errors = Vec::new();
+    for dep in task.depends_on.iter().filter(|dep| !tasks.iter().any(|t| t.id == dep.task_id)) {
+        errors.push(FieldError::new("depends_on", format!("No task '{}'", dep.task_id)));
+    }
+    if scheduler::has_dependency_cycle(task, &tasks) {
+        errors.push(FieldError::new("depends_on", "These dependencies would make the task wait for itself"));
+    }
+    let earliest = scheduler::earliest_start(task, |id| tasks.iter().find(|t| t.id == id), Utc::now());
+    if let Some((at, upstream)) = earliest.filter(|(at, _)| task.status == TaskStatus::Planned && task.start_time < *at) {
+        errors.push(FieldError::new("start_time", format!(
+            "Cannot start before {} UTC, when {} is done", at.format("%Y-%m-%d %H:%M"), upstream.operation_id,
+        )));
+    }
+    if errors.is_empty() { Ok(()) } else { Err(ApiError::invalid_fields(errors)) }
+}
+
+// Refuses to book the worker beyond their capacity, or a machine twice, unless forced
+fn check_conflicts(db: &DbStore, task: &Task, worker: &Worker, opts: &WriteOptions) -> Result<(), ApiError> {
+    if opts.force {
+        return Ok(());
+    }
+    // An updated task never conflicts with its own earlier slot
+    let others: Vec<Task> = db.get_all_tasks()
+        .map_err(|e| internal("Loading tasks", e))?
+        .into_iter()
+        .filter(|t| t.id != task.id)
+        .collect();
+    let conflicts = scheduler::find_conflicts(
+        &others, Some(&task.user_id), task.start_time, task.expected_duration_minutes,
+    );
+    let mut problems = Vec::new();
+    let mut ids: Vec<String> = Vec::new();
+    if scheduler::peak_overlap(&conflicts, task.start_time, task.end_time()) >= worker.max_concurrent as usize {
+        problems.push(format!("{} is already booked in this window", task.user_id));
+        ids.extend(conflicts.iter().map(|t| t.id.clone()));
+    }
+    for machine_id in &task.machine_ids {
+        let in_use = scheduler::find_machine_conflicts(&others, machine_id, task.start_time, task.expected_duration_minutes);
+        if !in_use.is_empty() {
+            problems.push(format!("Machine {} is already in use in this window", machine_id));
+            ids.extend(in_use.iter().map(|t| t.id.clone()));
+        }
+    }
+    if problems.is_empty() {
+        return Ok(());
+    }
+    ids.sort();
+    ids.dedup();
+    Err(ApiError::new(ErrorCode::TaskConflict, problems.join("\n")).with_conflicts(ids))
+}
+
+// POST /tasks: always a new task, under an id chosen here
+fn handle_create_task(session: Session, opts: WriteOptions, body: Bytes, db: Arc<DbStore>) -> warp::reply::Response {
+    let mut task = match parse_task(&body) {
+        Ok(task) => task,
+        Err(e) => return error::reply(e),
+    };
+    task.id = uuid::Uuid::new_v4().to_string();
+    task.version = 0;
+    // Execution state only changes through /start, /finish, /reopen and /cancel
+    task.status = TaskStatus::Planned;
+    task.actual_start_time = None;
+    task.actual_duration_minutes = None;
+    let checked = check_dependencies(&db, &task)
+        .and_then(|_| check_resources(&db, &task))
+        .and_then(|worker| check_conflicts(&db, &task, &worker, &opts));
+    if let Err(e) = checked {
+        return error::reply(e);
+    }
+
+    match db.add_task(&session.user.username, &mut task) {
+        Ok(_) => warp::reply::with_status(warp::reply::json(&task), warp::http::StatusCode::CREATED).into_response(),
+        Err(SaveError::Stale(current)) => stale_reply(format!("Task {}", task.id), current),
+        Err(SaveError::Db(e)) => error::reply(internal(format!("Saving task {}", task.id), e)),
+    }
+}
+
+// PUT /tasks/{id}: replaces the planned fields of an existing task. The body's `version` must
+// be the stored one; the id in the body is ignored.
+fn handle_update_task(id: String, session: Session, opts: WriteOptions, body: Bytes, db: Arc<DbStore>) -> warp::reply::Response {
+    let mut task = match parse_task(&body) {
+        Ok(task) => task,
+        Err(e) => return error::reply(e),
+    };
+    let existing = match load_task(&db, &id) {
+        Ok(existing) => existing,
+        Err(e) => return error::reply(e),
+    };
+    task.id = id;
+    task.status = existing.status;
+    task.actual_start_time = existing.actual_start_time;
+    task.actual_duration_minutes = existing.actual_duration_minutes;
+    let checked = check_dependencies(&db, &task)
+        .and_then(|_| check_resources(&db, &task))
+        .and_then(|worker| check_conflicts(&db, &task, &worker, &opts));
+    if let Err(e) = checked {
+        return error::reply(e);
+    }
+    save_task_reply(&db, &session, task)
+}
+
+fn load_job(db: &DbStore, id: &str) -> Result<Job, ApiError> {
+    let id = decode_segment(id);
+    match db.get_job(&id) {
+        Ok(Some(job)) => Ok(job),
+        Ok(None) => Err(not_found(format!("Job {}", id))),
+        Err(e) => Err(internal(format!("Loading job {}", id), e)),
+    }
+}
+
+fn view_job(db: &DbStore, job: Job) -> Result<JobView, ApiError> {
+    let mut tasks = Vec::new();
+    for id in &job.task_ids {
+        tasks.extend(db.get_task(id).map_err(|e| internal(format!("Loading task {}", id), e))?);
+    }
+    Ok(scheduler::job_view(job, tasks, Utc::now()))
+}
+
+// Files an error about step `index` of a new job under `steps.{index}.{field}`
+fn step_error(index: usize, error: FieldError) -> FieldError {
+    FieldError::new(format!("steps.{}.{}", index, error.field), format!("Step {}: {}", index + 1, error.message))
+}
+
+// POST /jobs: plans the steps back to back from `start_time`, each task depending on the one
+// before, with the duration and materials the catalog gives its operation. Every task must pass
+// the same checks as POST /tasks; nothing is stored unless all of them do.
+fn handle_create_job(session: Session, opts: WriteOptions, new_job: NewJob, db: Arc<DbStore>) -> warp::reply::Response {
+    let errors = new_job.validate();
+    if !errors.is_empty() {
+        return error::reply(ApiError::invalid_fields(errors));
+    }
+    let mut tasks: Vec<Task> = Vec::new();
+    let mut errors = Vec::new();
+    let mut ready_at = new_job.start_time;
+    for (index, step) in new_job.steps.iter().enumerate() {
+        let operation_id = step.operation_id.trim().to_string();
+        let operation = match db.get_preset(&operation_id) {
+            Ok(Some(operation)) => operation,
+            Ok(None) => {
+                let error = FieldError::new("operation_id", format!("No operation '{}' in the catalog", operation_id));
+                errors.push(step_error(index, error));
+                continue;
+            }
+            Err(e) => return error::reply(internal(format!("Loading operation '{}'", operation_id), e)),
+        };
+        let lag = if tasks.is_empty() { 0 } else { step.lag_minutes };
+        let mut task = Task::new(
+            step.user_id.trim().to_string(), operation_id, ready_at + Duration::minutes(lag),
+            operation.total_minutes(), operation.materials.clone(),
+        );
+        task.machine_ids = step.machine_ids.iter().map(|id| id.trim().to_string()).collect();
+        if let Some(previous) = tasks.last() {
+            task.depends_on.push(TaskDependency { task_id: previous.id.clone(), lag_minutes: lag });
+        }
+        let checked = match task.validate() {
+            problems if !problems.is_empty() => Err(ApiError::invalid_fields(problems)),
+            _ => check_resources(&db, &task).and_then(|worker| check_conflicts(&db, &task, &worker, &opts)),
+        };
+        match checked {
+            Ok(()) => {}
+            Err(e) if !e.field_errors.is_empty() => errors.extend(e.field_errors.into_iter().map(|f| step_error(index, f))),
+            Err(mut e) if e.code == ErrorCode::TaskConflict => {
+                e.message = format!("Step {}: {}", index + 1, e.message);
+                return error::reply(e);
+            }
+            Err(e) => return error::reply(e),
+        }
+        ready_at = task.end_time();
+        tasks.push(task);
+    }
+    if !errors.is_empty() {
+        return error::reply(ApiError::invalid_fields(errors));
+    }
+
+    let job = Job {
+        id: uuid::Uuid::new_v4().to_string(),
+        customer: new_job.customer.trim().to_string(),
+        due_date: new_job.due_date,
+        priority: new_job.priority,
+        notes: new_job.notes,
+        task_ids: tasks.iter().map(|t| t.id.clone()).collect(),
+    };
+    match db.add_job(&session.user.username, &job, &mut tasks) {
+        Ok(_) => {
+            let view = scheduler::job_view(job, tasks, Utc::now());
+            warp::reply::with_status(warp::reply::json(&view), warp::http::StatusCode::CREATED).into_response()
+        }
+        Err(e) => error::reply(internal(format!("Saving job {}", job.id), e)),
+    }
+}
+
+// PUT /jobs/{id}: replaces the customer, due date, priority and notes. The routing is changed
+// through its tasks.
+fn handle_update_job(id: String, session: Session, mut job: Job, db: Arc<DbStore>) -> warp::reply::Response {
+    let existing = match load_job(&db, &id) {
+        Ok(existing) => existing,
+        Err(e) => return error::reply(e),
+    };
+    job.id = existing.id;
+    job.task_ids = existing.task_ids;
+    job.customer = job.customer.trim().to_string();
+    let errors = job.validate();
+    if !errors.is_empty() {
+        return error::reply(ApiError::invalid_fields(errors));
+    }
+    if let Err(e) = db.put_job(&session.user.username, &job) {
+        return error::reply(internal(format!("Saving job {}", job.id), e));
+    }
+    match view_job(&db, job) {
+        Ok(view) => warp::reply::json(&view).into_response(),
+        Err(e) => error::reply(e),
+    }
+}
+
+const TASK_PAGE_SIZE: usize = 200;
+const TASK_MAX_PAGE_SIZE: usize = 1000;
+
+// Filters for GET /tasks; all optional and combined with AND
+#[derive(Deserialize)]
+struct TaskQuery {
+    // Start time range, `from` inclusive and `to` exclusive
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+    user_id: Option<String>,
+    operation_id: Option<String>,
+    status: Option<TaskStatus>,
+    // `next_cursor` of the previous page
+    cursor: Option<String>,
+    limit: Option<usize>,
+}
+
+fn handle_get_tasks(query: TaskQuery, db: Arc<DbStore>) -> warp::reply::Response {
+    let limit = query.limit.unwrap_or(TASK_PAGE_SIZE);
+    if limit == 0 || limit > TASK_MAX_PAGE_SIZE {
+        return error_reply(ErrorCode::InvalidRequest, format!("limit must be between 1 and {}", TASK_MAX_PAGE_SIZE));
+    }
+    let page = db.query_tasks(query.from, query.to, query.cursor.as_deref(), limit, |task| {
+        query.user_id.as_deref().is_none_or(|user| task.user_id == user)
+            && query.operation_id.as_deref().is_none_or(|op| task.operation_id == op)
+            && query.status.is_none_or(|status| task.status == status)
+    });
+    match page {
+        Ok(page) => warp::reply::json(&page).into_response(),
+        Err(e) => error::reply(internal("Querying tasks", e)),
+    }
+}
+
+const AUDIT_PAGE_SIZE: usize = 100;
+const AUDIT_MAX_PAGE_SIZE: usize = 1000;
+
+// Filters for GET /audit; all optional and combined with AND
+#[derive(Deserialize)]
+struct AuditQuery {
+    entity: Option<AuditEntity>,
+    entity_id: Option<String>,
+    actor: Option<String>,
+    since: Option<DateTime<Utc>>,
+    until: Option<DateTime<Utc>>,
+    // Id of the last entry already seen, to fetch the page after it
+    before: Option<String>,
+    limit: Option<usize>,
+}
+
+fn handle_get_audit(query: AuditQuery, db: Arc<DbStore>) -> warp::reply::Response {
+    let limit = query.limit.unwrap_or(AUDIT_PAGE_SIZE);
+    if limit == 0 || limit > AUDIT_MAX_PAGE_SIZE {
+        return error_reply(ErrorCode::InvalidRequest, format!("limit must be between 1 and {}", AUDIT_MAX_PAGE_SIZE));
+    }
+    let keep = |entry: &AuditEntry| {
+        query.entity.is_none_or(|entity| entry.entity == entity)
+            && query.entity_id.as_deref().is_none_or(|id| entry.entity_id == id)
+            && query.actor.as_deref().is_none_or(|actor| entry.actor == actor)
+    };
+    match db.audit_log(query.since, query.until, query.before.as_deref(), limit, keep) {
+        Ok(entries) => warp::reply::json(&entries).into_response(),
+        Err(e) => error::reply(internal("Reading audit log", e)),
+    }
 }
 
 async fn handle_suggestion(
     body: serde_json::Value, 
-    db: Arc<DbStore>
-) -> Result<impl warp::Reply, warp::Rejection> {
+    db: Arc<DbStore>,
+    model: Arc<dyn SchedulingModel>,
+    hours: WorkingHours,
+) -> Result<warp::reply::Response, Infallible> {
     let req_str = body["requirement"].as_str().unwrap_or("").to_string();
-    let tasks = db.get_all_tasks();
-    
-    match llm::suggest_time_slot(tasks, req_str).await {
-        Ok(suggestion) => Ok(warp::reply::json(&suggestion)),
-        Err(_) => Err(warp::reject::not_found()),
+    let user_id = body["user_id"].as_str().filter(|u| !u.trim().is_empty()).map(str::to_string);
+    // Absent means an hour; anything else must fit in a working day
+    let duration = match &body["duration_minutes"] {
+        serde_json::Value::Null => 60,
+        value => match value.as_i64() {
+            Some(minutes) if minutes > 0 && minutes <= hours.day_minutes() => minutes,
+            _ => return Ok(error_reply(ErrorCode::InvalidRequest, format!(
+                "duration_minutes must be a whole number of minutes between 1 and {}", hours.day_minutes(),
+            ))),
+        },
+    };
+    let tasks = match db.get_all_tasks() {
+        Ok(tasks) => tasks,
+        Err(e) => return Ok(error::reply(internal("Loading tasks", e))),
+    };
+    let now = chrono::Utc::now();
+
+    // The LLM is only advisory: its answer must pass the same checks as the deterministic finder.
+    let fallback_reason = match model.suggest_time_slot(tasks.clone(), req_str).await {
+        Ok(suggestion) => {
+            let start = suggestion.suggested_start_time;
+            let conflicts = scheduler::find_conflicts(&tasks, user_id.as_deref(), start, duration);
+            if start < now {
+                format!("{} suggested a time in the past", model.name())
+            } else if !scheduler::within_working_hours(start, duration, &hours) {
+                format!("{} suggested a time outside working hours", model.name())
+            } else if !conflicts.is_empty() {
+                let ids: Vec<&str> = conflicts.iter().map(|t| t.id.as_str()).collect();
+                format!("{} suggestion overlapped task(s) {}", model.name(), ids.join(", "))
+            } else {
+                return Ok(warp::reply::json(&suggestion).into_response());
+            }
+        }
+        Err(e) => format!("{} unavailable ({})", model.name(), e),
+    };
+
+    match scheduler::find_earliest_slot(&tasks, user_id.as_deref(), duration, &hours, now) {
+        Some(start) => Ok(warp::reply::json(&ScheduleSuggestion {
+            suggested_start_time: start,
+            reason: format!("{}; earliest conflict-free slot within working hours", fallback_reason),
+        }).into_response()),
+        None => Ok(error_reply(
+            ErrorCode::NoSlotAvailable,
+            format!("{}; no free slot of {} minutes in the coming weeks", fallback_reason, duration),
+        )),
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,203 @@
+use rocksdb::{Direction, IteratorMode, WriteBatch, DB};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fmt;
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+#[derive(Debug)]
+pub enum DbError {
+    Rocks(rocksdb::Error),
+    MissingColumnFamily(&'static str),
+    Serialize(String),
+    // A stored value that no longer deserializes into its entity type
+    Corrupt { cf: &'static str, key: String, error: String },
+}
+
+impl fmt::Display for DbError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DbError::Rocks(e) => write!(f, "RocksDB error: {}", e),
+            DbError::MissingColumnFamily(cf) => write!(f, "Column family '{}' is missing", cf),
+            DbError::Serialize(e) => write!(f, "Serialization error: {}", e),
+            DbError::Corrupt { cf, key, error } => write!(f, "Corrupt record {}/{}: {}", cf, key, error),
+        }
+    }
+}
+
+impl From<rocksdb::Error> for DbError {
+    fn from(e: rocksdb::Error) -> Self {
+        DbError::Rocks(e)
+    }
+}
+
+// Result of a lenient scan: everything that decoded, plus what did not
+pub struct Scan<T> {
+    pub records: Vec<(String, T)>,
+    pub corrupt: Vec<DbError>,
+}
+
+impl<T> Scan<T> {
+    // Logs every corrupt record and hands back the rest
+    pub fn reported(self) -> Vec<(String, T)> {
+        for e in &self.corrupt {
+            log::error!("{}", e);
+        }
+        self.records
+    }
+}
+
+// Typed view over one key prefix inside one column family.
+// Values are stored as JSON; keys are `prefix + id`.
+pub struct Repository<T> {
+    db: Arc<DB>,
+    cf: &'static str,
+    prefix: &'static str,
+    _entity: PhantomData<fn() -> T>,
+}
+
+impl<T: Serialize + DeserializeOwned> Repository<T> {
+    pub fn new(db: Arc<DB>, cf: &'static str, prefix: &'static str) -> Self {
+        Self { db, cf, prefix, _entity: PhantomData }
+    }
+
+    fn key(&self, id: &str) -> Vec<u8> {
+        format!("{}{}", self.prefix, id).into_bytes()
+    }
+
+    fn decode(&self, id: &str, value: &[u8]) -> Result<T, DbError> {
+        serde_json::from_slice(value).map_err(|e| DbError::Corrupt {
+            cf: self.cf,
+            key: format!("{}{}", self.prefix, id),
+            error: e.to_string(),
+        })
+    }
+
+    fn encode(value: &T) -> Result<Vec<u8>, DbError> {
+        serde_json::to_vec(value).map_err(|e| DbError::Serialize(e.to_string()))
+    }
+
+    pub fn get(&self, id: &str) -> Result<Option<T>, DbError> {
+        let cf = self.db.cf_handle(self.cf).ok_or(DbError::MissingColumnFamily(self.cf))?;
+        match self.db.get_cf(cf, self.key(id))? {
+            Some(value) => self.decode(id, &value).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    pub fn put(&self, id: &str, value: &T) -> Result<(), DbError> {
+        let cf = self.db.cf_handle(self.cf).ok_or(DbError::MissingColumnFamily(self.cf))?;
+        self.db.put_cf(cf, self.key(id), Self::encode(value)?)?;
+        Ok(())
+    }
+
+    // Batch variants, so writes across repositories can be committed atomically
+    pub fn put_in(&self, batch: &mut WriteBatch, id: &str, value: &T) -> Result<(), DbError> {
+        let cf = self.db.cf_handle(self.cf).ok_or(DbError::MissingColumnFamily(self.cf))?;
+        batch.put_cf(cf, self.key(id), Self::encode(value)?);
+        Ok(())
+    }
+
+    pub fn delete_in(&self, batch: &mut WriteBatch, id: &str) -> Result<(), DbError> {
+        let cf = self.db.cf_handle(self.cf).ok_or(DbError::MissingColumnFamily(self.cf))?;
+        batch.delete_cf(cf, self.key(id));
+        Ok(())
+    }
+
+    // Every record under the prefix, in key order. Undecodable values are collected, not dropped.
+    pub fn scan(&self) -> Result<Scan<T>, DbError> {
+        let cf = self.db.cf_handle(self.cf).ok_or(DbError::MissingColumnFamily(self.cf))?;
+        let mut scan = Scan { records: Vec::new(), corrupt: Vec::new() };
+        let iter = self.db.iterator_cf(cf, IteratorMode::From(self.prefix.as_bytes(), Direction::Forward));
+        for item in iter {
+            let (key, value) = item?;
+            let Some(id) = key.strip_prefix(self.prefix.as_bytes()) else {
+                break;
+            };
+            let id = String::from_utf8_lossy(id).into_owned();
+            match self.decode(&id, &value) {
+                Ok(record) => scan.records.push((id, record)),
+                Err(e) => scan.corrupt.push(e),
+            }
+        }
+        Ok(scan)
+    }
+
+    // Records with `lower <= id < upper` in key order, until `limit` of them pass `keep`
+    pub fn scan_from(
+        &self,
+        lower: Option<&str>,
+        upper: Option<&str>,
+        limit: usize,
+        mut keep: impl FnMut(&T) -> bool,
+    ) -> Result<Scan<T>, DbError> {
+        let cf = self.db.cf_handle(self.cf).ok_or(DbError::MissingColumnFamily(self.cf))?;
+        let mut scan = Scan { records: Vec::new(), corrupt: Vec::new() };
+        let start = self.key(lower.unwrap_or(""));
+        let iter = self.db.iterator_cf(cf, IteratorMode::From(&start, Direction::Forward));
+        for item in iter {
+            let (key, value) = item?;
+            let Some(id) = key.strip_prefix(self.prefix.as_bytes()) else {
+                break;
+            };
+            let id = String::from_utf8_lossy(id).into_owned();
+            if upper.is_some_and(|upper| id.as_str() >= upper) {
+                break;
+            }
+            match self.decode(&id, &value) {
+                Ok(record) if keep(&record) => {
+                    scan.records.push((id, record));
+                    if scan.records.len() >= limit {
+                        break;
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => scan.corrupt.push(e),
+            }
+        }
+        Ok(scan)
+    }
+
+    // Records with `lower <= id < upper`, highest id first, until `limit` of them pass `keep`.
+    // For prefixes whose ids sort by time this reads newest first.
+    pub fn scan_rev(
+        &self,
+        lower: Option<&str>,
+        upper: Option<&str>,
+        limit: usize,
+        mut keep: impl FnMut(&T) -> bool,
+    ) -> Result<Scan<T>, DbError> {
+        let cf = self.db.cf_handle(self.cf).ok_or(DbError::MissingColumnFamily(self.cf))?;
+        let mut scan = Scan { records: Vec::new(), corrupt: Vec::new() };
+        // 0xff never occurs in UTF-8, so this sorts after every id under the prefix
+        let start = match upper {
+            Some(upper) => self.key(upper),
+            None => [self.prefix.as_bytes(), &[0xff]].concat(),
+        };
+        let iter = self.db.iterator_cf(cf, IteratorMode::From(&start, Direction::Reverse));
+        for item in iter {
+            let (key, value) = item?;
+            if *key == *start {
+                continue;
+            }
+            let Some(id) = key.strip_prefix(self.prefix.as_bytes()) else {
+                break;
+            };
+            let id = String::from_utf8_lossy(id).into_owned();
+            if lower.is_some_and(|lower| id.as_str() < lower) {
+                break;
+            }
+            match self.decode(&id, &value) {
+                Ok(record) if keep(&record) => {
+                    scan.records.push((id, record));
+                    if scan.records.len() >= limit {
+                        break;
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => scan.corrupt.push(e),
+            }
+        }
+        Ok(scan)
+    }
+}
@@ -0,0 +1,400 @@
+use chrono::{DateTime, Duration, DurationRound, NaiveTime, Utc};
+use shared::{FieldError, Job, JobView, Task, TaskPreset, TaskStatus, Worker};
+use std::collections::{HashMap, HashSet};
+
+// How many days ahead the slot finder looks before giving up.
+const SEARCH_HORIZON_DAYS: i64 = 30;
+
+// Daily working window, in UTC like everything else stored on the server. Set in [schedule].
+#[derive(Debug, Clone, Copy)]
+pub struct WorkingHours {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+impl WorkingHours {
+    // Longest task that fits in one working day
+    pub fn day_minutes(&self) -> i64 {
+        (self.end - self.start).num_minutes()
+    }
+}
+
+fn overlaps(a_start: DateTime<Utc>, a_end: DateTime<Utc>, b_start: DateTime<Utc>, b_end: DateTime<Utc>) -> bool {
+    a_start < b_end && b_start < a_end
+}
+
+// Cancelled tasks keep their record but no longer occupy anyone's time
+fn occupies(task: &Task, user_id: Option<&str>) -> bool {
+    task.status != TaskStatus::Cancelled && user_id.is_none_or(|u| task.user_id == u)
+}
+
+// Tasks belonging to `user_id` (or every task when no worker is given) that intersect the window.
+pub fn find_conflicts<'a>(
+    tasks: &'a [Task],
+    user_id: Option<&str>,
+    start: DateTime<Utc>,
+    duration_minutes: i64,
+) -> Vec<&'a Task> {
+    let end = start + Duration::minutes(duration_minutes);
+    tasks.iter()
+        .filter(|t| occupies(t, user_id))
+        .filter(|t| overlaps(start, end, t.start_time, t.end_time()))
+        .collect()
+}
+
+// Tasks needing `machine_id` that intersect the window. A machine runs one task at a time.
+pub fn find_machine_conflicts<'a>(
+    tasks: &'a [Task],
+    machine_id: &str,
+    start: DateTime<Utc>,
+    duration_minutes: i64,
+) -> Vec<&'a Task> {
+    let end = start + Duration::minutes(duration_minutes);
+    tasks.iter()
+        .filter(|t| occupies(t, None) && t.machine_ids.iter().any(|m| m == machine_id))
+        .filter(|t| overlaps(start, end, t.start_time, t.end_time()))
+        .collect()
+}
+
+// Most of `tasks` running at the same moment within [start, end)
+pub fn peak_overlap(tasks: &[&Task], start: DateTime<Utc>, end: DateTime<Utc>) -> usize {
+    // The count only goes up where a task begins, so those moments are the ones to check
+    std::iter::once(start)
+        .chain(tasks.iter().map(|t| t.start_time).filter(|&at| start < at && at < end))
+        .map(|at| tasks.iter().filter(|t| t.start_time <= at && at < t.end_time()).count())
+        .max()
+        .unwrap_or(0)
+}
+
+// The operation's required skills that the worker lacks
+pub fn missing_skills<'a>(worker: &Worker, operation: &'a TaskPreset) -> Vec<&'a str> {
+    operation.required_skills.iter()
+        .filter(|skill| !worker.skills.contains(skill))
+        .map(String::as_str)
+        .collect()
+}
+
+// Why `task` cannot be done as planned by `worker`, if registered, given what its operation needs
+pub fn operation_problems(task: &Task, worker: Option<&Worker>, operation: &TaskPreset) -> Vec<FieldError> {
+    let mut problems = Vec::new();
+    if let Some(worker) = worker {
+        let missing = missing_skills(worker, operation);
+        if !missing.is_empty() {
+            problems.push(FieldError::new("user_id", format!(
+                "{} lacks {} needed for {}", worker.display_name, missing.join(", "), task.operation_id,
+            )));
+        }
+    }
+    if operation.allowed_machines.is_empty() {
+        return problems;
+    }
+    let disallowed: Vec<&str> = task.machine_ids.iter()
+        .filter(|id| !operation.allowed_machines.contains(id))
+        .map(String::as_str)
+        .collect();
+    if task.machine_ids.is_empty() {
+        problems.push(FieldError::new("machine_ids", format!(
+            "{} needs one of: {}", task.operation_id, operation.allowed_machines.join(", "),
+        )));
+    } else if !disallowed.is_empty() {
+        problems.push(FieldError::new("machine_ids", format!(
+            "{} cannot run on {}", task.operation_id, disallowed.join(", "),
+        )));
+    }
+    problems
+}
+
+// When `task` should be over: as recorded once done, and while it runs its planned length from
+// the actual start, but no earlier than `now`
+pub fn projected_end(task: &Task, now: DateTime<Utc>) -> DateTime<Utc> {
+    match (task.status, task.actual_start_time) {
+        (TaskStatus::Done, _) => task.actual_end_time().unwrap_or_else(|| task.end_time()),
+        (TaskStatus::InProgress, Some(started)) => (started + Duration::minutes(task.expected_duration_minutes)).max(now),
+        _ => task.end_time(),
+    }
+}
+
+// The earliest `task` may start given what it depends on, with the upstream task that decides it.
+// Cancelled and unknown upstream tasks hold nothing back.
+pub fn earliest_start<'a>(
+    task: &Task,
+    find: impl Fn(&str) -> Option<&'a Task>,
+    now: DateTime<Utc>,
+) -> Option<(DateTime<Utc>, &'a Task)> {
+    task.depends_on.iter()
+        .filter_map(|dep| {
+            let upstream = find(&dep.task_id).filter(|t| t.status != TaskStatus::Cancelled)?;
+            Some((projected_end(upstream, now) + Duration::minutes(dep.lag_minutes), upstream))
+        })
+        .max_by_key(|(at, _)| *at)
+}
+
+// Whether `task`'s dependencies lead back to it through those of the stored `tasks`
+pub fn has_dependency_cycle(task: &Task, tasks: &[Task]) -> bool {
+    let by_id: HashMap<&str, &Task> = tasks.iter().map(|t| (t.id.as_str(), t)).collect();
+    let mut seen = HashSet::new();
+    let mut pending: Vec<&str> = task.depends_on.iter().map(|d| d.task_id.as_str()).collect();
+    while let Some(id) = pending.pop() {
+        if id == task.id {
+            return true;
+        }
+        if seen.insert(id) {
+            if let Some(upstream) = by_id.get(id) {
+                pending.extend(upstream.depends_on.iter().map(|d| d.task_id.as_str()));
+            }
+        }
+    }
+    false
+}
+
+// Progress of `job` given its routing `tasks`: finished tasks count as done, cancelled ones not
+// at all, and the job completes when the last of the rest is projected to end
+pub fn job_view(job: Job, tasks: Vec<Task>, now: DateTime<Utc>) -> JobView {
+    let counted: Vec<&Task> = tasks.iter().filter(|t| t.status != TaskStatus::Cancelled).collect();
+    let done = counted.iter().filter(|t| t.status == TaskStatus::Done).count();
+    let total = counted.len();
+    let projected_completion = counted.iter().map(|t| projected_end(t, now)).max();
+    JobView { job, tasks, done, total, projected_completion }
+}
+
+// Planned tasks that would now start before what they depend on is done, because `changed_id`
+// moved or overran, each with its start pushed back just far enough. Follows the dependencies
+// as far as they go. Worker shifts and bookings are not considered.
+pub fn cascade(tasks: &[Task], changed_id: &str, now: DateTime<Utc>) -> Vec<Task> {
+    let mut current: HashMap<String, Task> = tasks.iter().map(|t| (t.id.clone(), t.clone())).collect();
+    let mut moved: Vec<String> = Vec::new();
+    let mut pending = vec![changed_id.to_string()];
+    // Cycles are refused on save; the bound only guards against stored data that has one
+    let mut rounds = tasks.len() * tasks.len();
+    while let Some(upstream_id) = pending.pop() {
+        if rounds == 0 {
+            break;
+        }
+        rounds -= 1;
+        let dependents: Vec<String> = current.values()
+            .filter(|t| t.status == TaskStatus::Planned && t.depends_on.iter().any(|d| d.task_id == upstream_id))
+            .map(|t| t.id.clone())
+            .collect();
+        for id in dependents {
+            let task = &current[&id];
+            let Some((at, _)) = earliest_start(task, |i: &str| current.get(i), now) else {
+                continue;
+            };
+            if task.start_time < at {
+                if let Some(task) = current.get_mut(&id) {
+                    task.start_time = at;
+                }
+                if !moved.contains(&id) {
+                    moved.push(id.clone());
+                }
+                pending.push(id);
+            }
+        }
+    }
+    moved.into_iter().filter_map(|id| current.remove(&id)).collect()
+}
+
+pub fn within_working_hours(start: DateTime<Utc>, duration_minutes: i64, hours: &WorkingHours) -> bool {
+    let end = start + Duration::minutes(duration_minutes);
+    start.date_naive() == end.date_naive()
+        && start.time() >= hours.start
+        && end.time() <= hours.end
+}
+
+// Earliest start at or after `not_before` (rounded up to the next quarter hour) that fits
+// inside working hours and does not overlap any of the worker's tasks.
+pub fn find_earliest_slot(
+    tasks: &[Task],
+    user_id: Option<&str>,
+    duration_minutes: i64,
+    hours: &WorkingHours,
+    not_before: DateTime<Utc>,
+) -> Option<DateTime<Utc>> {
+    let duration = Duration::minutes(duration_minutes);
+    if duration_minutes <= 0 || duration > hours.end - hours.start {
+        return None;
+    }
+
+    let quarter = Duration::minutes(15);
+    let truncated = not_before.duration_trunc(quarter).ok()?;
+    let not_before = if truncated < not_before { truncated + quarter } else { truncated };
+
+    let mut busy: Vec<(DateTime<Utc>, DateTime<Utc>)> = tasks.iter()
+        .filter(|t| occupies(t, user_id))
+        .map(|t| (t.start_time, t.end_time()))
+        .collect();
+    busy.sort();
+
+    for day_offset in 0..=SEARCH_HORIZON_DAYS {
+        let day = not_before.date_naive() + Duration::days(day_offset);
+        let window_start = day.and_time(hours.start).and_utc();
+        let window_end = day.and_time(hours.end).and_utc();
+
+        let mut cursor = window_start.max(not_before);
+        for &(b_start, b_end) in &busy {
+            if b_end <= cursor {
+                continue;
+            }
+            if cursor + duration <= b_start {
+                break;
+            }
+            cursor = b_end;
+        }
+        if cursor + duration <= window_end {
+            return Some(cursor);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use shared::TaskDependency;
+
+    // 2026-03-02 is a Monday
+    fn at(hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 3, 2, hour, minute, 0).unwrap()
+    }
+
+    fn task(id: &str, user_id: &str, start: DateTime<Utc>, minutes: i64) -> Task {
+        let mut task = Task::new(user_id.to_string(), "op".to_string(), start, minutes, HashMap::new());
+        task.id = id.to_string();
+        task
+    }
+
+    fn waits_for(mut task: Task, upstream: &str, lag_minutes: i64) -> Task {
+        task.depends_on.push(TaskDependency { task_id: upstream.to_string(), lag_minutes });
+        task
+    }
+
+    fn office_hours() -> WorkingHours {
+        WorkingHours { start: NaiveTime::from_hms_opt(8, 0, 0).unwrap(), end: NaiveTime::from_hms_opt(17, 0, 0).unwrap() }
+    }
+
+    #[test]
+    fn slot_rounds_up_to_the_quarter_hour() {
+        let hours = office_hours();
+        assert_eq!(find_earliest_slot(&[], Some("w"), 30, &hours, at(9, 7)), Some(at(9, 15)));
+        assert_eq!(find_earliest_slot(&[], Some("w"), 30, &hours, at(9, 15)), Some(at(9, 15)));
+        assert_eq!(find_earliest_slot(&[], Some("w"), 30, &hours, at(6, 0)), Some(at(8, 0)));
+    }
+
+    #[test]
+    fn slot_skips_busy_intervals() {
+        let hours = office_hours();
+        let mut cancelled = task("x", "w", at(12, 0), 120);
+        cancelled.status = TaskStatus::Cancelled;
+        let tasks = [
+            task("a", "w", at(8, 0), 120),
+            task("b", "w", at(10, 30), 90),
+            task("c", "someone else", at(12, 0), 60),
+            cancelled,
+        ];
+        // The half hour between `a` and `b` is too short for an hour
+        assert_eq!(find_earliest_slot(&tasks, Some("w"), 60, &hours, at(8, 0)), Some(at(12, 0)));
+        assert_eq!(find_earliest_slot(&tasks, Some("w"), 30, &hours, at(8, 0)), Some(at(10, 0)));
+        // Without a worker every task counts
+        assert_eq!(find_earliest_slot(&tasks, None, 60, &hours, at(8, 0)), Some(at(13, 0)));
+    }
+
+    #[test]
+    fn slot_rolls_over_to_the_next_day() {
+        let hours = office_hours();
+        let next_morning = at(8, 0) + Duration::days(1);
+        assert_eq!(find_earliest_slot(&[], Some("w"), 60, &hours, at(16, 30)), Some(next_morning));
+        let late = [task("a", "w", at(15, 0), 120)];
+        assert_eq!(find_earliest_slot(&late, Some("w"), 60, &hours, at(14, 30)), Some(next_morning));
+    }
+
+    #[test]
+    fn slot_gives_up_past_the_horizon() {
+        let hours = office_hours();
+        let away = [task("a", "w", at(0, 0), (SEARCH_HORIZON_DAYS + 2) * 24 * 60)];
+        assert_eq!(find_earliest_slot(&away, Some("w"), 60, &hours, at(8, 0)), None);
+        // Longer than a working day, or nothing to book at all
+        assert_eq!(find_earliest_slot(&[], Some("w"), 10 * 60, &hours, at(8, 0)), None);
+        assert_eq!(find_earliest_slot(&[], Some("w"), 0, &hours, at(8, 0)), None);
+    }
+
+    #[test]
+    fn direct_cycle() {
+        let a = waits_for(task("a", "w", at(9, 0), 60), "b", 0);
+        let b = waits_for(task("b", "w", at(10, 0), 60), "a", 0);
+        assert!(has_dependency_cycle(&a, std::slice::from_ref(&b)));
+        assert!(has_dependency_cycle(&b, &[a]));
+    }
+
+    #[test]
+    fn indirect_cycle() {
+        let a = waits_for(task("a", "w", at(9, 0), 60), "c", 0);
+        let b = waits_for(task("b", "w", at(10, 0), 60), "a", 0);
+        let c = waits_for(task("c", "w", at(11, 0), 60), "b", 0);
+        assert!(has_dependency_cycle(&a, &[b, c]));
+    }
+
+    #[test]
+    fn chain_without_cycle() {
+        let a = task("a", "w", at(9, 0), 60);
+        let b = waits_for(task("b", "w", at(10, 0), 60), "a", 0);
+        let c = waits_for(task("c", "w", at(11, 0), 60), "b", 0);
+        // A diamond reaches `b` twice without being a cycle
+        let d = waits_for(waits_for(task("d", "w", at(12, 0), 60), "b", 0), "c", 0);
+        assert!(!has_dependency_cycle(&d, &[a.clone(), b.clone(), c.clone()]));
+        assert!(!has_dependency_cycle(&c, &[a, b]));
+    }
+
+    #[test]
+    fn cascade_pushes_a_chain_back_with_lag() {
+        // `a` now runs until 11:00; `b` waits 30 minutes after it, `c` 15 after `b`
+        let a = task("a", "w", at(9, 0), 120);
+        let b = waits_for(task("b", "w", at(10, 0), 60), "a", 30);
+        let c = waits_for(task("c", "w", at(11, 0), 60), "b", 15);
+        let moved = cascade(&[a, b, c], "a", at(8, 0));
+        let starts: HashMap<&str, DateTime<Utc>> = moved.iter().map(|t| (t.id.as_str(), t.start_time)).collect();
+        assert_eq!(starts.len(), 2);
+        assert_eq!(starts["b"], at(11, 30));
+        assert_eq!(starts["c"], at(12, 45));
+    }
+
+    #[test]
+    fn cascade_leaves_tasks_that_already_fit() {
+        let a = task("a", "w", at(9, 0), 60);
+        let b = waits_for(task("b", "w", at(14, 0), 60), "a", 30);
+        assert!(cascade(&[a, b], "a", at(8, 0)).is_empty());
+    }
+
+    #[test]
+    fn cascade_only_moves_planned_tasks() {
+        let a = task("a", "w", at(9, 0), 120);
+        let mut running = waits_for(task("b", "w", at(10, 0), 60), "a", 0);
+        running.status = TaskStatus::InProgress;
+        running.actual_start_time = Some(at(10, 0));
+        let mut done = waits_for(task("c", "w", at(10, 0), 30), "a", 0);
+        done.status = TaskStatus::Done;
+        let mut cancelled = waits_for(task("d", "w", at(10, 0), 30), "a", 0);
+        cancelled.status = TaskStatus::Cancelled;
+        assert!(cascade(&[a, running, done, cancelled], "a", at(8, 0)).is_empty());
+    }
+
+    #[test]
+    fn cancelled_upstream_holds_nothing_back() {
+        let mut a = task("a", "w", at(9, 0), 120);
+        a.status = TaskStatus::Cancelled;
+        let b = waits_for(task("b", "w", at(10, 0), 60), "a", 0);
+        assert!(cascade(&[a.clone(), b.clone()], "a", at(8, 0)).is_empty());
+        assert!(earliest_start(&b, |id| (id == "a").then_some(&a), at(8, 0)).is_none());
+    }
+
+    #[test]
+    fn cascade_stops_on_a_stored_cycle() {
+        // Refused on save, but a cycle already in the data must not loop forever
+        let a = waits_for(task("a", "w", at(9, 0), 60), "b", 0);
+        let b = waits_for(task("b", "w", at(9, 30), 60), "a", 0);
+        let moved = cascade(&[a, b], "a", at(8, 0));
+        let mut ids: Vec<&str> = moved.iter().map(|t| t.id.as_str()).collect();
+        ids.sort();
+        assert_eq!(ids, ["a", "b"]);
+    }
+}
@@ -0,0 +1,79 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+// Machine-readable reason for a failed request. The server picks the HTTP status from it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    InvalidRequest,
+    NotFound,
+    MethodNotAllowed,
+    // Missing, invalid or expired session token
+    Unauthorized,
+    Forbidden,
+    AlreadyExists,
+    // Would double-book a worker; `conflicting_ids` lists the tasks in the way
+    TaskConflict,
+    InvalidTransition,
+    // The record was changed or deleted since the version the client sent; reload and retry
+    VersionConflict,
+    UnitMismatch,
+    InsufficientStock,
+    NoSlotAvailable,
+    Internal,
+    // A code added by a newer server
+    #[serde(other)]
+    Unknown,
+}
+
+// One rejected input, named after the JSON field it came from ("user_id", "materials.Steel")
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(field: impl Into<String>, message: impl Into<String>) -> Self {
+        Self { field: field.into(), message: message.into() }
+    }
+}
+
+// JSON body of every non-2xx response
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ApiError {
+    pub code: ErrorCode,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub conflicting_ids: Vec<String>,
+    // Set on InvalidRequest when specific inputs were at fault, so forms can mark them
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub field_errors: Vec<FieldError>,
+}
+
+impl ApiError {
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self { code, message: message.into(), conflicting_ids: Vec::new(), field_errors: Vec::new() }
+    }
+
+    // InvalidRequest listing every field problem, also summed up in the message
+    pub fn invalid_fields(errors: Vec<FieldError>) -> Self {
+        let summary: Vec<&str> = errors.iter().map(|e| e.message.as_str()).collect();
+        let mut error = Self::new(ErrorCode::InvalidRequest, summary.join("\n"));
+        error.field_errors = errors;
+        error
+    }
+
+    pub fn with_conflicts(mut self, ids: Vec<String>) -> Self {
+        self.conflicting_ids = ids;
+        self
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for ApiError {}
@@ -1,22 +1,66 @@
 use serde::{Deserialize, Serialize};
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Datelike, Duration, NaiveTime, Utc, Weekday};
 use uuid::Uuid;
 use std::collections::HashMap;
 
+pub mod error;
+pub mod quantity;
+pub use error::{ApiError, ErrorCode, FieldError};
+pub use quantity::{Dimension, Quantity, QuantityError, unit_info};
+
+// Longest a task may last or wait for another, a year. Keeps every time derived from a task
+// within what chrono can represent.
+pub const MAX_TASK_MINUTES: i64 = 366 * 24 * 60;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskStatus {
+    #[default]
+    Planned,
+    InProgress,
+    Done,
+    Cancelled,
+}
+
+impl TaskStatus {
+    pub fn label(&self) -> &'static str {
+        match self {
+            TaskStatus::Planned => "Planned",
+            TaskStatus::InProgress => "In Progress",
+            TaskStatus::Done => "Done",
+            TaskStatus::Cancelled => "Cancelled",
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct Task {
-    pub id: String, // Unique ID (UUID)
+    // Unique ID (UUID), assigned by the server, so a new task may be sent without one
+    #[serde(default)]
+    pub id: String,
     pub user_id: String,
     pub operation_id: String,
     pub expected_duration_minutes: i64,
     pub start_time: DateTime<Utc>,
     pub actual_start_time: Option<DateTime<Utc>>,
     pub actual_duration_minutes: Option<i64>,
-    pub materials: HashMap<String, String>, // Dictionary of materials
+    pub materials: HashMap<String, Quantity>, // Dictionary of materials
+    #[serde(default)]
+    pub status: TaskStatus,
+    // Machines the task occupies alongside its worker for its whole planned window
+    #[serde(default)]
+    pub machine_ids: Vec<String>,
+    // Tasks that must finish first. When one of them moves or overruns, this one is pushed back.
+    #[serde(default)]
+    pub depends_on: Vec<TaskDependency>,
+    // Bumped by the server on every write. A write must carry the version it was based on,
+    // 0 for a new task, and is rejected if the stored one has moved on since.
+    #[serde(default)]
+    pub version: u64,
 }
 
 impl Task {
-    pub fn new(user_id: String, op_id: String, start: DateTime<Utc>, duration: i64, materials: HashMap<String, String>) -> Self {
+    pub fn new(user_id: String, op_id: String, start: DateTime<Utc>, duration: i64, materials: HashMap<String, Quantity>) -> Self {
         Self {
             id: Uuid::new_v4().to_string(),
             user_id,
@@ -26,10 +70,291 @@ impl Task {
             actual_start_time: None,
             actual_duration_minutes: None,
             materials,
+            status: TaskStatus::Planned,
+            machine_ids: Vec::new(),
+            depends_on: Vec::new(),
+            version: 0,
+        }
+    }
+
+    pub fn end_time(&self) -> DateTime<Utc> {
+        self.start_time + Duration::minutes(self.expected_duration_minutes)
+    }
+
+    // End of the recorded execution, if the task has finished
+    pub fn actual_end_time(&self) -> Option<DateTime<Utc>> {
+        Some(self.actual_start_time? + Duration::minutes(self.actual_duration_minutes?))
+    }
+
+    // Problems with the planned fields; the server refuses to save the task unless this is empty
+    pub fn validate(&self) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+        if self.user_id.trim().is_empty() {
+            errors.push(FieldError::new("user_id", "Worker is required"));
+        }
+        if self.operation_id.trim().is_empty() {
+            errors.push(FieldError::new("operation_id", "Operation is required"));
+        }
+        if self.expected_duration_minutes <= 0 {
+            errors.push(FieldError::new("expected_duration_minutes", "Duration must be above zero"));
+        } else if self.expected_duration_minutes > MAX_TASK_MINUTES {
+            errors.push(FieldError::new("expected_duration_minutes", "Duration must not exceed a year"));
+        }
+        let mut materials: Vec<_> = self.materials.iter().collect();
+        materials.sort_by(|a, b| a.0.cmp(b.0));
+        for (name, quantity) in materials {
+            if name.trim().is_empty() {
+                errors.push(FieldError::new("materials", "Material names must not be empty"));
+            } else if !quantity.value.is_finite() || quantity.value < 0.0 {
+                errors.push(FieldError::new(format!("materials.{}", name), format!("{}: quantity must not be negative", name)));
+            }
+        }
+        if self.machine_ids.iter().any(|id| id.trim().is_empty()) {
+            errors.push(FieldError::new("machine_ids", "Machine ids must not be empty"));
+        } else if self.machine_ids.iter().enumerate().any(|(i, id)| self.machine_ids[..i].contains(id)) {
+            errors.push(FieldError::new("machine_ids", "Each machine may only be listed once"));
+        }
+        let upstream: Vec<&str> = self.depends_on.iter().map(|d| d.task_id.as_str()).collect();
+        if upstream.iter().any(|id| id.trim().is_empty()) {
+            errors.push(FieldError::new("depends_on", "Dependency ids must not be empty"));
+        } else if upstream.contains(&self.id.as_str()) {
+            errors.push(FieldError::new("depends_on", "A task cannot depend on itself"));
+        } else if upstream.iter().enumerate().any(|(i, id)| upstream[..i].contains(id)) {
+            errors.push(FieldError::new("depends_on", "Each task may only be listed once"));
+        }
+        if self.depends_on.iter().any(|d| d.lag_minutes < 0) {
+            errors.push(FieldError::new("depends_on", "Lag must not be negative"));
+        } else if self.depends_on.iter().any(|d| d.lag_minutes > MAX_TASK_MINUTES) {
+            errors.push(FieldError::new("depends_on", "Lag must not exceed a year"));
+        }
+        errors
+    }
+}
+
+// Finish-to-start link: the task may start no sooner than `lag_minutes` after `task_id` is done
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TaskDependency {
+    pub task_id: String,
+    #[serde(default)]
+    pub lag_minutes: i64,
+}
+
+// A weekly working window in UTC, like every time on the server. An `end` at or before
+// `start` means the shift runs past midnight into the next day.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Shift {
+    pub weekday: Weekday,
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+impl Shift {
+    // Whether [start, end) fits inside a single occurrence of this shift
+    pub fn covers(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> bool {
+        // An overnight shift that began the day before may still be running
+        [start.date_naive() - Duration::days(1), start.date_naive()].into_iter()
+            .filter(|day| day.weekday() == self.weekday)
+            .any(|day| {
+                let shift_start = day.and_time(self.start).and_utc();
+                let shift_end = if self.end > self.start { day } else { day + Duration::days(1) }
+                    .and_time(self.end).and_utc();
+                shift_start <= start && end <= shift_end
+            })
+    }
+}
+
+// Someone tasks are assigned to. `Task::user_id` holds the worker id, which is also the
+// username an operator signs in with to run their own tasks.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Worker {
+    pub id: String,
+    pub display_name: String,
+    #[serde(default)]
+    pub skills: Vec<String>,
+    #[serde(default)]
+    pub shifts: Vec<Shift>,
+    // Tasks they may have overlapping at any moment
+    pub max_concurrent: u32,
+}
+
+impl Worker {
+    pub fn on_shift(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> bool {
+        self.shifts.iter().any(|shift| shift.covers(start, end))
+    }
+
+    pub fn validate(&self) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+        if self.id.trim().is_empty() {
+            errors.push(FieldError::new("id", "Id is required"));
+        }
+        if self.display_name.trim().is_empty() {
+            errors.push(FieldError::new("display_name", "Name is required"));
         }
+        if self.skills.iter().any(|skill| skill.trim().is_empty()) {
+            errors.push(FieldError::new("skills", "Skills must not be empty"));
+        }
+        if self.max_concurrent == 0 {
+            errors.push(FieldError::new("max_concurrent", "Must be able to take at least one task"));
+        }
+        errors
     }
 }
 
+// A machine or workstation tasks may need as well as a worker. It runs one task at a time.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Machine {
+    pub id: String,
+    pub name: String,
+    // Weekly windows it can be booked in; none means it is always available
+    #[serde(default)]
+    pub availability: Vec<Shift>,
+}
+
+impl Machine {
+    pub fn available(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> bool {
+        self.availability.is_empty() || self.availability.iter().any(|shift| shift.covers(start, end))
+    }
+
+    pub fn validate(&self) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+        if self.id.trim().is_empty() {
+            errors.push(FieldError::new("id", "Id is required"));
+        }
+        if self.name.trim().is_empty() {
+            errors.push(FieldError::new("name", "Name is required"));
+        }
+        errors
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum JobPriority {
+    Low,
+    #[default]
+    Normal,
+    High,
+    Urgent,
+}
+
+impl JobPriority {
+    pub const ALL: [JobPriority; 4] = [JobPriority::Low, JobPriority::Normal, JobPriority::High, JobPriority::Urgent];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            JobPriority::Low => "Low",
+            JobPriority::Normal => "Normal",
+            JobPriority::High => "High",
+            JobPriority::Urgent => "Urgent",
+        }
+    }
+}
+
+// A customer order. Its tasks form a routing: each one waits for the one before it.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Job {
+    pub id: String, // Unique ID (UUID)
+    pub customer: String,
+    pub due_date: DateTime<Utc>,
+    #[serde(default)]
+    pub priority: JobPriority,
+    #[serde(default)]
+    pub notes: String,
+    // In routing order. Set when the job is created; tasks deleted later drop out.
+    #[serde(default)]
+    pub task_ids: Vec<String>,
+}
+
+impl Job {
+    pub fn validate(&self) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+        if self.customer.trim().is_empty() {
+            errors.push(FieldError::new("customer", "Customer is required"));
+        }
+        errors
+    }
+}
+
+// One operation of a new job's routing
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct JobStep {
+    pub operation_id: String,
+    pub user_id: String,
+    #[serde(default)]
+    pub machine_ids: Vec<String>,
+    // Wait after the previous step is done; ignored on the first
+    #[serde(default)]
+    pub lag_minutes: i64,
+}
+
+// Body of POST /jobs: the job, plus catalog operations to plan back to back from `start_time`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct NewJob {
+    pub customer: String,
+    pub due_date: DateTime<Utc>,
+    #[serde(default)]
+    pub priority: JobPriority,
+    #[serde(default)]
+    pub notes: String,
+    pub start_time: DateTime<Utc>,
+    pub steps: Vec<JobStep>,
+}
+
+impl NewJob {
+    // Field names of step errors are prefixed with `steps.{index}.`
+    pub fn validate(&self) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+        if self.customer.trim().is_empty() {
+            errors.push(FieldError::new("customer", "Customer is required"));
+        }
+        if self.steps.is_empty() {
+            errors.push(FieldError::new("steps", "Add at least one operation"));
+        }
+        for (index, step) in self.steps.iter().enumerate() {
+            if step.operation_id.trim().is_empty() {
+                errors.push(FieldError::new(format!("steps.{}.operation_id", index), "Operation is required"));
+            }
+            if step.user_id.trim().is_empty() {
+                errors.push(FieldError::new(format!("steps.{}.user_id", index), "Worker is required"));
+            }
+            if step.lag_minutes < 0 {
+                errors.push(FieldError::new(format!("steps.{}.lag_minutes", index), "Lag must not be negative"));
+            } else if step.lag_minutes > MAX_TASK_MINUTES {
+                errors.push(FieldError::new(format!("steps.{}.lag_minutes", index), "Lag must not exceed a year"));
+            }
+        }
+        errors
+    }
+}
+
+// A job as GET /jobs returns it: its tasks in routing order and how far along they are
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct JobView {
+    pub job: Job,
+    pub tasks: Vec<Task>,
+    // Cancelled tasks count towards neither
+    pub done: usize,
+    pub total: usize,
+    // When the last open task is expected to finish, or when the last one did
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub projected_completion: Option<DateTime<Utc>>,
+}
+
+impl JobView {
+    pub fn late(&self) -> bool {
+        self.projected_completion.is_some_and(|at| at > self.job.due_date)
+    }
+}
+
+// One page of GET /tasks, in start-time order
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct TaskPage {
+    pub tasks: Vec<Task>,
+    // Pass back as `cursor` for the next page; absent on the last one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+}
+
 // Request payload for LLM scheduling
 #[derive(Serialize, Deserialize)]
 pub struct ScheduleRequest {
@@ -45,8 +370,343 @@ pub struct ScheduleSuggestion {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(from = "InventoryItemRepr")]
 pub struct InventoryItem {
     pub name: String,
-    pub quantity: f64,
-    pub unit: String,
-}
\ No newline at end of file
+    pub quantity: Quantity,
+    // Same rules as `Task::version`
+    #[serde(default)]
+    pub version: u64,
+}
+
+// Older records store `{ "quantity": 5.0, "unit": "kg" }`; fold the unit into the quantity
+#[derive(Deserialize)]
+struct InventoryItemRepr {
+    name: String,
+    quantity: Quantity,
+    #[serde(default)]
+    unit: Option<String>,
+    #[serde(default)]
+    version: u64,
+}
+
+impl From<InventoryItemRepr> for InventoryItem {
+    fn from(repr: InventoryItemRepr) -> Self {
+        let mut quantity = repr.quantity;
+        if let Some(unit) = repr.unit {
+            if quantity.unit.is_empty() {
+                quantity.unit = unit;
+            }
+        }
+        Self { name: repr.name, quantity, version: repr.version }
+    }
+}
+
+// Stock taken out of inventory when a task was finished, kept so it can be put back on reopen
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct MaterialConsumption {
+    pub task_id: String,
+    pub consumed_at: DateTime<Utc>,
+    pub items: Vec<InventoryItem>,
+}
+
+// An entry of the operation catalog, keyed by operation id: the defaults for new tasks and what
+// the operation needs from the worker and machines doing it
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct TaskPreset {
+    pub duration_minutes: i64,
+    // Default bill of materials
+    pub materials: HashMap<String, Quantity>,
+    // The worker must have every one of these
+    #[serde(default)]
+    pub required_skills: Vec<String>,
+    // Machine ids it can run on. When set, tasks must use at least one and only these.
+    #[serde(default)]
+    pub allowed_machines: Vec<String>,
+    // Preparation ahead of the operation itself, booked as part of the task
+    #[serde(default)]
+    pub setup_minutes: i64,
+}
+
+impl TaskPreset {
+    // What a new task for the operation is booked for
+    pub fn total_minutes(&self) -> i64 {
+        self.setup_minutes.saturating_add(self.duration_minutes)
+    }
+
+    pub fn validate(&self) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+        if self.duration_minutes < 0 {
+            errors.push(FieldError::new("duration_minutes", "Duration must not be negative"));
+        } else if self.duration_minutes > MAX_TASK_MINUTES {
+            errors.push(FieldError::new("duration_minutes", "Duration must not exceed a year"));
+        }
+        if self.setup_minutes < 0 {
+            errors.push(FieldError::new("setup_minutes", "Setup time must not be negative"));
+        } else if self.setup_minutes > MAX_TASK_MINUTES {
+            errors.push(FieldError::new("setup_minutes", "Setup time must not exceed a year"));
+        }
+        // Tasks are booked for both together, so the sum has to fit a task as well
+        if errors.is_empty() && self.total_minutes() > MAX_TASK_MINUTES {
+            errors.push(FieldError::new("duration_minutes", "Setup and duration together must not exceed a year"));
+        }
+        if self.required_skills.iter().any(|skill| skill.trim().is_empty()) {
+            errors.push(FieldError::new("required_skills", "Skills must not be empty"));
+        }
+        if self.allowed_machines.iter().any(|id| id.trim().is_empty()) {
+            errors.push(FieldError::new("allowed_machines", "Machine ids must not be empty"));
+        }
+        errors
+    }
+}
+
+// Projected stock of one material right after a pending task consumes it
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ForecastPoint {
+    pub task_id: String,
+    pub start_time: DateTime<Utc>,
+    pub material: String,
+    pub required: Quantity,
+    pub projected: Quantity,
+}
+
+// Inventory projected over all pending tasks in start-time order
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct InventoryForecast {
+    pub points: Vec<ForecastPoint>,
+    // First point at which each material goes negative
+    pub shortages: Vec<ForecastPoint>,
+    // Materials whose unit cannot be converted to the stock unit
+    pub warnings: Vec<String>,
+}
+
+// Body of POST /auth/login
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoginRequest {
+    pub username: String,
+    pub password: String,
+}
+
+// What an account may do. Everyone can read the schedule; the rest is decided here so the
+// server and the UI agree on it.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    // Manages inventory, presets and accounts, and can do anything a planner can
+    Admin,
+    // Creates, moves and cancels tasks
+    Planner,
+    // Starts and finishes the tasks assigned to them
+    Operator,
+    #[default]
+    Viewer,
+}
+
+impl Role {
+    pub const ALL: [Role; 4] = [Role::Admin, Role::Planner, Role::Operator, Role::Viewer];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Role::Admin => "Admin",
+            Role::Planner => "Planner",
+            Role::Operator => "Operator",
+            Role::Viewer => "Viewer",
+        }
+    }
+
+    pub fn can_plan(self) -> bool {
+        matches!(self, Role::Admin | Role::Planner)
+    }
+
+    // Start and finish tasks, though operators only their own (see `can_execute`)
+    pub fn can_run_tasks(self) -> bool {
+        matches!(self, Role::Admin | Role::Planner | Role::Operator)
+    }
+
+    pub fn can_execute(self, task: &Task, username: &str) -> bool {
+        self.can_plan() || (self == Role::Operator && task.user_id == username)
+    }
+
+    // Inventory, presets and user accounts
+    pub fn can_administer(self) -> bool {
+        self == Role::Admin
+    }
+}
+
+// Public view of an account; the password hash never leaves the server
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct UserInfo {
+    pub username: String,
+    #[serde(default)]
+    pub role: Role,
+}
+
+// Issued on login; send `token` as `Authorization: Bearer <token>` until `expires_at`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct LoginResponse {
+    pub token: String,
+    pub expires_at: DateTime<Utc>,
+    pub user: UserInfo,
+}
+
+// Body of POST /users
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NewUser {
+    pub username: String,
+    pub password: String,
+    #[serde(default)]
+    pub role: Role,
+}
+
+// Kinds of record the audit log tracks
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditEntity {
+    Task,
+    InventoryItem,
+    Preset,
+    Worker,
+    Machine,
+    Job,
+}
+
+impl AuditEntity {
+    // Same spelling as the JSON, for query strings
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditEntity::Task => "task",
+            AuditEntity::InventoryItem => "inventory_item",
+            AuditEntity::Preset => "preset",
+            AuditEntity::Worker => "worker",
+            AuditEntity::Machine => "machine",
+            AuditEntity::Job => "job",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditAction {
+    Create,
+    Update,
+    Delete,
+}
+
+impl AuditAction {
+    pub fn label(&self) -> &'static str {
+        match self {
+            AuditAction::Create => "Created",
+            AuditAction::Update => "Updated",
+            AuditAction::Delete => "Deleted",
+        }
+    }
+}
+
+// One change to one record, as returned by GET /audit. Entries are never edited or removed.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AuditEntry {
+    // Sorts by time; pass it as `before` to page further back
+    pub id: String,
+    pub at: DateTime<Utc>,
+    // Username that made the change
+    pub actor: String,
+    pub entity: AuditEntity,
+    pub entity_id: String,
+    pub action: AuditAction,
+    // The record as JSON before and after; absent on create and delete respectively
+    pub before: Option<serde_json::Value>,
+    pub after: Option<serde_json::Value>,
+}
+
+// Pushed to every browser connected to GET /events
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerEvent {
+    // A task, inventory item, preset, worker, machine or job changed; same shape as the audit log entry
+    Changed(AuditEntry),
+    // Events were dropped because this client fell behind; reload everything
+    Resync,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    // 2026-03-02 is a Monday
+    fn monday(hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 3, 2, hour, minute, 0).unwrap()
+    }
+
+    fn shift(weekday: Weekday, start: (u32, u32), end: (u32, u32)) -> Shift {
+        Shift {
+            weekday,
+            start: NaiveTime::from_hms_opt(start.0, start.1, 0).unwrap(),
+            end: NaiveTime::from_hms_opt(end.0, end.1, 0).unwrap(),
+        }
+    }
+
+    #[test]
+    fn day_shift_covers_windows_inside_it() {
+        let day = shift(Weekday::Mon, (8, 0), (16, 0));
+        assert!(day.covers(monday(8, 0), monday(16, 0)));
+        assert!(day.covers(monday(10, 0), monday(11, 30)));
+        assert!(!day.covers(monday(7, 30), monday(9, 0)));
+        assert!(!day.covers(monday(15, 0), monday(16, 30)));
+        // Same hours, wrong day
+        assert!(!day.covers(monday(10, 0) + Duration::days(1), monday(11, 0) + Duration::days(1)));
+    }
+
+    #[test]
+    fn overnight_shift_covers_both_sides_of_midnight() {
+        // Monday 22:00 until Tuesday 06:00
+        let night = shift(Weekday::Mon, (22, 0), (6, 0));
+        assert!(night.covers(monday(22, 0), monday(23, 30)));
+        assert!(night.covers(monday(23, 0), monday(5, 0) + Duration::days(1)));
+        // Started on Tuesday's date, but still Monday's shift
+        assert!(night.covers(monday(1, 0) + Duration::days(1), monday(6, 0) + Duration::days(1)));
+    }
+
+    #[test]
+    fn overnight_shift_excludes_windows_outside_it() {
+        let night = shift(Weekday::Mon, (22, 0), (6, 0));
+        // The early hours of Monday belong to Sunday's night, not this one
+        assert!(!night.covers(monday(1, 0), monday(2, 0)));
+        assert!(!night.covers(monday(21, 0), monday(23, 0)));
+        assert!(!night.covers(monday(5, 0) + Duration::days(1), monday(7, 0) + Duration::days(1)));
+        assert!(!night.covers(monday(12, 0), monday(13, 0)));
+    }
+
+    #[test]
+    fn shift_ending_at_its_start_runs_a_full_day() {
+        let all_day = shift(Weekday::Mon, (6, 0), (6, 0));
+        assert!(all_day.covers(monday(6, 0), monday(6, 0) + Duration::days(1)));
+        assert!(!all_day.covers(monday(5, 0), monday(7, 0)));
+    }
+
+    #[test]
+    fn preset_durations_are_bounded() {
+        let preset = |setup_minutes, duration_minutes| TaskPreset { setup_minutes, duration_minutes, ..Default::default() };
+        let fields = |p: TaskPreset| p.validate().into_iter().map(|e| e.field).collect::<Vec<_>>();
+        assert!(fields(preset(30, 90)).is_empty());
+        assert_eq!(fields(preset(0, i64::MAX)), ["duration_minutes"]);
+        assert_eq!(fields(preset(i64::MAX, i64::MAX)), ["duration_minutes", "setup_minutes"]);
+        assert_eq!(fields(preset(MAX_TASK_MINUTES, 1)), ["duration_minutes"]);
+        assert_eq!(preset(i64::MAX, i64::MAX).total_minutes(), i64::MAX);
+    }
+
+    #[test]
+    fn new_tasks_may_leave_out_the_id() {
+        let task: Task = serde_json::from_str(r#"{
+            "user_id": "alice",
+            "operation_id": "cut",
+            "expected_duration_minutes": 30,
+            "start_time": "2026-03-02T08:00:00Z",
+            "actual_start_time": null,
+            "actual_duration_minutes": null,
+            "materials": {}
+        }"#).unwrap();
+        assert_eq!(task.id, "");
+        assert!(task.validate().is_empty());
+    }
+}
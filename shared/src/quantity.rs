@@ -0,0 +1,323 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Dimension {
+    Mass,
+    Volume,
+    Count,
+    Length,
+}
+
+// A known unit: `factor` converts one of it into the dimension's base unit (g, ml, pcs, mm)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UnitInfo {
+    pub dimension: Dimension,
+    pub factor: f64,
+}
+
+const UNITS: &[(&str, Dimension, f64)] = &[
+    ("mg", Dimension::Mass, 0.001),
+    ("g", Dimension::Mass, 1.0),
+    ("kg", Dimension::Mass, 1_000.0),
+    ("t", Dimension::Mass, 1_000_000.0),
+    ("oz", Dimension::Mass, 28.349_523_125),
+    ("lb", Dimension::Mass, 453.592_37),
+    ("ml", Dimension::Volume, 1.0),
+    ("cl", Dimension::Volume, 10.0),
+    ("l", Dimension::Volume, 1_000.0),
+    ("m3", Dimension::Volume, 1_000_000.0),
+    ("gal", Dimension::Volume, 3_785.411_784),
+    ("", Dimension::Count, 1.0),
+    ("pc", Dimension::Count, 1.0),
+    ("pcs", Dimension::Count, 1.0),
+    ("ea", Dimension::Count, 1.0),
+    ("unit", Dimension::Count, 1.0),
+    ("units", Dimension::Count, 1.0),
+    ("dozen", Dimension::Count, 12.0),
+    ("mm", Dimension::Length, 1.0),
+    ("cm", Dimension::Length, 10.0),
+    ("m", Dimension::Length, 1_000.0),
+    ("km", Dimension::Length, 1_000_000.0),
+    ("in", Dimension::Length, 25.4),
+    ("ft", Dimension::Length, 304.8),
+];
+
+// Looks a unit up in the registry (case-insensitive). Unknown units are still allowed on a
+// Quantity, they just only convert to themselves.
+pub fn unit_info(unit: &str) -> Option<UnitInfo> {
+    let unit = unit.trim();
+    UNITS.iter()
+        .find(|(name, _, _)| name.eq_ignore_ascii_case(unit))
+        .map(|&(_, dimension, factor)| UnitInfo { dimension, factor })
+}
+
+// How far below zero, in the stock unit, a level must fall to count as short. Running sums of
+// converted quantities pick up float error such as 0.3 kg - 3 * 100 g = -2.8e-17 kg.
+pub const STOCK_TOLERANCE: f64 = 1e-9;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum QuantityError {
+    Empty,
+    InvalidNumber(String),
+}
+
+impl fmt::Display for QuantityError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QuantityError::Empty => write!(f, "quantity is empty"),
+            QuantityError::InvalidNumber(raw) => write!(f, "'{}' does not start with a number", raw),
+        }
+    }
+}
+
+// An amount with its unit, e.g. "5 kg". Serialized as that string so stored data and older
+// clients keep working; also accepts `{ "value": 5, "unit": "kg" }` or a bare number.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Quantity {
+    pub value: f64,
+    pub unit: String,
+}
+
+impl Quantity {
+    pub fn new(value: f64, unit: impl Into<String>) -> Self {
+        Self { value, unit: unit.into() }
+    }
+
+    pub fn parse(raw: &str) -> Result<Self, QuantityError> {
+        let raw = raw.trim();
+        if raw.is_empty() {
+            return Err(QuantityError::Empty);
+        }
+        let split = raw
+            .find(|c: char| !(c.is_ascii_digit() || matches!(c, '.' | '-' | '+' | 'e' | 'E')))
+            .unwrap_or(raw.len());
+        // Back off an exponent marker that is really the start of the unit ("5 ea", "2e")
+        let mut number = &raw[..split];
+        while number.ends_with(['e', 'E']) {
+            number = &number[..number.len() - 1];
+        }
+        let value: f64 = number.parse().map_err(|_| QuantityError::InvalidNumber(raw.to_string()))?;
+        if !value.is_finite() {
+            return Err(QuantityError::InvalidNumber(raw.to_string()));
+        }
+        Ok(Self { value, unit: raw[number.len()..].trim().to_string() })
+    }
+
+    pub fn dimension(&self) -> Option<Dimension> {
+        unit_info(&self.unit).map(|u| u.dimension)
+    }
+
+    // Same amount expressed in `unit`, if the two units measure the same thing
+    pub fn convert_to(&self, unit: &str) -> Option<Quantity> {
+        if self.unit.trim().eq_ignore_ascii_case(unit.trim()) {
+            return Some(Quantity::new(self.value, unit));
+        }
+        let from = unit_info(&self.unit)?;
+        let to = unit_info(unit)?;
+        if from.dimension != to.dimension {
+            return None;
+        }
+        Some(Quantity::new(self.value * from.factor / to.factor, unit))
+    }
+
+    pub fn is_compatible(&self, unit: &str) -> bool {
+        self.convert_to(unit).is_some()
+    }
+
+    // self + other, in self's unit
+    pub fn checked_add(&self, other: &Quantity) -> Option<Quantity> {
+        let other = other.convert_to(&self.unit)?;
+        Some(Quantity::new(self.value + other.value, self.unit.clone()))
+    }
+
+    // self - other, in self's unit
+    pub fn checked_sub(&self, other: &Quantity) -> Option<Quantity> {
+        let other = other.convert_to(&self.unit)?;
+        Some(Quantity::new(self.value - other.value, self.unit.clone()))
+    }
+
+    // Below zero by more than float noise, see STOCK_TOLERANCE
+    pub fn is_short(&self) -> bool {
+        self.value < -STOCK_TOLERANCE
+    }
+
+    // Snaps float noise just below zero to exactly zero, so stock used up to the last gram is
+    // stored as empty rather than as a tiny negative
+    pub fn settled(mut self) -> Self {
+        if self.value < 0.0 && !self.is_short() {
+            self.value = 0.0;
+        }
+        self
+    }
+}
+
+impl fmt::Display for Quantity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Round away float noise like 0.30000000000000004, then drop trailing zeros
+        let rounded = format!("{:.6}", self.value);
+        let value = rounded.trim_end_matches('0').trim_end_matches('.');
+        let value = if value == "-0" { "0" } else { value };
+        if self.unit.is_empty() {
+            write!(f, "{}", value)
+        } else {
+            write!(f, "{} {}", value, self.unit)
+        }
+    }
+}
+
+impl FromStr for Quantity {
+    type Err = QuantityError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Quantity::parse(s)
+    }
+}
+
+// The exact value rather than Display's rounded one, since this is also how stock is stored
+impl Serialize for Quantity {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if self.unit.is_empty() {
+            serializer.collect_str(&self.value)
+        } else {
+            serializer.collect_str(&format_args!("{} {}", self.value, self.unit))
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Quantity {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Repr {
+            Text(String),
+            Number(f64),
+            Structured { value: f64, #[serde(default)] unit: String },
+        }
+
+        match Repr::deserialize(deserializer)? {
+            Repr::Text(raw) => Quantity::parse(&raw).map_err(serde::de::Error::custom),
+            Repr::Number(value) => Ok(Quantity::new(value, "")),
+            Repr::Structured { value, unit } => Ok(Quantity::new(value, unit)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    #[test]
+    fn parses_value_and_unit() {
+        assert_eq!(Quantity::parse("5 kg"), Ok(Quantity::new(5.0, "kg")));
+        assert_eq!(Quantity::parse("  2.5l "), Ok(Quantity::new(2.5, "l")));
+        assert_eq!(Quantity::parse("-3 pcs"), Ok(Quantity::new(-3.0, "pcs")));
+        assert_eq!(Quantity::parse("12"), Ok(Quantity::new(12.0, "")));
+    }
+
+    #[test]
+    fn parses_exponents_but_not_units_starting_with_e() {
+        assert_eq!(Quantity::parse("1.5e3 g"), Ok(Quantity::new(1500.0, "g")));
+        assert_eq!(Quantity::parse("2E-3kg"), Ok(Quantity::new(0.002, "kg")));
+        assert_eq!(Quantity::parse("5ea"), Ok(Quantity::new(5.0, "ea")));
+        assert_eq!(Quantity::parse("5 ea"), Ok(Quantity::new(5.0, "ea")));
+        assert_eq!(Quantity::parse("2e"), Ok(Quantity::new(2.0, "e")));
+    }
+
+    #[test]
+    fn rejects_missing_numbers() {
+        assert_eq!(Quantity::parse("   "), Err(QuantityError::Empty));
+        assert!(matches!(Quantity::parse("kg"), Err(QuantityError::InvalidNumber(_))));
+        assert!(matches!(Quantity::parse("inf kg"), Err(QuantityError::InvalidNumber(_))));
+        assert!(matches!(Quantity::parse("1e999 g"), Err(QuantityError::InvalidNumber(_))));
+    }
+
+    #[test]
+    fn converts_within_a_dimension() {
+        assert_eq!(Quantity::new(1.5, "kg").convert_to("g"), Some(Quantity::new(1500.0, "g")));
+        assert_eq!(Quantity::new(250.0, "ml").convert_to("L"), Some(Quantity::new(0.25, "L")));
+        assert_eq!(Quantity::new(2.0, "dozen").convert_to("pcs"), Some(Quantity::new(24.0, "pcs")));
+        let lb = Quantity::new(1.0, "lb").convert_to("g").unwrap();
+        assert!(close(lb.value, 453.592_37));
+    }
+
+    #[test]
+    fn conversion_round_trips() {
+        for (value, unit, via) in [(1.5, "kg", "g"), (2.0, "oz", "g"), (3.25, "gal", "l"), (7.0, "ft", "cm")] {
+            let there = Quantity::new(value, unit).convert_to(via).unwrap();
+            let back = there.convert_to(unit).unwrap();
+            assert!(close(back.value, value), "{} {} via {} came back as {}", value, unit, via, back);
+            assert_eq!(back.unit, unit);
+        }
+    }
+
+    #[test]
+    fn incompatible_units_do_not_convert() {
+        assert_eq!(Quantity::new(1.0, "kg").convert_to("l"), None);
+        assert_eq!(Quantity::new(1.0, "m").convert_to("pcs"), None);
+        assert!(!Quantity::new(1.0, "kg").is_compatible("ml"));
+        assert_eq!(Quantity::new(1.0, "kg").checked_sub(&Quantity::new(1.0, "l")), None);
+        // Unknown units only convert to themselves
+        assert_eq!(Quantity::new(3.0, "bags").convert_to("Bags"), Some(Quantity::new(3.0, "Bags")));
+        assert_eq!(Quantity::new(3.0, "bags").convert_to("g"), None);
+    }
+
+    #[test]
+    fn using_up_stock_in_steps_settles_at_zero() {
+        let mut stock = Quantity::new(0.3, "kg");
+        for _ in 0..3 {
+            stock = stock.checked_sub(&Quantity::new(100.0, "g")).unwrap().settled();
+            assert!(!stock.is_short(), "short at {}", stock.value);
+        }
+        assert_eq!(stock, Quantity::new(0.0, "kg"));
+        assert!(stock.checked_sub(&Quantity::new(1.0, "g")).unwrap().settled().is_short());
+    }
+
+    #[test]
+    fn adjusting_stock_down_in_steps_settles_at_zero() {
+        let mut stock = Quantity::new(0.3, "kg");
+        for _ in 0..3 {
+            stock = stock.checked_add(&Quantity::new(-0.1, "kg")).unwrap().settled();
+            assert!(!stock.is_short(), "short at {}", stock.value);
+        }
+        assert_eq!(stock, Quantity::new(0.0, "kg"));
+    }
+
+    #[test]
+    fn arithmetic_keeps_the_left_unit() {
+        let stock = Quantity::new(1.0, "kg");
+        assert_eq!(stock.checked_sub(&Quantity::new(250.0, "g")), Some(Quantity::new(0.75, "kg")));
+        assert_eq!(stock.checked_add(&Quantity::new(500.0, "g")), Some(Quantity::new(1.5, "kg")));
+    }
+
+    #[test]
+    fn display_rounds_but_serialization_is_exact() {
+        let tiny = Quantity::new(0.000_000_4, "kg");
+        assert_eq!(tiny.to_string(), "0 kg");
+        assert_eq!(Quantity::new(0.1 + 0.2, "l").to_string(), "0.3 l");
+        let json = serde_json::to_string(&tiny).unwrap();
+        assert_eq!(json, "\"0.0000004 kg\"");
+        assert_eq!(serde_json::from_str::<Quantity>(&json).unwrap(), tiny);
+    }
+
+    #[test]
+    fn serde_round_trips() {
+        for quantity in [Quantity::new(5.0, "kg"), Quantity::new(0.1 + 0.2, "l"), Quantity::new(12.0, ""), Quantity::new(-1.5, "m")] {
+            let json = serde_json::to_string(&quantity).unwrap();
+            assert_eq!(serde_json::from_str::<Quantity>(&json).unwrap(), quantity, "{}", json);
+        }
+    }
+
+    #[test]
+    fn deserializes_older_shapes() {
+        assert_eq!(serde_json::from_str::<Quantity>("5").unwrap(), Quantity::new(5.0, ""));
+        assert_eq!(serde_json::from_str::<Quantity>(r#"{"value": 2, "unit": "l"}"#).unwrap(), Quantity::new(2.0, "l"));
+        assert!(serde_json::from_str::<Quantity>("\"lots\"").is_err());
+    }
+}
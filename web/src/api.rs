@@ -0,0 +1,379 @@
+// Client for the scheduling server. Every request goes through here so the base URL is
+// decided in one place: a runtime `config.json` next to index.html wins, then the
+// API_BASE_URL the frontend was built with, then the local development server.
+use gloo_net::http::{RequestBuilder, Response};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use shared::{ApiError, ErrorCode};
+use std::cell::RefCell;
+use std::fmt;
+
+const DEFAULT_BASE_URL: &str = "http://localhost:8081";
+const SESSION_KEY: &str = "session";
+
+thread_local! {
+    static BASE_URL: RefCell<String> = RefCell::new(
+        option_env!("API_BASE_URL").unwrap_or(DEFAULT_BASE_URL).to_string()
+    );
+}
+
+#[derive(Deserialize)]
+struct RuntimeConfig {
+    api_base_url: Option<String>,
+}
+
+// Called once before the app renders. A missing or malformed config.json keeps the build-time URL.
+pub async fn init() {
+    let Ok(resp) = gloo_net::http::Request::get("/config.json").send().await else {
+        return;
+    };
+    if !resp.ok() {
+        return;
+    }
+    if let Ok(RuntimeConfig { api_base_url: Some(url) }) = resp.json().await {
+        let url = url.trim().trim_end_matches('/').to_string();
+        BASE_URL.with(|base| *base.borrow_mut() = url);
+    }
+}
+
+fn url(path: &str) -> String {
+    BASE_URL.with(|base| format!("{}{}", base.borrow().trim_end_matches('/'), path))
+}
+
+// Builds a request to the server, signed with the current session if there is one
+fn request(method: fn(&str) -> RequestBuilder, path: &str) -> RequestBuilder {
+    let builder = method(&url(path));
+    match auth::session() {
+        Some(session) => builder.header("Authorization", &format!("Bearer {}", session.token)),
+        None => builder,
+    }
+}
+
+// WebSocket address of the live update stream. Browsers cannot set headers on the handshake,
+// so the session token goes in the query string.
+pub fn events_url(token: &str) -> String {
+    let http = url(&format!("/events?token={}", segment(token)));
+    // A relative base URL is resolved against the page, like fetch would
+    let http = if http.starts_with('/') {
+        format!("{}{}", gloo::utils::window().location().origin().unwrap_or_default(), http)
+    } else {
+        http
+    };
+    match http.strip_prefix("http") {
+        Some(rest) => format!("ws{}", rest),
+        None => http,
+    }
+}
+
+// Path segments (task ids, item names, operation ids) may contain spaces or slashes
+fn segment(raw: &str) -> String {
+    String::from(js_sys::encode_uri_component(raw))
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Network(gloo_net::Error),
+    // The server answered with a non-2xx status
+    Api(ApiError),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Network(e) => write!(f, "Could not reach the server: {}", e),
+            Error::Api(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl From<gloo_net::Error> for Error {
+    fn from(e: gloo_net::Error) -> Self {
+        Error::Network(e)
+    }
+}
+
+// Turns a non-2xx response into the ApiError in its body. Anything else in front of the
+// server (a proxy, an older build) may answer in plain text, so fall back to that.
+async fn check(resp: Response) -> Result<Response, Error> {
+    if resp.ok() {
+        return Ok(resp);
+    }
+    let status = resp.status();
+    // The session expired or the account went away: start over at the login page
+    if status == 401 && auth::session().is_some() {
+        auth::forget();
+        let _ = gloo::utils::window().location().set_href("/login");
+    }
+    let body = resp.text().await.unwrap_or_default();
+    let error = serde_json::from_str::<ApiError>(&body).unwrap_or_else(|_| {
+        let message = if body.trim().is_empty() { format!("Server returned {}", status) } else { body };
+        ApiError::new(ErrorCode::Unknown, message)
+    });
+    Err(Error::Api(error))
+}
+
+async fn json<T: DeserializeOwned>(resp: Response) -> Result<T, Error> {
+    Ok(check(resp).await?.json().await?)
+}
+
+pub mod auth {
+    use super::{json, request, Error, SESSION_KEY};
+    use gloo::storage::{LocalStorage, Storage};
+    use gloo_net::http::Request;
+    use shared::{LoginRequest, LoginResponse};
+
+    // The stored session, unless it has run out
+    pub fn session() -> Option<LoginResponse> {
+        LocalStorage::get::<LoginResponse>(SESSION_KEY).ok()
+            .filter(|session| session.expires_at > chrono::Utc::now())
+    }
+
+    pub fn forget() {
+        LocalStorage::delete(SESSION_KEY);
+    }
+
+    pub async fn login(username: &str, password: &str) -> Result<LoginResponse, Error> {
+        let body = LoginRequest { username: username.to_string(), password: password.to_string() };
+        let session: LoginResponse = json(request(Request::post, "/auth/login").json(&body)?.send().await?).await?;
+        // Only fails when storage is unavailable, in which case the session lasts until reload
+        let _ = LocalStorage::set(SESSION_KEY, &session);
+        Ok(session)
+    }
+}
+
+pub mod tasks {
+    use super::{check, json, request, segment, Error};
+    use chrono::{DateTime, Utc};
+    use gloo_net::http::{Request, RequestBuilder};
+    use shared::{ApiError, ErrorCode, Task, TaskPage};
+
+    pub enum SaveOutcome {
+        // As stored, with its new version
+        Saved(Task),
+        // Overlaps another task for the same worker; retry with `force` to book it anyway
+        Conflict(ApiError),
+        // Someone else saved or deleted the task since `task.version` was loaded
+        Stale(ApiError),
+    }
+
+    // Every task starting in [from, to), a page at a time
+    pub async fn list_starting(from: DateTime<Utc>, to: DateTime<Utc>) -> Result<Vec<Task>, Error> {
+        let mut tasks = Vec::new();
+        let mut cursor: Option<String> = None;
+        loop {
+            let mut path = format!("/tasks?from={}&to={}", segment(&from.to_rfc3339()), segment(&to.to_rfc3339()));
+            if let Some(cursor) = &cursor {
+                path.push_str(&format!("&cursor={}", segment(cursor)));
+            }
+            let page: TaskPage = json(request(Request::get, &path).send().await?).await?;
+            tasks.extend(page.tasks);
+            match page.next_cursor {
+                Some(next) => cursor = Some(next),
+                None => return Ok(tasks),
+            }
+        }
+    }
+
+    pub async fn get(id: &str) -> Result<Task, Error> {
+        json(request(Request::get, &format!("/tasks/{}", segment(id))).send().await?).await
+    }
+
+    // The server picks the new task's id; the one in `task` is ignored
+    pub async fn create(task: &Task, force: bool) -> Result<SaveOutcome, Error> {
+        let path = if force { "/tasks?force=true" } else { "/tasks" };
+        save(request(Request::post, path), task).await
+    }
+
+    // `task.id` must exist and `task.version` must be the stored one
+    pub async fn update(task: &Task, force: bool) -> Result<SaveOutcome, Error> {
+        let path = format!("/tasks/{}{}", segment(&task.id), if force { "?force=true" } else { "" });
+        save(request(Request::put, &path), task).await
+    }
+
+    async fn save(builder: RequestBuilder, task: &Task) -> Result<SaveOutcome, Error> {
+        match json(builder.json(task)?.send().await?).await {
+            Ok(saved) => Ok(SaveOutcome::Saved(saved)),
+            Err(Error::Api(e)) if e.code == ErrorCode::TaskConflict => Ok(SaveOutcome::Conflict(e)),
+            Err(Error::Api(e)) if e.code == ErrorCode::VersionConflict => Ok(SaveOutcome::Stale(e)),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub async fn delete(id: &str) -> Result<(), Error> {
+        check(request(Request::delete, &format!("/tasks/{}", segment(id))).send().await?).await?;
+        Ok(())
+    }
+
+    // `action` is one of start, finish, reopen, cancel
+    pub async fn transition(id: &str, action: &str) -> Result<Task, Error> {
+        json(request(Request::post, &format!("/tasks/{}/{}", segment(id), action)).send().await?).await
+    }
+}
+
+pub mod inventory {
+    use super::{check, json, request, segment, Error};
+    use gloo_net::http::Request;
+    use shared::{InventoryForecast, InventoryItem};
+
+    pub async fn list() -> Result<Vec<InventoryItem>, Error> {
+        json(request(Request::get, "/inventory").send().await?).await
+    }
+
+    pub async fn get(name: &str) -> Result<InventoryItem, Error> {
+        json(request(Request::get, &format!("/inventory/{}", segment(name))).send().await?).await
+    }
+
+    pub async fn add(item: &InventoryItem) -> Result<InventoryItem, Error> {
+        json(request(Request::post, "/inventory").json(item)?.send().await?).await
+    }
+
+    // Replaces the item stored under `name`; `item.name` may differ to rename it.
+    // Fails with VersionConflict unless `item.version` is the one stored.
+    pub async fn replace(name: &str, item: &InventoryItem) -> Result<InventoryItem, Error> {
+        json(request(Request::put, &format!("/inventory/{}", segment(name))).json(item)?.send().await?).await
+    }
+
+    pub async fn delete(name: &str) -> Result<(), Error> {
+        check(request(Request::delete, &format!("/inventory/{}", segment(name))).send().await?).await?;
+        Ok(())
+    }
+
+    pub async fn forecast() -> Result<InventoryForecast, Error> {
+        json(request(Request::get, "/forecast").send().await?).await
+    }
+}
+
+pub mod presets {
+    use super::{check, json, request, segment, Error};
+    use gloo_net::http::Request;
+    use shared::TaskPreset;
+    use std::collections::HashMap;
+
+    pub async fn list() -> Result<HashMap<String, TaskPreset>, Error> {
+        json(request(Request::get, "/presets").send().await?).await
+    }
+
+    pub async fn save(op_id: &str, preset: &TaskPreset) -> Result<(), Error> {
+        check(request(Request::put, &format!("/presets/{}", segment(op_id))).json(preset)?.send().await?).await?;
+        Ok(())
+    }
+
+    pub async fn delete(op_id: &str) -> Result<(), Error> {
+        check(request(Request::delete, &format!("/presets/{}", segment(op_id))).send().await?).await?;
+        Ok(())
+    }
+
+    // One-off upload of presets that used to live in LocalStorage
+    pub async fn import(presets: &HashMap<String, TaskPreset>) -> Result<(), Error> {
+        check(request(Request::post, "/presets/import").json(presets)?.send().await?).await?;
+        Ok(())
+    }
+}
+
+pub mod workers {
+    use super::{check, json, request, segment, Error};
+    use gloo_net::http::Request;
+    use shared::Worker;
+
+    pub async fn list() -> Result<Vec<Worker>, Error> {
+        json(request(Request::get, "/workers").send().await?).await
+    }
+
+    pub async fn create(worker: &Worker) -> Result<Worker, Error> {
+        json(request(Request::post, "/workers").json(worker)?.send().await?).await
+    }
+
+    // Replaces the worker stored under `worker.id`
+    pub async fn update(worker: &Worker) -> Result<Worker, Error> {
+        json(request(Request::put, &format!("/workers/{}", segment(&worker.id))).json(worker)?.send().await?).await
+    }
+
+    pub async fn delete(id: &str) -> Result<(), Error> {
+        check(request(Request::delete, &format!("/workers/{}", segment(id))).send().await?).await?;
+        Ok(())
+    }
+}
+
+pub mod machines {
+    use super::{check, json, request, segment, Error};
+    use gloo_net::http::Request;
+    use shared::Machine;
+
+    pub async fn list() -> Result<Vec<Machine>, Error> {
+        json(request(Request::get, "/machines").send().await?).await
+    }
+
+    pub async fn create(machine: &Machine) -> Result<Machine, Error> {
+        json(request(Request::post, "/machines").json(machine)?.send().await?).await
+    }
+
+    // Replaces the machine stored under `machine.id`
+    pub async fn update(machine: &Machine) -> Result<Machine, Error> {
+        json(request(Request::put, &format!("/machines/{}", segment(&machine.id))).json(machine)?.send().await?).await
+    }
+
+    pub async fn delete(id: &str) -> Result<(), Error> {
+        check(request(Request::delete, &format!("/machines/{}", segment(id))).send().await?).await?;
+        Ok(())
+    }
+}
+
+pub mod jobs {
+    use super::{check, json, request, segment, Error};
+    use gloo_net::http::Request;
+    use shared::{Job, JobView, NewJob};
+
+    pub async fn list() -> Result<Vec<JobView>, Error> {
+        json(request(Request::get, "/jobs").send().await?).await
+    }
+
+    // Plans a task per step; with `force` even where that double-books a worker or machine
+    pub async fn create(new_job: &NewJob, force: bool) -> Result<JobView, Error> {
+        let path = if force { "/jobs?force=true" } else { "/jobs" };
+        json(request(Request::post, path).json(new_job)?.send().await?).await
+    }
+
+    // Replaces the details of the job stored under `job.id`; its routing stays as it is
+    pub async fn update(job: &Job) -> Result<JobView, Error> {
+        json(request(Request::put, &format!("/jobs/{}", segment(&job.id))).json(job)?.send().await?).await
+    }
+
+    // Deletes the job's tasks along with it
+    pub async fn delete(id: &str) -> Result<(), Error> {
+        check(request(Request::delete, &format!("/jobs/{}", segment(id))).send().await?).await?;
+        Ok(())
+    }
+}
+
+pub mod schedule {
+    use super::{json, request, Error};
+    use gloo_net::http::Request;
+    use shared::ScheduleSuggestion;
+
+    pub async fn suggest(requirement: &str, user_id: &str, duration_minutes: i64) -> Result<ScheduleSuggestion, Error> {
+        let body = serde_json::json!({
+            "requirement": requirement,
+            "user_id": user_id,
+            "duration_minutes": duration_minutes,
+        });
+        json(request(Request::post, "/suggest").json(&body)?.send().await?).await
+    }
+}
+
+pub mod audit {
+    use super::{json, request, segment, Error};
+    use gloo_net::http::Request;
+    use shared::{AuditEntity, AuditEntry};
+
+    // Newest first; `entity_id` narrows to one record, `before` continues after an earlier page
+    pub async fn list(entity: AuditEntity, entity_id: Option<&str>, before: Option<&str>, limit: usize) -> Result<Vec<AuditEntry>, Error> {
+        let mut path = format!("/audit?entity={}&limit={}", entity.as_str(), limit);
+        if let Some(id) = entity_id {
+            path.push_str(&format!("&entity_id={}", segment(id)));
+        }
+        if let Some(before) = before {
+            path.push_str(&format!("&before={}", segment(before)));
+        }
+        json(request(Request::get, &path).send().await?).await
+    }
+}
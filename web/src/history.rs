@@ -0,0 +1,141 @@
+use chrono::Local;
+use serde_json::Value;
+use shared::{AuditAction, AuditEntity, AuditEntry};
+use yew::prelude::*;
+use crate::api;
+use crate::toast::use_toaster;
+
+const PAGE_SIZE: usize = 20;
+// Longer values are cut off in the change list
+const MAX_VALUE_CHARS: usize = 60;
+
+fn show_value(value: Option<&Value>) -> String {
+    let text = match value {
+        None | Some(Value::Null) => "—".to_string(),
+        Some(Value::String(s)) => s.clone(),
+        Some(other) => other.to_string(),
+    };
+    if text.chars().count() > MAX_VALUE_CHARS {
+        format!("{}…", text.chars().take(MAX_VALUE_CHARS).collect::<String>())
+    } else {
+        text
+    }
+}
+
+// One "field: old → new" line per top-level field that differs
+fn changed_fields(entry: &AuditEntry) -> Vec<String> {
+    let (Some(Value::Object(before)), Some(Value::Object(after))) = (&entry.before, &entry.after) else {
+        return Vec::new();
+    };
+    let mut keys: Vec<&String> = before.keys().chain(after.keys()).collect();
+    keys.sort();
+    keys.dedup();
+    keys.into_iter()
+        .filter(|k| before.get(*k) != after.get(*k))
+        .map(|k| format!("{}: {} → {}", k, show_value(before.get(k)), show_value(after.get(k))))
+        .collect()
+}
+
+#[derive(Properties, PartialEq)]
+pub struct HistoryPanelProps {
+    pub entity: AuditEntity,
+    // Only this record's history; everything of `entity` when unset
+    #[prop_or_default]
+    pub entity_id: Option<String>,
+}
+
+// Recent audit log entries for one kind of record, newest first
+#[function_component(HistoryPanel)]
+pub fn history_panel(props: &HistoryPanelProps) -> Html {
+    let entries = use_state(Vec::<AuditEntry>::new);
+    // False once a page came back short, so there is nothing older to load
+    let has_more = use_state(|| false);
+    let toaster = use_toaster();
+
+    let load = {
+        let entries = entries.clone();
+        let has_more = has_more.clone();
+        let toaster = toaster.clone();
+        let entity = props.entity;
+        let entity_id = props.entity_id.clone();
+        // `Some(id)` appends the page older than `id`, `None` starts over
+        Callback::from(move |before: Option<String>| {
+            let entries = entries.clone();
+            let has_more = has_more.clone();
+            let toaster = toaster.clone();
+            let entity_id = entity_id.clone();
+            wasm_bindgen_futures::spawn_local(async move {
+                let result = api::audit::list(entity, entity_id.as_deref(), before.as_deref(), PAGE_SIZE).await;
+                if let Some(page) = toaster.report(result) {
+                    has_more.set(page.len() == PAGE_SIZE);
+                    let mut all = if before.is_some() { (*entries).clone() } else { Vec::new() };
+                    all.extend(page);
+                    entries.set(all);
+                }
+            });
+        })
+    };
+
+    {
+        let load = load.clone();
+        use_effect_with_deps(move |_| {
+            load.emit(None);
+            || {}
+        }, (props.entity, props.entity_id.clone()));
+    }
+
+    let on_refresh = {
+        let load = load.clone();
+        Callback::from(move |_| load.emit(None))
+    };
+    let on_older = {
+        let load = load.clone();
+        let last = entries.last().map(|e| e.id.clone());
+        Callback::from(move |_| load.emit(last.clone()))
+    };
+
+    html! {
+        <div class="card p-3 mt-3">
+            <div class="d-flex justify-content-between align-items-center mb-2">
+                <h5 class="mb-0">
+                    {"History"}
+                    if let Some(id) = &props.entity_id {
+                        <small class="text-muted ms-2">{id}</small>
+                    }
+                </h5>
+                <button class="btn btn-outline-secondary btn-sm" onclick={on_refresh}>{"Refresh"}</button>
+            </div>
+            if entries.is_empty() {
+                <div class="text-muted">{"No changes recorded yet."}</div>
+            } else {
+                <ul class="list-group list-group-flush small">
+                    {for entries.iter().map(|entry| {
+                        let changes = changed_fields(entry);
+                        let badge = match entry.action {
+                            AuditAction::Create => "badge bg-success",
+                            AuditAction::Update => "badge bg-secondary",
+                            AuditAction::Delete => "badge bg-danger",
+                        };
+                        html! {
+                            <li key={entry.id.clone()} class="list-group-item px-0">
+                                <span class={classes!(badge, "me-2")}>{entry.action.label()}</span>
+                                <strong>{&entry.entity_id}</strong>
+                                <span class="text-muted">
+                                    {format!(" by {} at {}", entry.actor, entry.at.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S"))}
+                                </span>
+                                if !changes.is_empty() {
+                                    <ul class="mb-0">
+                                        {for changes.iter().map(|c| html! { <li>{c}</li> })}
+                                    </ul>
+                                }
+                            </li>
+                        }
+                    })}
+                </ul>
+                if *has_more {
+                    <button class="btn btn-link btn-sm" onclick={on_older}>{"Older changes"}</button>
+                }
+            }
+        </div>
+    }
+}
@@ -1,16 +1,19 @@
 use yew::prelude::*;
 use yew_router::prelude::*;
-use gloo_net::http::Request;
-use shared::{Task, InventoryItem};
+use shared::{AuditEntity, FieldError, Task, TaskStatus, InventoryItem, InventoryForecast, Machine, Quantity, ServerEvent, TaskDependency, Worker};
 use wasm_bindgen::prelude::*;
 use wasm_bindgen::JsCast;
 use chrono::{Utc, Duration, Local, TimeZone};
 use std::collections::HashMap;
-use regex::Regex;
-use gloo::storage::{LocalStorage, Storage};
 use web_sys::{HtmlInputElement, HtmlTextAreaElement, InputEvent};
 use crate::Route;
-use crate::types::TaskPreset;
+use crate::history::HistoryPanel;
+use crate::live::{confirm_merge, merge_field, use_server_events, ListAction, LiveList};
+use crate::login::UserBar;
+use crate::api::{self, tasks::SaveOutcome};
+use crate::presets::load_presets;
+use crate::toast::{use_toaster, Toaster};
+use shared::TaskPreset;
 
 #[wasm_bindgen]
 extern "C" {
@@ -18,6 +21,69 @@ extern "C" {
     fn draw_gantt_chart(data: JsValue, on_select: &JsValue);
 }
 
+// The material table edits raw text; quantities are only parsed when saving
+fn materials_to_text(materials: &HashMap<String, Quantity>) -> HashMap<String, String> {
+    materials.iter().map(|(name, qty)| (name.clone(), qty.to_string())).collect()
+}
+
+// Every quantity that does not parse is named like the server names it ("materials.<name>")
+fn parse_materials(materials: &HashMap<String, String>) -> Result<HashMap<String, Quantity>, Vec<FieldError>> {
+    let mut parsed = HashMap::new();
+    let mut errors = Vec::new();
+    for (name, raw) in materials {
+        match Quantity::parse(raw) {
+            Ok(qty) => {
+                parsed.insert(name.clone(), qty);
+            }
+            Err(e) => errors.push(FieldError::new(format!("materials.{}", name), format!("{}: {}", name, e))),
+        }
+    }
+    if errors.is_empty() { Ok(parsed) } else { Err(errors) }
+}
+
+// Messages of the form inputs that failed the last save, keyed by field
+type FieldErrors = HashMap<String, String>;
+
+fn by_field(errors: &[FieldError]) -> FieldErrors {
+    errors.iter().map(|e| (e.field.clone(), e.message.clone())).collect()
+}
+
+// The task the form describes, or the inputs that need fixing first
+fn form_task(
+    user_id: &str, op_id: &str, start: chrono::DateTime<Utc>, duration: i64, materials: &HashMap<String, String>,
+    machine_ids: &[String], depends_on: &[TaskDependency],
+) -> Result<Task, Vec<FieldError>> {
+    let parsed = parse_materials(materials);
+    let mut task = Task::new(user_id.to_string(), op_id.to_string(), start, duration, parsed.clone().unwrap_or_default());
+    task.machine_ids = machine_ids.to_vec();
+    task.depends_on = depends_on.to_vec();
+    let mut errors = parsed.err().unwrap_or_default();
+    errors.extend(task.validate());
+    if errors.is_empty() { Ok(task) } else { Err(errors) }
+}
+
+// Like `Toaster::report`, also flagging the inputs the server rejected, or clearing them on success
+fn report_save<T>(toaster: &Toaster, field_errors: &UseStateHandle<FieldErrors>, result: Result<T, api::Error>) -> Option<T> {
+    match &result {
+        Ok(_) => field_errors.set(FieldErrors::new()),
+        Err(api::Error::Api(e)) => field_errors.set(by_field(&e.field_errors)),
+        Err(_) => {}
+    }
+    toaster.report(result)
+}
+
+// The tasks starting on `date` (YYYY-MM-DD, local time); none if it does not parse
+async fn load_day(date: &str) -> Result<Vec<Task>, api::Error> {
+    let midnight = |day: chrono::NaiveDate| Local.from_local_datetime(&day.and_hms_opt(0, 0, 0)?).earliest();
+    let Ok(day) = chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d") else {
+        return Ok(Vec::new());
+    };
+    match (midnight(day), day.succ_opt().and_then(midnight)) {
+        (Some(from), Some(to)) => api::tasks::list_starting(from.with_timezone(&Utc), to.with_timezone(&Utc)).await,
+        _ => Ok(Vec::new()),
+    }
+}
+
 // Struct to pass data to JS
 #[derive(serde::Serialize)]
 struct JsTask {
@@ -26,11 +92,108 @@ struct JsTask {
     resource: String,
     start: i64, // ms timestamp
     end: i64,
+    percent: f64,
+    // Comma separated ids of the rows this one waits for
+    dependencies: String,
+}
+
+// Chart rows are keyed by task id. A task drawn on more than one row gets a '#' and what sets
+// the row apart appended, so the id can be recovered when a row is clicked.
+const ROW_SEPARATOR: char = '#';
+
+// Suffix for the row showing a task's recorded execution next to its planned bar
+const ACTUAL_ROW_SUFFIX: &str = "#actual";
+
+// What the chart's lanes are grouped by
+#[derive(Clone, Copy, PartialEq)]
+enum GanttGrouping {
+    Worker,
+    // A task needing several machines is drawn once for each
+    Machine,
+}
+
+// How a task is named where it is picked by hand
+fn task_label(task: &Task) -> String {
+    format!("{} ({}, {})", task.operation_id, task.user_id, task.start_time.with_timezone(&Local).format("%H:%M"))
+}
+
+// (endpoint, button label) pairs allowed from each status
+fn transitions(status: TaskStatus) -> &'static [(&'static str, &'static str)] {
+    match status {
+        TaskStatus::Planned => &[("start", "Start"), ("cancel", "Cancel")],
+        TaskStatus::InProgress => &[("finish", "Finish"), ("cancel", "Cancel")],
+        TaskStatus::Done | TaskStatus::Cancelled => &[("reopen", "Reopen")],
+    }
+}
+
+async fn send_task(task: &Task, create: bool, force: bool) -> Result<SaveOutcome, api::Error> {
+    if create {
+        api::tasks::create(task, force).await
+    } else {
+        api::tasks::update(task, force).await
+    }
+}
+
+// Creates or updates a task; on a double-booking asks whether to save anyway and retries with ?force=true
+async fn save_task(task: &Task, create: bool) -> Result<SaveOutcome, api::Error> {
+    match send_task(task, create, false).await? {
+        SaveOutcome::Conflict(conflict) => {
+            let msg = format!("{}\nConflicting tasks: {}\n\nSave anyway?",
+                conflict.message, conflict.conflicting_ids.join(", "));
+            if gloo::dialogs::confirm(&msg) {
+                send_task(task, create, true).await
+            } else {
+                Ok(SaveOutcome::Conflict(conflict))
+            }
+        }
+        outcome => Ok(outcome),
+    }
+}
+
+// The form's edits of `base` applied on top of `latest`
+fn merge_task(base: &Task, mine: &Task, latest: &Task) -> Task {
+    Task {
+        user_id: merge_field(&base.user_id, &mine.user_id, &latest.user_id),
+        operation_id: merge_field(&base.operation_id, &mine.operation_id, &latest.operation_id),
+        start_time: merge_field(&base.start_time, &mine.start_time, &latest.start_time),
+        expected_duration_minutes: merge_field(
+            &base.expected_duration_minutes, &mine.expected_duration_minutes, &latest.expected_duration_minutes,
+        ),
+        materials: merge_field(&base.materials, &mine.materials, &latest.materials),
+        machine_ids: merge_field(&base.machine_ids, &mine.machine_ids, &latest.machine_ids),
+        depends_on: merge_field(&base.depends_on, &mine.depends_on, &latest.depends_on),
+        ..latest.clone()
+    }
+}
+
+// Saves `edited`, the form's version of `base`. If someone else saved the task in the meantime,
+// offers to apply the edits on top of their version or to drop them and load theirs.
+// Returns the task the form should show now, None to leave the form as it is.
+async fn update_task(base: &Task, mut edited: Task) -> Result<Option<Task>, api::Error> {
+    edited.version = base.version;
+    let stale = match save_task(&edited, false).await? {
+        SaveOutcome::Saved(saved) => return Ok(Some(saved)),
+        SaveOutcome::Conflict(_) => return Ok(None),
+        SaveOutcome::Stale(stale) => stale,
+    };
+    let latest = api::tasks::get(&edited.id).await?;
+    if !confirm_merge(&stale.message) {
+        return Ok(Some(latest));
+    }
+    match save_task(&merge_task(base, &edited, &latest), false).await? {
+        SaveOutcome::Saved(saved) => Ok(Some(saved)),
+        SaveOutcome::Conflict(_) => Ok(None),
+        // Changed yet again; leave the form as is so the next update tries once more
+        SaveOutcome::Stale(stale) => Err(api::Error::Api(stale)),
+    }
 }
 
 #[function_component(Home)]
 pub fn home() -> Html {
-    let tasks = use_state(Vec::new);
+    let session = api::auth::session();
+    let role = session.as_ref().map(|s| s.user.role).unwrap_or_default();
+    let username = session.map(|s| s.user.username).unwrap_or_default();
+    let tasks = use_reducer(LiveList::<Task>::default);
     let form_op_id = use_state(|| "".to_string());
     let form_user_id = use_state(|| "".to_string());
     let form_date = use_state(|| Local::now().format("%Y-%m-%d").to_string());
@@ -42,19 +205,33 @@ pub fn home() -> Html {
     let ai_prompt = use_state(|| "".to_string());
     let ai_suggestion = use_state(|| "".to_string());
     let selected_task_id = use_state(|| None::<String>);
-    let inventory = use_state(Vec::new);
+    // The selected task as it was when loaded into the form; updates are based on its version
+    let loaded_task = use_state(|| None::<Task>);
+    let field_errors = use_state(FieldErrors::new);
+    let inventory = use_reducer(LiveList::<InventoryItem>::default);
+    let forecast = use_state(InventoryForecast::default);
     let presets = use_state(|| HashMap::<String, TaskPreset>::new());
     let pending_preset_update = use_state(|| None::<(String, TaskPreset)>);
+    let workers = use_reducer(LiveList::<Worker>::default);
+    let machines = use_reducer(LiveList::<Machine>::default);
+    let form_machines = use_state(Vec::<String>::new);
+    let form_depends = use_state(Vec::<TaskDependency>::new);
+    let gantt_grouping = use_state(|| GanttGrouping::Worker);
+    let toaster = use_toaster();
 
-    // Fetch tasks
+    // Fetch the tasks of the day shown
     let fetch_tasks = {
         let tasks = tasks.clone();
+        let toaster = toaster.clone();
+        let form_date = form_date.clone();
         Callback::from(move |_| {
             let tasks = tasks.clone();
+            let toaster = toaster.clone();
+            let date = (*form_date).clone();
             wasm_bindgen_futures::spawn_local(async move {
-                let fetched: Vec<Task> = Request::get("http://localhost:8081/tasks")
-                    .send().await.unwrap().json().await.unwrap();
-                tasks.set(fetched);
+                if let Some(fetched) = toaster.report(load_day(&date).await) {
+                    tasks.dispatch(ListAction::Replace(fetched));
+                }
             });
         })
     };
@@ -68,29 +245,140 @@ pub fn home() -> Html {
     }
 
     // Fetch Inventory
-    {
+    let fetch_inventory = {
         let inventory = inventory.clone();
+        let toaster = toaster.clone();
+        Callback::from(move |_| {
+            let inventory = inventory.clone();
+            let toaster = toaster.clone();
+            wasm_bindgen_futures::spawn_local(async move {
+                if let Some(fetched) = toaster.report(api::inventory::list().await) {
+                    inventory.dispatch(ListAction::Replace(fetched));
+                }
+            });
+        })
+    };
+
+    {
+        let fetch_inventory = fetch_inventory.clone();
         use_effect_with_deps(move |_| {
+            fetch_inventory.emit(());
+            || {}
+        }, ());
+    }
+
+    // Fetch Workers
+    let fetch_workers = {
+        let workers = workers.clone();
+        let toaster = toaster.clone();
+        Callback::from(move |_| {
+            let workers = workers.clone();
+            let toaster = toaster.clone();
             wasm_bindgen_futures::spawn_local(async move {
-                let fetched: Vec<InventoryItem> = Request::get("http://localhost:8081/inventory")
-                    .send().await.unwrap().json().await.unwrap();
-                inventory.set(fetched);
+                if let Some(fetched) = toaster.report(api::workers::list().await) {
+                    workers.dispatch(ListAction::Replace(fetched));
+                }
+            });
+        })
+    };
+
+    {
+        let fetch_workers = fetch_workers.clone();
+        use_effect_with_deps(move |_| {
+            fetch_workers.emit(());
+            || {}
+        }, ());
+    }
+
+    // Fetch Machines
+    let fetch_machines = {
+        let machines = machines.clone();
+        let toaster = toaster.clone();
+        Callback::from(move |_| {
+            let machines = machines.clone();
+            let toaster = toaster.clone();
+            wasm_bindgen_futures::spawn_local(async move {
+                if let Some(fetched) = toaster.report(api::machines::list().await) {
+                    machines.dispatch(ListAction::Replace(fetched));
+                }
             });
+        })
+    };
+
+    {
+        let fetch_machines = fetch_machines.clone();
+        use_effect_with_deps(move |_| {
+            fetch_machines.emit(());
+            || {}
         }, ());
     }
 
     // Load Presets
     {
         let presets = presets.clone();
+        let toaster = toaster.clone();
         use_effect_with_deps(move |_| {
-            let loaded: HashMap<String, TaskPreset> = LocalStorage::get("task_presets").unwrap_or_default();
-            presets.set(loaded);
+            wasm_bindgen_futures::spawn_local(async move {
+                if let Some(loaded) = toaster.report(load_presets(&toaster).await) {
+                    presets.set(loaded);
+                }
+            });
         }, ());
     }
 
-    // Handle selection from Chart
-    let on_select_task = {
-        let tasks = tasks.clone();
+    // Changes made anywhere, including our own echoed back, are patched in as they happen
+    {
+        let tasks = tasks.dispatcher();
+        let inventory = inventory.dispatcher();
+        let workers = workers.dispatcher();
+        let machines = machines.dispatcher();
+        let presets = presets.clone();
+        let fetch_tasks = fetch_tasks.clone();
+        let fetch_inventory = fetch_inventory.clone();
+        let fetch_workers = fetch_workers.clone();
+        let fetch_machines = fetch_machines.clone();
+        let toaster = toaster.clone();
+        use_server_events(Callback::from(move |event: ServerEvent| match event {
+            ServerEvent::Changed(entry) => match entry.entity {
+                AuditEntity::Task => match ListAction::from_entry(&entry) {
+                    Some(action) => tasks.dispatch(action),
+                    None => fetch_tasks.emit(()),
+                },
+                AuditEntity::InventoryItem => match ListAction::from_entry(&entry) {
+                    Some(action) => inventory.dispatch(action),
+                    None => fetch_inventory.emit(()),
+                },
+                AuditEntity::Preset => {
+                    let presets = presets.clone();
+                    let toaster = toaster.clone();
+                    wasm_bindgen_futures::spawn_local(async move {
+                        if let Some(loaded) = toaster.report(api::presets::list().await) {
+                            presets.set(loaded);
+                        }
+                    });
+                }
+                AuditEntity::Worker => match ListAction::from_entry(&entry) {
+                    Some(action) => workers.dispatch(action),
+                    None => fetch_workers.emit(()),
+                },
+                AuditEntity::Machine => match ListAction::from_entry(&entry) {
+                    Some(action) => machines.dispatch(action),
+                    None => fetch_machines.emit(()),
+                },
+                // Their tasks arrive as task changes of their own
+                AuditEntity::Job => {}
+            },
+            ServerEvent::Resync => {
+                fetch_tasks.emit(());
+                fetch_inventory.emit(());
+                fetch_workers.emit(());
+                fetch_machines.emit(());
+            }
+        }));
+    }
+
+    // Fill the form from a task and select it
+    let load_form = {
         let form_op_id = form_op_id.clone();
         let form_user_id = form_user_id.clone();
         let form_date = form_date.clone();
@@ -99,33 +387,69 @@ pub fn home() -> Html {
         let form_dur_hour = form_dur_hour.clone();
         let form_dur_min = form_dur_min.clone();
         let form_materials = form_materials.clone();
+        let form_machines = form_machines.clone();
+        let form_depends = form_depends.clone();
         let selected_task_id = selected_task_id.clone();
+        let loaded_task = loaded_task.clone();
+        let field_errors = field_errors.clone();
+
+        Callback::from(move |task: Task| {
+            field_errors.set(FieldErrors::new());
+            selected_task_id.set(Some(task.id.clone()));
+            form_op_id.set(task.operation_id.clone());
+            form_user_id.set(task.user_id.clone());
+            let local_dt = task.start_time.with_timezone(&Local);
+            form_date.set(local_dt.format("%Y-%m-%d").to_string());
+            form_start_hour.set(local_dt.format("%H").to_string());
+            form_start_min.set(local_dt.format("%M").to_string());
+            form_dur_hour.set((task.expected_duration_minutes / 60).to_string());
+            form_dur_min.set((task.expected_duration_minutes % 60).to_string());
+            form_materials.set(materials_to_text(&task.materials));
+            form_machines.set(task.machine_ids.clone());
+            form_depends.set(task.depends_on.clone());
+            loaded_task.set(Some(task));
+        })
+    };
+
+    // Handle selection from Chart
+    let on_select_task = {
+        let tasks = tasks.clone();
+        let load_form = load_form.clone();
 
         Callback::from(move |id: String| {
             if let Some(task) = tasks.iter().find(|t| t.id == id) {
-                selected_task_id.set(Some(id));
-                form_op_id.set(task.operation_id.clone());
-                form_user_id.set(task.user_id.clone());
-                let local_dt = task.start_time.with_timezone(&Local);
-                form_date.set(local_dt.format("%Y-%m-%d").to_string());
-                form_start_hour.set(local_dt.format("%H").to_string());
-                form_start_min.set(local_dt.format("%M").to_string());
-                form_dur_hour.set((task.expected_duration_minutes / 60).to_string());
-                form_dur_min.set((task.expected_duration_minutes % 60).to_string());
-                form_materials.set(task.materials.clone());
+                load_form.emit(task.clone());
             }
         })
     };
 
+    // Re-project stock whenever the schedule or the inventory changes
+    {
+        let forecast = forecast.clone();
+        let toaster = toaster.clone();
+        use_effect_with_deps(move |_| {
+            wasm_bindgen_futures::spawn_local(async move {
+                if let Some(fetched) = toaster.report(api::inventory::forecast().await) {
+                    forecast.set(fetched);
+                }
+            });
+            || {}
+        }, (tasks.clone(), inventory.clone()));
+    }
+
     // Effect to redraw chart when tasks change
     {
         let tasks = tasks.clone();
         let form_date = form_date.clone();
-        use_effect_with_deps(move |(tasks, date_handle, on_select)| {
+        let forecast = forecast.clone();
+        let workers = workers.clone();
+        let machines = machines.clone();
+        let grouping = gantt_grouping.clone();
+        use_effect_with_deps(move |(tasks, date_handle, on_select, forecast, workers, machines, grouping)| {
             let date_str = &**date_handle;
             let selected_date = chrono::NaiveDate::parse_from_str(date_str, "%Y-%m-%d").ok();
 
-            let js_data: Vec<JsTask> = tasks.iter()
+            let mut rows: Vec<(String, JsTask)> = tasks.iter()
                 .filter(|t| {
                     if let Some(d) = selected_date {
                         t.start_time.with_timezone(&Local).date_naive() == d
@@ -133,24 +457,80 @@ pub fn home() -> Html {
                         true
                     }
                 })
-                .map(|t| {
+                .flat_map(|t| {
                 let end_time = t.start_time + Duration::minutes(t.expected_duration_minutes);
-                JsTask {
-                    id: t.id.clone(),
-                    name: t.operation_id.clone(),
-                    resource: t.user_id.clone(),
-                    start: t.start_time.timestamp_millis(),
-                    end: end_time.timestamp_millis(),
-                }
+                let name = match t.status {
+                    TaskStatus::Cancelled => format!("{} [Cancelled]", t.operation_id),
+                    _ if forecast.shortages.iter().any(|s| s.task_id == t.id) => {
+                        format!("\u{26a0} {} [Shortage]", t.operation_id)
+                    }
+                    _ => t.operation_id.clone(),
+                };
+                // Lanes the task shows up in, with the suffix that keeps each of its rows apart.
+                // Workers and machines deleted since keep their id.
+                let lanes: Vec<(String, String)> = match **grouping {
+                    GanttGrouping::Worker => {
+                        let worker = workers.iter().find(|w| w.id == t.user_id)
+                            .map_or_else(|| t.user_id.clone(), |w| w.display_name.clone());
+                        vec![(String::new(), worker)]
+                    }
+                    GanttGrouping::Machine if t.machine_ids.is_empty() => vec![(String::new(), "No machine".to_string())],
+                    GanttGrouping::Machine => t.machine_ids.iter().map(|id| {
+                        let machine = machines.iter().find(|m| m.id == *id).map_or_else(|| id.clone(), |m| m.name.clone());
+                        (format!("{}machine:{}", ROW_SEPARATOR, id), machine)
+                    }).collect(),
+                };
+                lanes.into_iter().flat_map(|(suffix, lane)| {
+                    let planned = JsTask {
+                        id: format!("{}{}", t.id, suffix),
+                        name: name.clone(),
+                        resource: lane.clone(),
+                        start: t.start_time.timestamp_millis(),
+                        end: end_time.timestamp_millis(),
+                        percent: if t.status == TaskStatus::Done { 100.0 } else { 0.0 },
+                        dependencies: String::new(),
+                    };
+                    // Actual bar: finished tasks use the recorded duration, running ones extend to now
+                    let actual = t.actual_start_time.map(|started| JsTask {
+                        id: format!("{}{}{}", t.id, suffix, ACTUAL_ROW_SUFFIX),
+                        name: format!("{} (actual)", t.operation_id),
+                        resource: "Actual".to_string(),
+                        start: started.timestamp_millis(),
+                        end: t.actual_end_time().unwrap_or_else(Utc::now).max(started + Duration::minutes(1)).timestamp_millis(),
+                        percent: if t.status == TaskStatus::Done { 100.0 } else { 0.0 },
+                        dependencies: String::new(),
+                    });
+                    std::iter::once(planned).chain(actual).map(move |row| (lane.clone(), row))
+                }).collect::<Vec<_>>()
             }).collect();
-            
+            // Keep each lane's rows together, in start order within it
+            rows.sort_by(|a, b| a.0.cmp(&b.0));
+            let mut js_data: Vec<JsTask> = rows.into_iter().map(|(_, row)| row).collect();
+
+            // Arrows run from each planned row of an upstream task on the chart to those of the task
+            let task_of = |row: &str| row.split(ROW_SEPARATOR).next().unwrap_or_default().to_string();
+            let planned_rows: Vec<String> = js_data.iter()
+                .map(|row| row.id.clone())
+                .filter(|id| !id.ends_with(ACTUAL_ROW_SUFFIX))
+                .collect();
+            for row in js_data.iter_mut().filter(|row| !row.id.ends_with(ACTUAL_ROW_SUFFIX)) {
+                let Some(task) = tasks.iter().find(|t| t.id == task_of(&row.id)) else {
+                    continue;
+                };
+                row.dependencies = planned_rows.iter()
+                    .filter(|upstream| task.depends_on.iter().any(|d| d.task_id == task_of(upstream)))
+                    .cloned()
+                    .collect::<Vec<_>>()
+                    .join(",");
+            }
+
             let closure_handle = if !js_data.is_empty() {
                 let val = serde_wasm_bindgen::to_value(&js_data).unwrap();
                 
                 let cb = on_select.clone();
                 let closure = wasm_bindgen::closure::Closure::<dyn FnMut(JsValue)>::new(move |id: JsValue| {
                     if let Some(id_str) = id.as_string() {
-                        cb.emit(id_str);
+                        cb.emit(id_str.split(ROW_SEPARATOR).next().unwrap_or_default().to_string());
                     }
                 });
                 
@@ -160,7 +540,7 @@ pub fn home() -> Html {
                 None
             };
             move || drop(closure_handle)
-        }, (tasks, form_date, on_select_task));
+        }, (tasks, form_date, on_select_task, forecast, workers, machines, grouping));
     }
 
     // Sync form input with selection
@@ -194,7 +574,11 @@ pub fn home() -> Html {
         let dur_h = form_dur_hour.clone();
         let dur_m = form_dur_min.clone();
         let mat = form_materials.clone();
+        let machine_ids = form_machines.clone();
+        let depends_on = form_depends.clone();
         let fetch = fetch_tasks.clone();
+        let toaster = toaster.clone();
+        let field_errors = field_errors.clone();
         
         Callback::from(move |_| {
             let d_str = (*date).clone();
@@ -208,18 +592,20 @@ pub fn home() -> Html {
             let dm: i64 = dur_m.parse().unwrap_or(0);
             let duration = dh * 60 + dm;
 
-            let task = Task::new(
-                (*u_id).clone(),
-                (*op_id).clone(),
-                start_time,
-                duration,
-                (*mat).clone()
-            );
+            let task = match form_task(&u_id, &op_id, start_time, duration, &mat, &machine_ids, &depends_on) {
+                Ok(task) => task,
+                Err(errors) => {
+                    field_errors.set(by_field(&errors));
+                    toaster.error("Please fix the highlighted fields");
+                    return;
+                }
+            };
             
             let fetch = fetch.clone();
+            let toaster = toaster.clone();
+            let field_errors = field_errors.clone();
             wasm_bindgen_futures::spawn_local(async move {
-                Request::post("http://localhost:8081/tasks")
-                    .json(&task).unwrap().send().await.unwrap();
+                report_save(&toaster, &field_errors, save_task(&task, true).await);
                 fetch.emit(());
             });
         })
@@ -235,11 +621,24 @@ pub fn home() -> Html {
         let dur_h = form_dur_hour.clone();
         let dur_m = form_dur_min.clone();
         let mat = form_materials.clone();
+        let machine_ids = form_machines.clone();
+        let depends_on = form_depends.clone();
         let fetch = fetch_tasks.clone();
+        let toaster = toaster.clone();
         let selected_task_id = selected_task_id.clone();
+        let loaded_task = loaded_task.clone();
+        let tasks = tasks.clone();
+        let load_form = load_form.clone();
+        let field_errors = field_errors.clone();
         
         Callback::from(move |_| {
             if let Some(id) = &*selected_task_id {
+                // Selected by typing its operation and worker rather than loaded: base it on the list
+                let Some(base) = loaded_task.as_ref().filter(|t| t.id == *id)
+                    .or_else(|| tasks.iter().find(|t| t.id == *id))
+                    .cloned() else {
+                    return;
+                };
                 let d_str = (*date).clone();
                 let h: u32 = start_h.parse().unwrap_or(9);
                 let m: u32 = start_m.parse().unwrap_or(0);
@@ -251,19 +650,24 @@ pub fn home() -> Html {
                 let dm: i64 = dur_m.parse().unwrap_or(0);
                 let duration = dh * 60 + dm;
 
-                let mut task = Task::new(
-                    (*u_id).clone(),
-                    (*op_id).clone(),
-                    start_time,
-                    duration,
-                    (*mat).clone()
-                );
+                let mut task = match form_task(&u_id, &op_id, start_time, duration, &mat, &machine_ids, &depends_on) {
+                    Ok(task) => task,
+                    Err(errors) => {
+                        field_errors.set(by_field(&errors));
+                        toaster.error("Please fix the highlighted fields");
+                        return;
+                    }
+                };
                 task.id = id.clone();
                 
                 let fetch = fetch.clone();
+                let toaster = toaster.clone();
+                let load_form = load_form.clone();
+                let field_errors = field_errors.clone();
                 wasm_bindgen_futures::spawn_local(async move {
-                    Request::post("http://localhost:8081/tasks")
-                        .json(&task).unwrap().send().await.unwrap();
+                    if let Some(Some(shown)) = report_save(&toaster, &field_errors, update_task(&base, task).await) {
+                        load_form.emit(shown);
+                    }
                     fetch.emit(());
                 });
             }
@@ -277,76 +681,108 @@ pub fn home() -> Html {
         let form_op_id = form_op_id.clone();
         let form_user_id = form_user_id.clone();
         let form_materials = form_materials.clone();
+        let form_machines = form_machines.clone();
+        let form_depends = form_depends.clone();
+        let toaster = toaster.clone();
         
         Callback::from(move |_| {
             if let Some(id) = &*selected_task_id {
                 let id = id.clone();
+                let toaster = toaster.clone();
                 let fetch = fetch.clone();
                 let selected_task_id = selected_task_id.clone();
                 let form_op_id = form_op_id.clone();
                 let form_user_id = form_user_id.clone();
                 let form_materials = form_materials.clone();
+                let form_machines = form_machines.clone();
+                let form_depends = form_depends.clone();
 
                 wasm_bindgen_futures::spawn_local(async move {
-                    Request::delete(&format!("http://localhost:8081/tasks/{}", id))
-                        .send().await.unwrap();
+                    if toaster.report(api::tasks::delete(&id).await).is_none() {
+                        return;
+                    }
                     fetch.emit(());
                     selected_task_id.set(None);
                     form_op_id.set("".to_string());
                     form_user_id.set("".to_string());
                     form_materials.set(HashMap::new());
+                    form_machines.set(Vec::new());
+                    form_depends.set(Vec::new());
+                });
+            }
+        })
+    };
+
+    // Execution Handler: start / finish / reopen / cancel the selected task.
+    // Finishing takes the materials out of stock and reopening puts them back.
+    let on_transition = {
+        let selected_task_id = selected_task_id.clone();
+        let fetch = fetch_tasks.clone();
+        let fetch_inventory = fetch_inventory.clone();
+        let toaster = toaster.clone();
+        let loaded_task = loaded_task.clone();
+
+        Callback::from(move |action: &'static str| {
+            if let Some(id) = &*selected_task_id {
+                let id = id.clone();
+                let toaster = toaster.clone();
+                let fetch = fetch.clone();
+                let fetch_inventory = fetch_inventory.clone();
+                let loaded_task = loaded_task.clone();
+                wasm_bindgen_futures::spawn_local(async move {
+                    match api::tasks::transition(&id, action).await {
+                        // Our own write: later updates from the form build on its version
+                        Ok(task) => loaded_task.set(Some(task)),
+                        Err(e) => toaster.error(format!("Could not {} task:\n{}", action, e)),
+                    }
+                    fetch.emit(());
+                    fetch_inventory.emit(());
                 });
             }
         })
     };
 
+    let selected_task = selected_task_id.as_ref()
+        .and_then(|id| tasks.iter().find(|t| t.id == *id))
+        .cloned();
+
     // AI Suggest Handler
     let on_suggest = {
         let prompt = ai_prompt.clone();
         let suggestion = ai_suggestion.clone();
+        let u_id = form_user_id.clone();
+        let dur_h = form_dur_hour.clone();
+        let dur_m = form_dur_min.clone();
+        let toaster = toaster.clone();
         
         Callback::from(move |_| {
             let prompt_text = (*prompt).clone();
             let suggestion = suggestion.clone();
+            let toaster = toaster.clone();
+            let dh: i64 = dur_h.parse().unwrap_or(1);
+            let dm: i64 = dur_m.parse().unwrap_or(0);
+            let user_id = (*u_id).clone();
             
             wasm_bindgen_futures::spawn_local(async move {
-                let body = serde_json::json!({ "requirement": prompt_text });
-                let resp = Request::post("http://localhost:8081/suggest")
-                    .header("Content-Type", "application/json")
-                    .body(body.to_string()).unwrap()
-                    .send().await.unwrap();
-                    
-                if resp.ok() {
-                    let json: serde_json::Value = resp.json().await.unwrap();
-                    suggestion.set(format!("Time: {} \nReason: {}", 
-                        json["suggested_start_time"], json["reason"]));
+                if let Some(s) = toaster.report(api::schedule::suggest(&prompt_text, &user_id, dh * 60 + dm).await) {
+                    suggestion.set(format!("Time: {} \nReason: {}",
+                        s.suggested_start_time.with_timezone(&Local).format("%Y-%m-%d %H:%M"), s.reason));
                 }
             });
         })
     };
 
-    // Helper to calculate leftover
+    // Helper to calculate leftover, in the unit the material is stocked in
     let calculate_leftover = |mat_name: &str, req_qty: &str, inventory: &Vec<InventoryItem>| -> String {
-        let inv_item = inventory.iter().find(|i| i.name == mat_name);
-        if let Some(item) = inv_item {
-            // Simple regex to split number and unit
-            let re = Regex::new(r"^([\d\.\+\-eE]+)\s*(.*)$").unwrap();
-            
-            let req_caps = re.captures(req_qty);
-            
-            if let Some(rc) = req_caps {
-                let r_val: f64 = rc[1].parse().unwrap_or(0.0);
-                let r_unit = rc.get(2).map_or("", |m| m.as_str()).trim();
-                
-                if r_unit == item.unit {
-                    return format!("{:.2} {}", item.quantity - r_val, item.unit);
-                } else {
-                    return "Unit Mismatch".to_string();
-                }
-            }
-            "Parse Error".to_string()
-        } else {
-            "Not in Inventory".to_string()
+        let Some(item) = inventory.iter().find(|i| i.name == mat_name) else {
+            return "Not in Inventory".to_string();
+        };
+        let Ok(required) = Quantity::parse(req_qty) else {
+            return "Parse Error".to_string();
+        };
+        match item.quantity.checked_sub(&required) {
+            Some(left) => left.to_string(),
+            None => "Unit Mismatch".to_string(),
         }
     };
 
@@ -389,9 +825,12 @@ pub fn home() -> Html {
         })
     };
 
+    // Switch days: load that day's tasks and select the first one, or clear the form
     let on_date_change = {
         let form_date = form_date.clone();
-        let tasks = tasks.clone();
+        let tasks = tasks.dispatcher();
+        let toaster = toaster.clone();
+        let load_form = load_form.clone();
         let selected_task_id = selected_task_id.clone();
         let form_op_id = form_op_id.clone();
         let form_user_id = form_user_id.clone();
@@ -400,46 +839,47 @@ pub fn home() -> Html {
         let form_dur_hour = form_dur_hour.clone();
         let form_dur_min = form_dur_min.clone();
         let form_materials = form_materials.clone();
+        let form_machines = form_machines.clone();
+        let form_depends = form_depends.clone();
 
         Callback::from(move |e: InputEvent| {
             let date_val = e.target_unchecked_into::<web_sys::HtmlInputElement>().value();
             form_date.set(date_val.clone());
 
-            let selected_date = chrono::NaiveDate::parse_from_str(&date_val, "%Y-%m-%d").ok();
-            
-            let mut tasks_on_date: Vec<Task> = tasks.iter()
-                .filter(|t| {
-                    if let Some(d) = selected_date {
-                        t.start_time.with_timezone(&Local).date_naive() == d
-                    } else {
-                        false
-                    }
-                })
-                .cloned()
-                .collect();
-            
-            tasks_on_date.sort_by_key(|t| t.start_time);
-
-            if let Some(first_task) = tasks_on_date.first() {
-                selected_task_id.set(Some(first_task.id.clone()));
-                form_op_id.set(first_task.operation_id.clone());
-                form_user_id.set(first_task.user_id.clone());
-                let local_dt = first_task.start_time.with_timezone(&Local);
-                form_start_hour.set(local_dt.format("%H").to_string());
-                form_start_min.set(local_dt.format("%M").to_string());
-                form_dur_hour.set((first_task.expected_duration_minutes / 60).to_string());
-                form_dur_min.set((first_task.expected_duration_minutes % 60).to_string());
-                form_materials.set(first_task.materials.clone());
-            } else {
-                selected_task_id.set(None);
-                form_op_id.set("".to_string());
-                form_user_id.set("".to_string());
-                form_materials.set(HashMap::new());
-                form_start_hour.set("09".to_string());
-                form_start_min.set("00".to_string());
-                form_dur_hour.set("1".to_string());
-                form_dur_min.set("00".to_string());
-            }
+            let tasks = tasks.clone();
+            let toaster = toaster.clone();
+            let load_form = load_form.clone();
+            let selected_task_id = selected_task_id.clone();
+            let form_op_id = form_op_id.clone();
+            let form_user_id = form_user_id.clone();
+            let form_start_hour = form_start_hour.clone();
+            let form_start_min = form_start_min.clone();
+            let form_dur_hour = form_dur_hour.clone();
+            let form_dur_min = form_dur_min.clone();
+            let form_materials = form_materials.clone();
+            let form_machines = form_machines.clone();
+            let form_depends = form_depends.clone();
+            wasm_bindgen_futures::spawn_local(async move {
+                let Some(tasks_on_date) = toaster.report(load_day(&date_val).await) else {
+                    return;
+                };
+                // Already in start-time order
+                if let Some(first_task) = tasks_on_date.first() {
+                    load_form.emit(first_task.clone());
+                } else {
+                    selected_task_id.set(None);
+                    form_op_id.set("".to_string());
+                    form_user_id.set("".to_string());
+                    form_materials.set(HashMap::new());
+                    form_machines.set(Vec::new());
+                    form_depends.set(Vec::new());
+                    form_start_hour.set("09".to_string());
+                    form_start_min.set("00".to_string());
+                    form_dur_hour.set("1".to_string());
+                    form_dur_min.set("00".to_string());
+                }
+                tasks.dispatch(ListAction::Replace(tasks_on_date));
+            });
         })
     };
 
@@ -449,6 +889,7 @@ pub fn home() -> Html {
         let form_dur_hour = form_dur_hour.clone();
         let form_dur_min = form_dur_min.clone();
         let form_materials = form_materials.clone();
+        let form_machines = form_machines.clone();
         let presets = presets.clone();
         
         Callback::from(move |e: InputEvent| {
@@ -456,9 +897,18 @@ pub fn home() -> Html {
             form_op_id.set(val.clone());
             
             if let Some(preset) = presets.get(&val) {
-                form_dur_hour.set((preset.duration_minutes / 60).to_string());
-                form_dur_min.set((preset.duration_minutes % 60).to_string());
-                form_materials.set(preset.materials.clone());
+                form_dur_hour.set((preset.total_minutes() / 60).to_string());
+                form_dur_min.set((preset.total_minutes() % 60).to_string());
+                form_materials.set(materials_to_text(&preset.materials));
+                // Keep only machines the operation may use; with a single choice, pick it
+                let mut machine_ids: Vec<String> = form_machines.iter()
+                    .filter(|id| preset.allowed_machines.is_empty() || preset.allowed_machines.contains(id))
+                    .cloned()
+                    .collect();
+                if let [only] = preset.allowed_machines.as_slice() {
+                    machine_ids = vec![only.clone()];
+                }
+                form_machines.set(machine_ids);
             }
         })
     };
@@ -469,6 +919,8 @@ pub fn home() -> Html {
         let form_dur_min = form_dur_min.clone();
         let form_materials = form_materials.clone();
         let pending_preset_update = pending_preset_update.clone();
+        let presets = presets.clone();
+        let toaster = toaster.clone();
         
         Callback::from(move |_| {
             let op_id = (*form_op_id).clone();
@@ -478,9 +930,20 @@ pub fn home() -> Html {
             let dm: i64 = form_dur_min.parse().unwrap_or(0);
             let duration = dh * 60 + dm;
             
+            let materials = match parse_materials(&form_materials) {
+                Ok(materials) => materials,
+                Err(errors) => {
+                    let messages: Vec<String> = errors.into_iter().map(|e| e.message).collect();
+                    toaster.error(format!("Invalid material quantity - {}", messages.join("; ")));
+                    return;
+                }
+            };
+            // The form holds the booked time, which includes the setup
+            let existing = presets.get(&op_id).cloned().unwrap_or_default();
             let new_preset = TaskPreset {
-                duration_minutes: duration,
-                materials: (*form_materials).clone(),
+                duration_minutes: (duration - existing.setup_minutes).max(0),
+                materials,
+                ..existing
             };
             
             pending_preset_update.set(Some((op_id, new_preset)));
@@ -490,14 +953,23 @@ pub fn home() -> Html {
     let on_confirm_preset = {
         let pending_preset_update = pending_preset_update.clone();
         let presets = presets.clone();
+        let toaster = toaster.clone();
         
         Callback::from(move |_| {
             if let Some((op_id, new_preset)) = &*pending_preset_update {
                 let mut current_presets = (*presets).clone();
                 current_presets.insert(op_id.clone(), new_preset.clone());
-                LocalStorage::set("task_presets", &current_presets).unwrap();
                 presets.set(current_presets);
                 pending_preset_update.set(None);
+
+                let op_id = op_id.clone();
+                let new_preset = new_preset.clone();
+                let toaster = toaster.clone();
+                wasm_bindgen_futures::spawn_local(async move {
+                    if toaster.report(api::presets::save(&op_id, &new_preset).await).is_some() {
+                        toaster.success(format!("Saved preset '{}'", op_id));
+                    }
+                });
             }
         })
     };
@@ -564,12 +1036,48 @@ pub fn home() -> Html {
         }
     };
 
+    let on_add_dependency = {
+        let form_depends = form_depends.clone();
+        Callback::from(move |e: Event| {
+            let select = e.target_unchecked_into::<web_sys::HtmlSelectElement>();
+            let task_id = select.value();
+            // Back to the prompt, ready for the next one
+            select.set_value("");
+            if task_id.is_empty() {
+                return;
+            }
+            let mut deps = (*form_depends).clone();
+            deps.push(TaskDependency { task_id, lag_minutes: 0 });
+            form_depends.set(deps);
+        })
+    };
+
+    // The catalog entry for the operation typed into the form, if there is one
+    let form_operation = presets.get(&*form_op_id);
+    let machine_allowed = |id: &String| {
+        form_operation.is_none_or(|op| op.allowed_machines.is_empty() || op.allowed_machines.contains(id))
+            || form_machines.contains(id)
+    };
+    let mut operations: Vec<(&String, &TaskPreset)> = presets.iter().collect();
+    operations.sort_by_key(|(k, _)| *k);
+
+    // Bootstrap turns an input red with `is-invalid`; its message goes right below it
+    let invalid = |field: &str| if field_errors.contains_key(field) { "is-invalid" } else { "" };
+    let feedback = |field: &str| match field_errors.get(field) {
+        Some(message) => html! { <div class="invalid-feedback d-block">{message}</div> },
+        None => html! {},
+    };
+
     html! {
         <div class="row">
             <div class="col-12 mb-3">
                 <Link<Route> to={Route::Home} classes="btn btn-outline-primary me-2">{"Gantt Chart"}</Link<Route>>
                 <Link<Route> to={Route::Inventory} classes="btn btn-outline-success">{"Inventory"}</Link<Route>>
                 <Link<Route> to={Route::Presets} classes="btn btn-outline-info ms-2">{"Task Presets"}</Link<Route>>
+                <Link<Route> to={Route::Workers} classes="btn btn-outline-dark ms-2">{"Workers"}</Link<Route>>
+                <Link<Route> to={Route::Machines} classes="btn btn-outline-secondary ms-2">{"Machines"}</Link<Route>>
+                <Link<Route> to={Route::Jobs} classes="btn btn-outline-warning ms-2">{"Jobs"}</Link<Route>>
+                <UserBar />
             </div>
 
             <div class="col-md-8">
@@ -580,178 +1088,327 @@ pub fn home() -> Html {
                         value={(*form_date).clone()}
                         oninput={on_date_change} />
                 </div>
+                <div class="btn-group btn-group-sm mb-2" role="group">
+                    <span class="align-self-center me-2">{"Group by:"}</span>
+                    {for [(GanttGrouping::Worker, "Worker"), (GanttGrouping::Machine, "Machine")].into_iter().map(|(grouping, label)| {
+                        let gantt_grouping = gantt_grouping.clone();
+                        let class = if *gantt_grouping == grouping { "btn btn-secondary" } else { "btn btn-outline-secondary" };
+                        html! {
+                            <button {class} onclick={Callback::from(move |_| gantt_grouping.set(grouping))}>{label}</button>
+                        }
+                    })}
+                </div>
                 <div id="chart_div" style="width: 100%; height: 400px; border: 1px solid #ccc;"></div>
                 <button onclick={let fetch = fetch_tasks.clone(); move |_| fetch.emit(())} class="btn btn-secondary mt-2">{"Refresh Data"}</button>
+
+                if !forecast.shortages.is_empty() {
+                    <div class="alert alert-warning mt-3">
+                        <h5>{"Projected Material Shortages"}</h5>
+                        <ul class="mb-0">
+                            {for forecast.shortages.iter().map(|s| {
+                                let op = tasks.iter().find(|t| t.id == s.task_id)
+                                    .map(|t| format!("{} ({})", t.operation_id, t.user_id))
+                                    .unwrap_or_else(|| s.task_id.clone());
+                                html! {
+                                    <li>{format!("{}: runs out at {} on {}, projected {}",
+                                        s.material, op, s.start_time.with_timezone(&Local).format("%Y-%m-%d %H:%M"),
+                                        s.projected)}</li>
+                                }
+                            })}
+                        </ul>
+                    </div>
+                }
                 
                 <datalist id="inventory-list">
                     {for inventory.iter().map(|i| html! { <option value={i.name.clone()} /> })}
                 </datalist>
+                // Only workers with the skills the chosen operation needs
+                <datalist id="worker-list">
+                    {for workers.iter()
+                        .filter(|w| form_operation.is_none_or(|op| op.required_skills.iter().all(|s| w.skills.contains(s))))
+                        .map(|w| html! { <option value={w.id.clone()}>{&w.display_name}</option> })}
+                </datalist>
                 <datalist id="preset-list">
-                    {for presets.keys().map(|k| html! { <option value={k.clone()} /> })}
+                    {for operations.iter().map(|(k, op)| {
+                        let mut label = format!("{} min", op.total_minutes());
+                        if !op.required_skills.is_empty() {
+                            label.push_str(&format!(", needs {}", op.required_skills.join(", ")));
+                        }
+                        html! { <option value={k.to_string()}>{label}</option> }
+                    })}
                 </datalist>
 
-                <h4 class="mt-4">{"Material Requirements"}</h4>
-                <table class="table table-bordered">
-                    <thead>
-                        <tr>
-                            <th>{"Material Name"}</th>
-                            <th>{"Required Quantity"}</th>
-                            <th>{"Est. Leftover"}</th>
-                            <th>{"Action"}</th>
-                        </tr>
-                    </thead>
-                    <tbody>
-                        {for form_materials.iter().map(|(name, qty)| {
-                            let name_c = name.clone();
-                            let name_c2 = name.clone();
-                            let name_delete = name.clone();
-                            let update_key = update_material_key.clone();
-                            let update_val = update_material_val.clone();
-                            let delete = delete_material.clone();
-                            let leftover = calculate_leftover(name, qty, &inventory);
+                if role.can_plan() {
+                    <h4 class="mt-4">{"Material Requirements"}</h4>
+                    <table class="table table-bordered">
+                        <thead>
+                            <tr>
+                                <th>{"Material Name"}</th>
+                                <th>{"Required Quantity"}</th>
+                                <th>{"Est. Leftover"}</th>
+                                <th>{"Action"}</th>
+                            </tr>
+                        </thead>
+                        <tbody>
+                            {for form_materials.iter().map(|(name, qty)| {
+                                let name_c = name.clone();
+                                let name_c2 = name.clone();
+                                let name_delete = name.clone();
+                                let update_key = update_material_key.clone();
+                                let update_val = update_material_val.clone();
+                                let delete = delete_material.clone();
+                                let leftover = calculate_leftover(name, qty, &inventory);
                             
-                            let is_valid = inventory.iter().any(|i| i.name == *name);
-                            let inv_item = inventory.iter().find(|i| i.name == *name);
+                                let is_valid = inventory.iter().any(|i| i.name == *name);
+                                let inv_item = inventory.iter().find(|i| i.name == *name);
                             
-                            // Extract numeric value for input if unit exists
-                            let (num_val, unit_label) = if let Some(item) = inv_item {
-                                let re = Regex::new(r"^([\d\.\+\-eE]+)").unwrap();
-                                let val = re.captures(qty).map(|c| c[1].to_string()).unwrap_or_default();
-                                (val, Some(item.unit.clone()))
-                            } else {
-                                (qty.clone(), None)
-                            };
-
-                            html! {
-                                <tr key={name.clone()}>
-                                    <td>
-                                        <input class={classes!("form-control", if !is_valid { "is-invalid" } else { "" })} 
-                                            list="inventory-list"
-                                            value={name.clone()} 
-                                            onchange={Callback::from(move |e: Event| {
-                                                let val = e.target_unchecked_into::<web_sys::HtmlInputElement>().value();
-                                                update_key.emit((name_c.clone(), val));
-                                            })} />
-                                    </td>
-                                    <td>
-                                        {if let Some(unit) = unit_label {
-                                            let unit_cb = unit.clone();
-                                            html! {
-                                                <div class="input-group">
-                                                    <input type="number" class="form-control" value={num_val} 
-                                                        onchange={Callback::from(move |e: Event| {
-                                                            let val = e.target_unchecked_into::<web_sys::HtmlInputElement>().value();
-                                                            update_val.emit((name_c2.clone(), format!("{} {}", val, unit_cb)));
-                                                        })} />
-                                                    <span class="input-group-text">{unit}</span>
-                                                </div>
-                                            }
-                                        } else {
-                                            html! {
-                                                <input class="form-control" value={qty.clone()} 
+                                // Any unit convertible to the stock unit is accepted
+                                let qty_field = format!("materials.{}", name);
+                                let qty_ok = !field_errors.contains_key(&qty_field) && match (Quantity::parse(qty), inv_item) {
+                                    (Ok(q), Some(item)) => q.is_compatible(&item.quantity.unit),
+                                    (Ok(_), None) => true,
+                                    (Err(_), _) => false,
+                                };
+                                let unit_label = inv_item.map(|item| format!("stock: {}", item.quantity.unit));
+
+                                html! {
+                                    <tr key={name.clone()}>
+                                        <td>
+                                            <input class={classes!("form-control", if !is_valid { "is-invalid" } else { "" })} 
+                                                list="inventory-list"
+                                                value={name.clone()} 
+                                                onchange={Callback::from(move |e: Event| {
+                                                    let val = e.target_unchecked_into::<web_sys::HtmlInputElement>().value();
+                                                    update_key.emit((name_c.clone(), val));
+                                                })} />
+                                        </td>
+                                        <td>
+                                            <div class="input-group">
+                                                <input class={classes!("form-control", if !qty_ok { "is-invalid" } else { "" })}
+                                                    value={qty.clone()} placeholder="e.g. 2.5 kg"
                                                     onchange={Callback::from(move |e: Event| {
                                                         let val = e.target_unchecked_into::<web_sys::HtmlInputElement>().value();
                                                         update_val.emit((name_c2.clone(), val));
                                                     })} />
-                                            }
-                                        }}
-                                    </td>
-                                    <td>{leftover}</td>
-                                    <td>
-                                        <button class="btn btn-danger btn-sm" onclick={Callback::from(move |_| delete.emit(name_delete.clone()))}>{"X"}</button>
-                                    </td>
-                                </tr>
-                            }
-                        })}
-                    </tbody>
-                </table>
-                <button class="btn btn-sm btn-primary" onclick={add_material_row}>{"+ Add Material"}</button>
+                                                if let Some(unit) = unit_label {
+                                                    <span class="input-group-text">{unit}</span>
+                                                }
+                                            </div>
+                                            {feedback(&qty_field)}
+                                        </td>
+                                        <td>{leftover}</td>
+                                        <td>
+                                            <button class="btn btn-danger btn-sm" onclick={Callback::from(move |_| delete.emit(name_delete.clone()))}>{"X"}</button>
+                                        </td>
+                                    </tr>
+                                }
+                            })}
+                        </tbody>
+                    </table>
+                    {feedback("materials")}
+                    <button class="btn btn-sm btn-primary" onclick={add_material_row}>{"+ Add Material"}</button>
+                }
+
+                <HistoryPanel entity={AuditEntity::Task} entity_id={(*selected_task_id).clone()} />
             </div>
             
             <div class="col-md-4">
                 <div class="card p-3 mb-3">
-                    <h4>{if selected_task_id.is_some() { "Edit Operation" } else { "Add Operation" }}</h4>
-                    <input class="form-control mb-2" placeholder="User ID" 
-                        value={(*form_user_id).clone()} 
-                        oninput={
-                            let form_user_id = form_user_id.clone();
-                            Callback::from(move |e: InputEvent| form_user_id.set(e.target_unchecked_into::<web_sys::HtmlInputElement>().value()))
-                        } />
+                    if role.can_plan() {
+                        <h4>{if selected_task_id.is_some() { "Edit Operation" } else { "Add Operation" }}</h4>
+                        <div class="mb-2">
+                            <input class={classes!("form-control", invalid("user_id"))} placeholder="Worker ID" 
+                                list="worker-list"
+                                value={(*form_user_id).clone()} 
+                                oninput={
+                                    let form_user_id = form_user_id.clone();
+                                    Callback::from(move |e: InputEvent| form_user_id.set(e.target_unchecked_into::<web_sys::HtmlInputElement>().value()))
+                                } />
+                            {feedback("user_id")}
+                        </div>
                         
-                    <input class="form-control mb-2" placeholder="Operation ID" 
-                        list="preset-list"
-                        value={(*form_op_id).clone()} 
-                        oninput={on_op_input} />
-
-                    <div class="row g-2 mb-2">
-                        <div class="col">
-                            <label class="form-label">{"Start (HH:MM)"}</label>
-                            <div class="input-group">
-                                <input type="number" class="form-control" placeholder="HH" min="0" max="23"
-                                    value={(*form_start_hour).clone()}
-                                    oninput={Callback::from(move |e: InputEvent| form_start_hour.set(e.target_unchecked_into::<web_sys::HtmlInputElement>().value()))} />
-                                <input type="number" class="form-control" placeholder="MM" min="0" max="59"
-                                    value={(*form_start_min).clone()}
-                                    oninput={Callback::from(move |e: InputEvent| form_start_min.set(e.target_unchecked_into::<web_sys::HtmlInputElement>().value()))} />
+                        <div class="mb-2">
+                            <input class={classes!("form-control", invalid("operation_id"))} placeholder="Operation ID" 
+                                list="preset-list"
+                                value={(*form_op_id).clone()} 
+                                oninput={on_op_input} />
+                            {feedback("operation_id")}
+                        </div>
+
+                        if !machines.is_empty() {
+                            <div class="mb-2">
+                                <label class="form-label">{"Machines"}</label>
+                                <div class={classes!("form-control", invalid("machine_ids"))}>
+                                    {for machines.iter().filter(|m| machine_allowed(&m.id)).map(|m| {
+                                        let id = m.id.clone();
+                                        let checked = form_machines.contains(&m.id);
+                                        let form_machines = form_machines.clone();
+                                        let on_toggle = Callback::from(move |_| {
+                                            let mut ids = (*form_machines).clone();
+                                            match ids.iter().position(|i| *i == id) {
+                                                Some(at) => { ids.remove(at); }
+                                                None => ids.push(id.clone()),
+                                            }
+                                            form_machines.set(ids);
+                                        });
+                                        html! {
+                                            <div class="form-check form-check-inline">
+                                                <input class="form-check-input" type="checkbox" id={format!("machine-{}", m.id)}
+                                                    {checked} onchange={on_toggle} />
+                                                <label class="form-check-label" for={format!("machine-{}", m.id)}>{&m.name}</label>
+                                            </div>
+                                        }
+                                    })}
+                                </div>
+                                {feedback("machine_ids")}
                             </div>
+                        }
+
+                        <div class="mb-2">
+                            <label class="form-label">{"Waits for"}</label>
+                            {for form_depends.iter().enumerate().map(|(index, dep)| {
+                                let label = tasks.iter().find(|t| t.id == dep.task_id)
+                                    .map_or_else(|| dep.task_id.clone(), task_label);
+                                let on_lag = {
+                                    let form_depends = form_depends.clone();
+                                    Callback::from(move |e: Event| {
+                                        let value = e.target_unchecked_into::<web_sys::HtmlInputElement>().value();
+                                        let mut deps = (*form_depends).clone();
+                                        if let (Ok(lag), Some(dep)) = (value.parse::<i64>(), deps.get_mut(index)) {
+                                            dep.lag_minutes = lag;
+                                            form_depends.set(deps);
+                                        }
+                                    })
+                                };
+                                let on_remove = {
+                                    let form_depends = form_depends.clone();
+                                    Callback::from(move |_| {
+                                        let mut deps = (*form_depends).clone();
+                                        deps.remove(index);
+                                        form_depends.set(deps);
+                                    })
+                                };
+                                html! {
+                                    <div class="input-group input-group-sm mb-1">
+                                        <span class="input-group-text flex-grow-1">{label}</span>
+                                        <span class="input-group-text">{"+ lag (min)"}</span>
+                                        <input type="number" min="0" class="form-control" style="max-width: 5em;"
+                                            value={dep.lag_minutes.to_string()} onchange={on_lag} />
+                                        <button class="btn btn-outline-danger" onclick={on_remove}>{"X"}</button>
+                                    </div>
+                                }
+                            })}
+                            <select class={classes!("form-select", "form-select-sm", invalid("depends_on"))} onchange={on_add_dependency}>
+                                <option value="" selected=true>{"Add a task to wait for..."}</option>
+                                {for tasks.iter()
+                                    .filter(|t| selected_task_id.as_deref() != Some(t.id.as_str()))
+                                    .filter(|t| !form_depends.iter().any(|d| d.task_id == t.id))
+                                    .map(|t| html! { <option value={t.id.clone()}>{task_label(t)}</option> })}
+                            </select>
+                            {feedback("depends_on")}
                         </div>
-                        <div class="col">
-                            <label class="form-label">{"Duration (HH:MM)"}</label>
-                            <div class="input-group">
-                                <input type="number" class="form-control" placeholder="HH" min="0"
-                                    value={(*form_dur_hour).clone()}
-                                    oninput={Callback::from(move |e: InputEvent| form_dur_hour.set(e.target_unchecked_into::<web_sys::HtmlInputElement>().value()))} />
-                                <input type="number" class="form-control" placeholder="MM" min="0" max="59"
-                                    value={(*form_dur_min).clone()}
-                                    oninput={Callback::from(move |e: InputEvent| form_dur_min.set(e.target_unchecked_into::<web_sys::HtmlInputElement>().value()))} />
+
+                        <div class="row g-2 mb-2">
+                            <div class="col">
+                                <label class="form-label">{"Start (HH:MM)"}</label>
+                                <div class="input-group">
+                                    <input type="number" class={classes!("form-control", invalid("start_time"))} placeholder="HH" min="0" max="23"
+                                        value={(*form_start_hour).clone()}
+                                        oninput={Callback::from(move |e: InputEvent| form_start_hour.set(e.target_unchecked_into::<web_sys::HtmlInputElement>().value()))} />
+                                    <input type="number" class={classes!("form-control", invalid("start_time"))} placeholder="MM" min="0" max="59"
+                                        value={(*form_start_min).clone()}
+                                        oninput={Callback::from(move |e: InputEvent| form_start_min.set(e.target_unchecked_into::<web_sys::HtmlInputElement>().value()))} />
+                                </div>
+                                {feedback("start_time")}
+                            </div>
+                            <div class="col">
+                                <label class="form-label">{"Duration (HH:MM)"}</label>
+                                <div class="input-group">
+                                    <input type="number" class={classes!("form-control", invalid("expected_duration_minutes"))} placeholder="HH" min="0"
+                                        value={(*form_dur_hour).clone()}
+                                        oninput={Callback::from(move |e: InputEvent| form_dur_hour.set(e.target_unchecked_into::<web_sys::HtmlInputElement>().value()))} />
+                                    <input type="number" class={classes!("form-control", invalid("expected_duration_minutes"))} placeholder="MM" min="0" max="59"
+                                        value={(*form_dur_min).clone()}
+                                        oninput={Callback::from(move |e: InputEvent| form_dur_min.set(e.target_unchecked_into::<web_sys::HtmlInputElement>().value()))} />
+                                </div>
+                                {feedback("expected_duration_minutes")}
                             </div>
                         </div>
-                    </div>
 
-                    <div class="d-flex gap-2">
-                        <button onclick={on_add} class="btn btn-primary flex-grow-1" 
+                        <div class="d-flex gap-2">
+                            <button onclick={on_add} class="btn btn-primary flex-grow-1" 
+                                disabled={
+                                    form_user_id.trim().is_empty() || 
+                                    form_op_id.trim().is_empty() ||
+                                    !form_materials.keys().all(|k| inventory.iter().any(|i| i.name == *k))
+                                }>
+                                {"Add Task"}
+                            </button>
+                            if selected_task_id.is_some() {
+                                <button onclick={on_delete} class="btn btn-danger">{"Delete"}</button>
+                            }
+                        </div>
+                    } else if let Some(task) = &selected_task {
+                        <h4>{format!("{} ({})", task.operation_id, task.user_id)}</h4>
+                    } else {
+                        <div class="text-muted">{"Select a task in the chart to see its details."}</div>
+                    }
+                    if let Some(task) = &selected_task {
+                        <div class="d-flex gap-2 align-items-center mt-2">
+                            <span class="badge bg-secondary me-auto">{task.status.label()}</span>
+                            {for transitions(task.status).iter().filter(|&&(action, _)| match action {
+                                "start" | "finish" => role.can_execute(task, &username),
+                                _ => role.can_plan(),
+                            }).map(|&(action, label)| {
+                                let on_transition = on_transition.clone();
+                                html! {
+                                    <button class="btn btn-sm btn-outline-success"
+                                        onclick={Callback::from(move |_| on_transition.emit(action))}>{label}</button>
+                                }
+                            })}
+                        </div>
+                        if let (Some(started), Some(actual)) = (task.actual_start_time, task.actual_duration_minutes) {
+                            <small class="text-muted mt-1">
+                                {format!("Actual: started {}, took {}m (planned {}m)",
+                                    started.with_timezone(&Local).format("%H:%M"), actual, task.expected_duration_minutes)}
+                            </small>
+                        }
+                    }
+                </div>
+
+                if role.can_plan() {
+                    <div class="card p-3">
+                        <h4>{"AI Assistant"}</h4>
+                        <textarea class="form-control mb-2" placeholder="Describe new request..."
+                            value={(*ai_prompt).clone()}
+                            oninput={Callback::from(move |e: InputEvent| ai_prompt.set(e.target_unchecked_into::<web_sys::HtmlTextAreaElement>().value()))}>
+                        </textarea>
+                        <button onclick={on_suggest} class="btn btn-success">{"Suggest Schedule"}</button>
+                        <pre class="mt-2 p-2 bg-light">{(*ai_suggestion).clone()}</pre>
+                    </div>
+                    <div class="mt-3">
+                        <button onclick={on_update} class="btn btn-warning w-100 mb-2"
                             disabled={
+                                selected_task_id.is_none() ||
                                 form_user_id.trim().is_empty() || 
                                 form_op_id.trim().is_empty() ||
                                 !form_materials.keys().all(|k| inventory.iter().any(|i| i.name == *k))
                             }>
-                            {"Add Task"}
+                            {"Update Selected Task"}
                         </button>
-                        if selected_task_id.is_some() {
-                            <button onclick={on_delete} class="btn btn-danger">{"Delete"}</button>
-                        }
-                    </div>
-                </div>
-
-                <div class="card p-3">
-                    <h4>{"AI Assistant"}</h4>
-                    <textarea class="form-control mb-2" placeholder="Describe new request..."
-                        value={(*ai_prompt).clone()}
-                        oninput={Callback::from(move |e: InputEvent| ai_prompt.set(e.target_unchecked_into::<web_sys::HtmlTextAreaElement>().value()))}>
-                    </textarea>
-                    <button onclick={on_suggest} class="btn btn-success">{"Suggest Schedule"}</button>
-                    <pre class="mt-2 p-2 bg-light">{(*ai_suggestion).clone()}</pre>
-                </div>
-                <div class="mt-3">
-                    <button onclick={on_update} class="btn btn-warning w-100 mb-2"
-                        disabled={
-                            selected_task_id.is_none() ||
-                            form_user_id.trim().is_empty() || 
-                            form_op_id.trim().is_empty() ||
-                            !form_materials.keys().all(|k| inventory.iter().any(|i| i.name == *k))
-                        }>
-                        {"Update Selected Task"}
-                    </button>
                     
-                    <button onclick={on_update_preset} class="btn btn-info w-100"
-                        disabled={form_op_id.trim().is_empty()}>
-                        {"Update Task Preset"}
-                    </button>
+                        if role.can_administer() {
+                            <button onclick={on_update_preset} class="btn btn-info w-100"
+                                disabled={form_op_id.trim().is_empty()}>
+                                {"Update Task Preset"}
+                            </button>
                     
-                    if let Some((op_id, new_preset)) = &*pending_preset_update {
-                        {render_preset_diff(op_id, new_preset)}
-                    }
-                </div>
+                            if let Some((op_id, new_preset)) = &*pending_preset_update {
+                                {render_preset_diff(op_id, new_preset)}
+                        }
+                        }
+                    </div>
+                }
             </div>
         </div>
     }
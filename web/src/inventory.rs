@@ -1,26 +1,74 @@
 use yew::prelude::*;
 use yew_router::prelude::*;
-use gloo_net::http::Request;
-use shared::InventoryItem;
+use shared::{AuditEntity, ErrorCode, InventoryItem, Quantity, ServerEvent};
 use wasm_bindgen::JsCast;
 use web_sys::{HtmlInputElement, InputEvent};
 use crate::Route;
+use crate::history::HistoryPanel;
+use crate::live::{confirm_merge, merge_field, use_server_events, ListAction, LiveList};
+use crate::login::UserBar;
+use crate::api;
+use crate::toast::use_toaster;
+
+// The amount typed into a quantity field. Never guesses: an empty or unreadable field is an
+// error rather than 0, which would wipe the stock on save.
+fn parse_amount(raw: &str) -> Result<f64, String> {
+    match raw.trim().parse::<f64>() {
+        Ok(value) if value.is_finite() => Ok(value),
+        _ => Err(format!("'{}' is not a valid quantity", raw.trim())),
+    }
+}
+
+enum EditOutcome {
+    Saved,
+    // The user chose to drop their edit; carry on editing the stored item instead
+    Reload(InventoryItem),
+}
+
+// Saves `edited`, the edit form's version of `base`. If someone else saved the item in the
+// meantime, offers to apply the edit on top of their version or to load theirs.
+async fn save_edit(base: &InventoryItem, mut edited: InventoryItem) -> Result<EditOutcome, api::Error> {
+    edited.version = base.version;
+    let stale = match api::inventory::replace(&base.name, &edited).await {
+        Ok(_) => return Ok(EditOutcome::Saved),
+        Err(api::Error::Api(e)) if e.code == ErrorCode::VersionConflict => e,
+        Err(e) => return Err(e),
+    };
+    let latest = api::inventory::get(&base.name).await?;
+    if !confirm_merge(&stale.message) {
+        return Ok(EditOutcome::Reload(latest));
+    }
+    let merged = InventoryItem {
+        quantity: merge_field(&base.quantity, &edited.quantity, &latest.quantity),
+        ..latest
+    };
+    api::inventory::replace(&base.name, &merged).await?;
+    Ok(EditOutcome::Saved)
+}
 
 #[function_component(Inventory)]
 pub fn inventory_page() -> Html {
-    let inventory = use_state(Vec::new);
+    let inventory = use_reducer(LiveList::<InventoryItem>::default);
     let new_name = use_state(|| "".to_string());
     let new_qty = use_state(|| "".to_string());
     let new_unit = use_state(|| "".to_string());
+    // The item being edited as it was loaded, so the save can be checked against its version
+    let editing = use_state(|| None::<InventoryItem>);
+    let edit_qty = use_state(|| "".to_string());
+    let edit_unit = use_state(|| "".to_string());
+    let toaster = use_toaster();
+    let can_edit = api::auth::session().is_some_and(|s| s.user.role.can_administer());
 
     let fetch_inv = {
         let inventory = inventory.clone();
+        let toaster = toaster.clone();
         Callback::from(move |_| {
             let inventory = inventory.clone();
+            let toaster = toaster.clone();
             wasm_bindgen_futures::spawn_local(async move {
-                let fetched: Vec<InventoryItem> = Request::get("http://localhost:8081/inventory")
-                    .send().await.unwrap().json().await.unwrap();
-                inventory.set(fetched);
+                if let Some(fetched) = toaster.report(api::inventory::list().await) {
+                    inventory.dispatch(ListAction::Replace(fetched));
+                }
             });
         })
     };
@@ -33,21 +81,111 @@ pub fn inventory_page() -> Html {
         }, ());
     }
 
+    {
+        let inventory = inventory.dispatcher();
+        let fetch_inv = fetch_inv.clone();
+        use_server_events(Callback::from(move |event: ServerEvent| match event {
+            ServerEvent::Changed(entry) if entry.entity == AuditEntity::InventoryItem => {
+                match ListAction::from_entry(&entry) {
+                    Some(action) => inventory.dispatch(action),
+                    None => fetch_inv.emit(()),
+                }
+            }
+            ServerEvent::Changed(_) => {}
+            ServerEvent::Resync => fetch_inv.emit(()),
+        }));
+    }
+
     let on_add = {
         let name = new_name.clone();
         let qty = new_qty.clone();
         let unit = new_unit.clone();
         let fetch = fetch_inv.clone();
+        let toaster = toaster.clone();
         Callback::from(move |_| {
+            let value = match parse_amount(&qty) {
+                Ok(value) => value,
+                Err(e) => return toaster.error(e),
+            };
             let item = InventoryItem {
                 name: (*name).clone(),
-                quantity: (*qty).parse().unwrap_or(0.0),
-                unit: (*unit).clone(),
+                quantity: Quantity::new(value, unit.trim()),
+                version: 0,
             };
             let fetch = fetch.clone();
+            let toaster = toaster.clone();
+            wasm_bindgen_futures::spawn_local(async move {
+                toaster.report(api::inventory::add(&item).await);
+                fetch.emit(());
+            });
+        })
+    };
+
+    let on_edit = {
+        let editing = editing.clone();
+        let edit_qty = edit_qty.clone();
+        let edit_unit = edit_unit.clone();
+        Callback::from(move |item: InventoryItem| {
+            edit_qty.set(item.quantity.value.to_string());
+            edit_unit.set(item.quantity.unit.clone());
+            editing.set(Some(item));
+        })
+    };
+
+    let on_cancel_edit = {
+        let editing = editing.clone();
+        Callback::from(move |_| editing.set(None))
+    };
+
+    let on_save = {
+        let editing = editing.clone();
+        let edit_qty = edit_qty.clone();
+        let edit_unit = edit_unit.clone();
+        let fetch = fetch_inv.clone();
+        let toaster = toaster.clone();
+        let on_edit = on_edit.clone();
+        Callback::from(move |_| {
+            if let Some(base) = &*editing {
+                let value = match parse_amount(&edit_qty) {
+                    Ok(value) => value,
+                    Err(e) => return toaster.error(e),
+                };
+                let item = InventoryItem {
+                    name: base.name.clone(),
+                    quantity: Quantity::new(value, edit_unit.trim()),
+                    version: base.version,
+                };
+                let base = base.clone();
+                let fetch = fetch.clone();
+                let editing = editing.clone();
+                let toaster = toaster.clone();
+                let on_edit = on_edit.clone();
+                wasm_bindgen_futures::spawn_local(async move {
+                    // Keep the row in edit mode so the input isn't lost
+                    match toaster.report(save_edit(&base, item).await) {
+                        Some(EditOutcome::Saved) => {
+                            editing.set(None);
+                            fetch.emit(());
+                        }
+                        Some(EditOutcome::Reload(latest)) => on_edit.emit(latest),
+                        None => {}
+                    }
+                });
+            }
+        })
+    };
+
+    let on_delete = {
+        let fetch = fetch_inv.clone();
+        let toaster = toaster.clone();
+        Callback::from(move |name: String| {
+            if !gloo::dialogs::confirm(&format!("Delete '{}' from inventory?", name)) {
+                return;
+            }
+            let fetch = fetch.clone();
+            let toaster = toaster.clone();
             wasm_bindgen_futures::spawn_local(async move {
-                Request::post("http://localhost:8081/inventory")
-                    .json(&item).unwrap().send().await.unwrap();
+                toaster.report(api::inventory::delete(&name).await);
                 fetch.emit(());
             });
         })
@@ -57,33 +195,70 @@ pub fn inventory_page() -> Html {
         <div class="container">
             <div class="mb-3">
                 <Link<Route> to={Route::Home} classes="btn btn-outline-primary me-2">{"Gantt Chart"}</Link<Route>>
-                <Link<Route> to={Route::Inventory} classes="btn btn-outline-success">{"Inventory"}</Link<Route>>
+                <Link<Route> to={Route::Inventory} classes="btn btn-outline-success me-2">{"Inventory"}</Link<Route>>
+                <Link<Route> to={Route::Workers} classes="btn btn-outline-dark me-2">{"Workers"}</Link<Route>>
+                <Link<Route> to={Route::Machines} classes="btn btn-outline-secondary me-2">{"Machines"}</Link<Route>>
+                <Link<Route> to={Route::Jobs} classes="btn btn-outline-warning">{"Jobs"}</Link<Route>>
+                <UserBar />
             </div>
             <h2>{"Inventory Management"}</h2>
-            <div class="row mb-3">
-                <div class="col">
-                    <input class="form-control" placeholder="Item Name" value={(*new_name).clone()} 
-                        oninput={Callback::from(move |e: InputEvent| new_name.set(e.target_unchecked_into::<web_sys::HtmlInputElement>().value()))} />
+            if can_edit {
+                <div class="row mb-3">
+                    <div class="col">
+                        <input class="form-control" placeholder="Item Name" value={(*new_name).clone()} 
+                            oninput={Callback::from(move |e: InputEvent| new_name.set(e.target_unchecked_into::<web_sys::HtmlInputElement>().value()))} />
+                    </div>
+                    <div class="col">
+                        <input type="number" class="form-control" placeholder="Quantity" value={(*new_qty).clone()} 
+                            oninput={Callback::from(move |e: InputEvent| new_qty.set(e.target_unchecked_into::<web_sys::HtmlInputElement>().value()))} />
+                    </div>
+                    <div class="col">
+                        <input class="form-control" placeholder="Unit (e.g. kg)" value={(*new_unit).clone()} 
+                            oninput={Callback::from(move |e: InputEvent| new_unit.set(e.target_unchecked_into::<web_sys::HtmlInputElement>().value()))} />
+                    </div>
+                    <div class="col">
+                        <button class="btn btn-primary" onclick={on_add}>{"Add Item"}</button>
+                    </div>
                 </div>
-                <div class="col">
-                    <input type="number" class="form-control" placeholder="Quantity" value={(*new_qty).clone()} 
-                        oninput={Callback::from(move |e: InputEvent| new_qty.set(e.target_unchecked_into::<web_sys::HtmlInputElement>().value()))} />
-                </div>
-                <div class="col">
-                    <input class="form-control" placeholder="Unit (e.g. kg)" value={(*new_unit).clone()} 
-                        oninput={Callback::from(move |e: InputEvent| new_unit.set(e.target_unchecked_into::<web_sys::HtmlInputElement>().value()))} />
-                </div>
-                <div class="col">
-                    <button class="btn btn-primary" onclick={on_add}>{"Add Item"}</button>
-                </div>
-            </div>
+            }
             <ul class="list-group">
-                {for inventory.iter().map(|item| html! {
-                    <li class="list-group-item d-flex justify-content-between align-items-center">
-                        {format!("{}: {} {}", item.name, item.quantity, item.unit)}
-                    </li>
+                {for inventory.iter().map(|item| {
+                    if editing.as_ref().is_some_and(|e| e.name == item.name) {
+                        let edit_qty = edit_qty.clone();
+                        let edit_unit = edit_unit.clone();
+                        html! {
+                            <li class="list-group-item d-flex gap-2 align-items-center">
+                                <span class="me-auto">{&item.name}</span>
+                                <input type="number" class="form-control w-auto" value={(*edit_qty).clone()}
+                                    oninput={Callback::from(move |e: InputEvent| edit_qty.set(e.target_unchecked_into::<web_sys::HtmlInputElement>().value()))} />
+                                <input class="form-control w-auto" value={(*edit_unit).clone()}
+                                    oninput={Callback::from(move |e: InputEvent| edit_unit.set(e.target_unchecked_into::<web_sys::HtmlInputElement>().value()))} />
+                                <button class="btn btn-success btn-sm" onclick={on_save.clone()}>{"Save"}</button>
+                                <button class="btn btn-secondary btn-sm" onclick={on_cancel_edit.clone()}>{"Cancel"}</button>
+                            </li>
+                        }
+                    } else {
+                        let item_edit = item.clone();
+                        let name_del = item.name.clone();
+                        let on_edit = on_edit.clone();
+                        let on_delete = on_delete.clone();
+                        html! {
+                            <li class="list-group-item d-flex justify-content-between align-items-center">
+                                {format!("{}: {}", item.name, item.quantity)}
+                                if can_edit {
+                                    <div>
+                                        <button class="btn btn-outline-secondary btn-sm me-2"
+                                            onclick={Callback::from(move |_| on_edit.emit(item_edit.clone()))}>{"Edit"}</button>
+                                        <button class="btn btn-danger btn-sm"
+                                            onclick={Callback::from(move |_| on_delete.emit(name_del.clone()))}>{"Delete"}</button>
+                                    </div>
+                                }
+                            </li>
+                        }
+                    }
                 })}
             </ul>
+            <HistoryPanel entity={AuditEntity::InventoryItem} entity_id={editing.as_ref().map(|e| e.name.clone())} />
         </div>
     }
 }
\ No newline at end of file
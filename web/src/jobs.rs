@@ -0,0 +1,641 @@
+use yew::prelude::*;
+use yew_router::prelude::*;
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone, Utc};
+use std::collections::HashMap;
+use web_sys::InputEvent;
+use shared::{AuditEntity, ErrorCode, FieldError, Job, JobPriority, JobStep, JobView, NewJob, ServerEvent, TaskPreset, TaskStatus, Worker};
+use crate::Route;
+use crate::history::HistoryPanel;
+use crate::live::use_server_events;
+use crate::login::UserBar;
+use crate::presets::load_presets;
+use crate::api;
+use crate::toast::use_toaster;
+
+// What `datetime-local` inputs hold: local time without a zone
+const INPUT_FORMAT: &str = "%Y-%m-%dT%H:%M";
+
+fn parse_local(value: &str) -> Option<DateTime<Utc>> {
+    let naive = NaiveDateTime::parse_from_str(value, INPUT_FORMAT).ok()?;
+    Some(Local.from_local_datetime(&naive).earliest()?.with_timezone(&Utc))
+}
+
+fn local_input(at: DateTime<Utc>) -> String {
+    at.with_timezone(&Local).format(INPUT_FORMAT).to_string()
+}
+
+fn local_label(at: DateTime<Utc>) -> String {
+    at.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string()
+}
+
+fn span_label(minutes: i64) -> String {
+    if minutes < 60 { format!("{} min", minutes) } else { format!("{} h {} min", minutes / 60, minutes % 60) }
+}
+
+fn empty_step() -> JobStep {
+    JobStep { operation_id: String::new(), user_id: String::new(), machine_ids: Vec::new(), lag_minutes: 0 }
+}
+
+fn priority_badge(priority: JobPriority) -> &'static str {
+    match priority {
+        JobPriority::Low => "badge bg-light text-dark",
+        JobPriority::Normal => "badge bg-secondary",
+        JobPriority::High => "badge bg-warning text-dark",
+        JobPriority::Urgent => "badge bg-danger",
+    }
+}
+
+// Most pressing first: higher priority, then the earlier due date
+fn sort_jobs(jobs: &mut [JobView]) {
+    jobs.sort_by(|a, b| b.job.priority.cmp(&a.job.priority).then(a.job.due_date.cmp(&b.job.due_date)));
+}
+
+// The new job the form describes, or the inputs that need fixing first
+fn form_new_job(customer: &str, due: &str, priority: JobPriority, notes: &str, start: &str, steps: &[JobStep]) -> Result<NewJob, Vec<FieldError>> {
+    let (due_date, start_time) = (parse_local(due), parse_local(start));
+    let new_job = NewJob {
+        customer: customer.trim().to_string(),
+        due_date: due_date.unwrap_or_else(Utc::now),
+        priority,
+        notes: notes.trim().to_string(),
+        start_time: start_time.unwrap_or_else(Utc::now),
+        steps: steps.iter().map(|step| JobStep {
+            operation_id: step.operation_id.trim().to_string(),
+            user_id: step.user_id.trim().to_string(),
+            ..step.clone()
+        }).collect(),
+    };
+    let mut errors = new_job.validate();
+    if due_date.is_none() {
+        errors.push(FieldError::new("due_date", "Due date is required"));
+    }
+    if start_time.is_none() {
+        errors.push(FieldError::new("start_time", "Start is required"));
+    }
+    if errors.is_empty() { Ok(new_job) } else { Err(errors) }
+}
+
+// `job` with the details from the form; its routing is left alone
+fn form_job(job: &Job, customer: &str, due: &str, priority: JobPriority, notes: &str) -> Result<Job, Vec<FieldError>> {
+    let due_date = parse_local(due);
+    let job = Job {
+        customer: customer.trim().to_string(),
+        due_date: due_date.unwrap_or(job.due_date),
+        priority,
+        notes: notes.trim().to_string(),
+        ..job.clone()
+    };
+    let mut errors = job.validate();
+    if due_date.is_none() {
+        errors.push(FieldError::new("due_date", "Due date is required"));
+    }
+    if errors.is_empty() { Ok(job) } else { Err(errors) }
+}
+
+// Creates the job; on a double-booking asks whether to plan it anyway and retries with ?force=true.
+// None if the planner decided against it.
+async fn create_job(new_job: &NewJob) -> Result<Option<JobView>, api::Error> {
+    match api::jobs::create(new_job, false).await {
+        Err(api::Error::Api(conflict)) if conflict.code == ErrorCode::TaskConflict => {
+            let msg = format!("{}\nConflicting tasks: {}\n\nPlan anyway?",
+                conflict.message, conflict.conflicting_ids.join(", "));
+            if gloo::dialogs::confirm(&msg) {
+                api::jobs::create(new_job, true).await.map(Some)
+            } else {
+                Ok(None)
+            }
+        }
+        result => result.map(Some),
+    }
+}
+
+#[derive(Properties, PartialEq)]
+struct RoutingEditorProps {
+    steps: Vec<JobStep>,
+    // The operation catalog, to describe each chosen operation
+    presets: HashMap<String, TaskPreset>,
+    // Keyed `steps.{index}.{field}`, as the server reports them
+    errors: HashMap<String, String>,
+    on_change: Callback<Vec<JobStep>>,
+}
+
+// The steps of a new job, one row each in routing order
+#[function_component(RoutingEditor)]
+fn routing_editor(props: &RoutingEditorProps) -> Html {
+    let edit = |index: usize, apply: fn(&mut JobStep, String)| {
+        let steps = props.steps.clone();
+        let on_change = props.on_change.clone();
+        Callback::from(move |e: Event| {
+            let value = e.target_unchecked_into::<web_sys::HtmlInputElement>().value();
+            let mut steps = steps.clone();
+            if let Some(step) = steps.get_mut(index) {
+                apply(step, value);
+                on_change.emit(steps);
+            }
+        })
+    };
+    let on_add = {
+        let steps = props.steps.clone();
+        let on_change = props.on_change.clone();
+        Callback::from(move |_| {
+            let mut steps = steps.clone();
+            // The same person often carries on with the next operation
+            let user_id = steps.last().map(|s| s.user_id.clone()).unwrap_or_default();
+            steps.push(JobStep { user_id, ..empty_step() });
+            on_change.emit(steps);
+        })
+    };
+
+    html! {
+        <>
+            <table class="table table-sm">
+                <thead>
+                    <tr>
+                        <th></th>
+                        <th>{"Operation"}</th>
+                        <th>{"Worker"}</th>
+                        <th>{"Machines"}</th>
+                        <th>{"Wait (min)"}</th>
+                        <th></th>
+                    </tr>
+                </thead>
+                <tbody>
+                    {for props.steps.iter().enumerate().map(|(index, step)| {
+                        let field = |name: &str| format!("steps.{}.{}", index, name);
+                        let invalid = |name: &str| props.errors.contains_key(&field(name)).then_some("is-invalid");
+                        let prefix = format!("steps.{}.", index);
+                        let mut problems: Vec<&String> = props.errors.iter()
+                            .filter(|(key, _)| key.starts_with(&prefix))
+                            .map(|(_, message)| message)
+                            .collect();
+                        problems.sort();
+                        let hint = props.presets.get(step.operation_id.trim()).map(|op| {
+                            let mut parts = vec![format!("{} min", op.total_minutes())];
+                            if !op.required_skills.is_empty() {
+                                parts.push(format!("needs {}", op.required_skills.join(", ")));
+                            }
+                            if !op.allowed_machines.is_empty() {
+                                parts.push(format!("on {}", op.allowed_machines.join(" or ")));
+                            }
+                            parts.join(" · ")
+                        });
+                        let on_operation = {
+                            let steps = props.steps.clone();
+                            let presets = props.presets.clone();
+                            let on_change = props.on_change.clone();
+                            Callback::from(move |e: Event| {
+                                let value = e.target_unchecked_into::<web_sys::HtmlInputElement>().value();
+                                let mut steps = steps.clone();
+                                if let Some(step) = steps.get_mut(index) {
+                                    // An operation that only runs on one machine gets it right away
+                                    if let Some([only]) = presets.get(value.trim()).map(|op| op.allowed_machines.as_slice()) {
+                                        if step.machine_ids.is_empty() {
+                                            step.machine_ids = vec![only.clone()];
+                                        }
+                                    }
+                                    step.operation_id = value;
+                                    on_change.emit(steps);
+                                }
+                            })
+                        };
+                        let on_up = {
+                            let steps = props.steps.clone();
+                            let on_change = props.on_change.clone();
+                            Callback::from(move |_| {
+                                let mut steps = steps.clone();
+                                steps.swap(index - 1, index);
+                                on_change.emit(steps);
+                            })
+                        };
+                        let on_remove = {
+                            let on_change = props.on_change.clone();
+                            let mut remaining = props.steps.clone();
+                            remaining.remove(index);
+                            Callback::from(move |_| on_change.emit(remaining.clone()))
+                        };
+                        html! {
+                            <>
+                                <tr>
+                                    <td class="text-nowrap">
+                                        {index + 1}
+                                        if index > 0 {
+                                            <button class="btn btn-link btn-sm p-0 ms-1" title="Move up" onclick={on_up}>{"↑"}</button>
+                                        }
+                                    </td>
+                                    <td>
+                                        <input class={classes!("form-control", "form-control-sm", invalid("operation_id"))}
+                                            list="job-operation-list" value={step.operation_id.clone()} onchange={on_operation} />
+                                        if let Some(hint) = hint {
+                                            <div class="form-text">{hint}</div>
+                                        }
+                                    </td>
+                                    <td>
+                                        <input class={classes!("form-control", "form-control-sm", invalid("user_id"), invalid("start_time"))}
+                                            list="job-worker-list" value={step.user_id.clone()}
+                                            onchange={edit(index, |step, value| step.user_id = value)} />
+                                    </td>
+                                    <td>
+                                        <input class={classes!("form-control", "form-control-sm", invalid("machine_ids"))}
+                                            placeholder="Comma separated" value={step.machine_ids.join(", ")}
+                                            onchange={edit(index, |step, value| {
+                                                step.machine_ids = value.split(',').map(str::trim).filter(|s| !s.is_empty()).map(str::to_string).collect();
+                                            })} />
+                                    </td>
+                                    <td>
+                                        <input type="number" min="0" class={classes!("form-control", "form-control-sm", invalid("lag_minutes"))}
+                                            disabled={index == 0} value={step.lag_minutes.to_string()}
+                                            onchange={edit(index, |step, value| step.lag_minutes = value.trim().parse().unwrap_or(0))} />
+                                    </td>
+                                    <td>
+                                        <button class="btn btn-danger btn-sm" onclick={on_remove}>{"X"}</button>
+                                    </td>
+                                </tr>
+                                if !problems.is_empty() {
+                                    <tr>
+                                        <td colspan="6" class="border-0 pt-0">
+                                            {for problems.iter().map(|message| html! {
+                                                <div class="invalid-feedback d-block">{*message}</div>
+                                            })}
+                                        </td>
+                                    </tr>
+                                }
+                            </>
+                        }
+                    })}
+                </tbody>
+            </table>
+            <div class="form-text mb-2">{"Each step starts when the one before is done, plus its wait."}</div>
+            <button class="btn btn-secondary btn-sm mb-3" onclick={on_add}>{"+ Add Step"}</button>
+        </>
+    }
+}
+
+#[function_component(JobsPage)]
+pub fn jobs_page() -> Html {
+    let jobs = use_state(Vec::<JobView>::new);
+    let presets = use_state(HashMap::<String, TaskPreset>::new);
+    let workers = use_state(Vec::<Worker>::new);
+    // The id of the job being viewed; None while planning a new one
+    let editing = use_state(|| None::<String>);
+    let form_customer = use_state(String::new);
+    let form_due = use_state(String::new);
+    let form_priority = use_state(JobPriority::default);
+    let form_notes = use_state(String::new);
+    let form_start = use_state(|| local_input(Utc::now()));
+    let form_steps = use_state(|| vec![empty_step()]);
+    let field_errors = use_state(HashMap::<String, String>::new);
+    let toaster = use_toaster();
+    // Everyone may follow jobs; planners create and change them
+    let can_edit = api::auth::session().is_some_and(|s| s.user.role.can_plan());
+
+    let fetch_jobs = {
+        let jobs = jobs.clone();
+        let toaster = toaster.clone();
+        Callback::from(move |_| {
+            let jobs = jobs.clone();
+            let toaster = toaster.clone();
+            wasm_bindgen_futures::spawn_local(async move {
+                if let Some(mut fetched) = toaster.report(api::jobs::list().await) {
+                    sort_jobs(&mut fetched);
+                    jobs.set(fetched);
+                }
+            });
+        })
+    };
+
+    {
+        let fetch_jobs = fetch_jobs.clone();
+        let presets = presets.clone();
+        let workers = workers.clone();
+        let toaster = toaster.clone();
+        use_effect_with_deps(move |_| {
+            fetch_jobs.emit(());
+            wasm_bindgen_futures::spawn_local(async move {
+                if let Some(loaded) = toaster.report(load_presets(&toaster).await) {
+                    presets.set(loaded);
+                }
+                if let Some(loaded) = toaster.report(api::workers::list().await) {
+                    workers.set(loaded);
+                }
+            });
+            || {}
+        }, ());
+    }
+
+    {
+        // Progress moves with every task of a job, so both kinds of change reload the list
+        let fetch_jobs = fetch_jobs.clone();
+        use_server_events(Callback::from(move |event: ServerEvent| match event {
+            ServerEvent::Changed(entry) if matches!(entry.entity, AuditEntity::Job | AuditEntity::Task) => fetch_jobs.emit(()),
+            ServerEvent::Changed(_) => {}
+            ServerEvent::Resync => fetch_jobs.emit(()),
+        }));
+    }
+
+    // Fill the form from `job`, or clear it for a new one
+    let load_form = {
+        let editing = editing.clone();
+        let form_customer = form_customer.clone();
+        let form_due = form_due.clone();
+        let form_priority = form_priority.clone();
+        let form_notes = form_notes.clone();
+        let form_start = form_start.clone();
+        let form_steps = form_steps.clone();
+        let field_errors = field_errors.clone();
+        Callback::from(move |job: Option<Job>| {
+            field_errors.set(HashMap::new());
+            editing.set(job.as_ref().map(|j| j.id.clone()));
+            match job {
+                Some(job) => {
+                    form_customer.set(job.customer);
+                    form_due.set(local_input(job.due_date));
+                    form_priority.set(job.priority);
+                    form_notes.set(job.notes);
+                }
+                None => {
+                    form_customer.set(String::new());
+                    form_due.set(String::new());
+                    form_priority.set(JobPriority::default());
+                    form_notes.set(String::new());
+                    form_start.set(local_input(Utc::now()));
+                    form_steps.set(vec![empty_step()]);
+                }
+            }
+        })
+    };
+
+    let on_save = {
+        let jobs = jobs.clone();
+        let editing = editing.clone();
+        let form_customer = form_customer.clone();
+        let form_due = form_due.clone();
+        let form_priority = form_priority.clone();
+        let form_notes = form_notes.clone();
+        let form_start = form_start.clone();
+        let form_steps = form_steps.clone();
+        let field_errors = field_errors.clone();
+        let load_form = load_form.clone();
+        let toaster = toaster.clone();
+        Callback::from(move |_| {
+            let existing = editing.as_ref().and_then(|id| jobs.iter().find(|v| &v.job.id == id)).map(|v| v.job.clone());
+            let planned = match &existing {
+                Some(job) => form_job(job, &form_customer, &form_due, *form_priority, &form_notes).map(Err),
+                None => form_new_job(&form_customer, &form_due, *form_priority, &form_notes, &form_start, &form_steps).map(Ok),
+            };
+            // Ok(new job) or Err(updated job)
+            let planned = match planned {
+                Ok(planned) => planned,
+                Err(errors) => {
+                    field_errors.set(errors.iter().map(|e| (e.field.clone(), e.message.clone())).collect());
+                    return;
+                }
+            };
+            let field_errors = field_errors.clone();
+            let load_form = load_form.clone();
+            let toaster = toaster.clone();
+            wasm_bindgen_futures::spawn_local(async move {
+                let result = match &planned {
+                    Ok(new_job) => create_job(new_job).await,
+                    Err(job) => api::jobs::update(job).await.map(Some),
+                };
+                if let Err(api::Error::Api(e)) = &result {
+                    field_errors.set(e.field_errors.iter().map(|e| (e.field.clone(), e.message.clone())).collect());
+                }
+                if let Some(Some(saved)) = toaster.report(result) {
+                    load_form.emit(Some(saved.job));
+                }
+            });
+        })
+    };
+
+    let on_delete = {
+        let editing = editing.clone();
+        let load_form = load_form.clone();
+        let toaster = toaster.clone();
+        Callback::from(move |job: Job| {
+            let msg = format!("Delete the job for {} and its {} tasks?", job.customer, job.task_ids.len());
+            if !gloo::dialogs::confirm(&msg) {
+                return;
+            }
+            if editing.as_deref() == Some(job.id.as_str()) {
+                load_form.emit(None);
+            }
+            let toaster = toaster.clone();
+            wasm_bindgen_futures::spawn_local(async move {
+                toaster.report(api::jobs::delete(&job.id).await);
+            });
+        })
+    };
+
+    let invalid = |field: &str| if field_errors.contains_key(field) { "is-invalid" } else { "" };
+    let feedback = |field: &str| match field_errors.get(field) {
+        Some(message) => html! { <div class="invalid-feedback d-block">{message}</div> },
+        None => html! {},
+    };
+    let text_input = |handle: &UseStateHandle<String>| {
+        let handle = handle.clone();
+        Callback::from(move |e: InputEvent| handle.set(e.target_unchecked_into::<web_sys::HtmlInputElement>().value()))
+    };
+    let on_priority = {
+        let form_priority = form_priority.clone();
+        Callback::from(move |e: Event| {
+            let value = e.target_unchecked_into::<web_sys::HtmlSelectElement>().value();
+            if let Some(&priority) = value.parse::<usize>().ok().and_then(|i| JobPriority::ALL.get(i)) {
+                form_priority.set(priority);
+            }
+        })
+    };
+    let viewed = editing.as_ref().and_then(|id| jobs.iter().find(|v| &v.job.id == id));
+
+    html! {
+        <div class="container">
+            <div class="mb-3">
+                <Link<Route> to={Route::Home} classes="btn btn-outline-primary me-2">{"Gantt Chart"}</Link<Route>>
+                <Link<Route> to={Route::Inventory} classes="btn btn-outline-success me-2">{"Inventory"}</Link<Route>>
+                <Link<Route> to={Route::Presets} classes="btn btn-outline-info me-2">{"Task Presets"}</Link<Route>>
+                <Link<Route> to={Route::Workers} classes="btn btn-outline-dark me-2">{"Workers"}</Link<Route>>
+                <Link<Route> to={Route::Machines} classes="btn btn-outline-secondary me-2">{"Machines"}</Link<Route>>
+                <Link<Route> to={Route::Jobs} classes="btn btn-outline-warning">{"Jobs"}</Link<Route>>
+                <UserBar />
+            </div>
+            <h2>{"Jobs"}</h2>
+            <datalist id="job-operation-list">
+                {for presets.keys().map(|op| html! { <option value={op.clone()} /> })}
+            </datalist>
+            <datalist id="job-worker-list">
+                {for workers.iter().map(|w| html! { <option value={w.id.clone()}>{&w.display_name}</option> })}
+            </datalist>
+            <div class="row">
+                <div class="col-md-7">
+                    <table class="table table-bordered table-sm">
+                        <thead>
+                            <tr>
+                                <th>{"Customer"}</th>
+                                <th>{"Priority"}</th>
+                                <th>{"Due"}</th>
+                                <th>{"Progress"}</th>
+                                <th>{"Projected Completion"}</th>
+                                <th></th>
+                            </tr>
+                        </thead>
+                        <tbody>
+                            {for jobs.iter().map(|view| {
+                                let job = &view.job;
+                                let selected = editing.as_deref() == Some(job.id.as_str());
+                                let percent = (view.done * 100).checked_div(view.total).unwrap_or(0);
+                                let job_view = job.clone();
+                                let job_del = job.clone();
+                                let load_form = load_form.clone();
+                                let on_delete = on_delete.clone();
+                                html! {
+                                    <tr key={job.id.clone()} class={classes!(selected.then_some("table-active"))}>
+                                        <td>{&job.customer}</td>
+                                        <td><span class={priority_badge(job.priority)}>{job.priority.label()}</span></td>
+                                        <td>{local_label(job.due_date)}</td>
+                                        <td style="min-width: 8rem;">
+                                            <div class="progress" style="height: 0.6rem;">
+                                                <div class="progress-bar bg-success" style={format!("width: {}%", percent)}></div>
+                                            </div>
+                                            <small>{format!("{} of {} done", view.done, view.total)}</small>
+                                        </td>
+                                        <td>
+                                            {match view.projected_completion {
+                                                Some(at) => html! {
+                                                    <>
+                                                        {local_label(at)}
+                                                        if view.late() {
+                                                            <span class="badge bg-danger ms-2">{"Late"}</span>
+                                                        }
+                                                    </>
+                                                },
+                                                None => html! { {"—"} },
+                                            }}
+                                        </td>
+                                        <td class="text-nowrap">
+                                            <button class="btn btn-outline-secondary btn-sm me-2"
+                                                onclick={Callback::from(move |_| load_form.emit(Some(job_view.clone())))}>{"View"}</button>
+                                            if can_edit {
+                                                <button class="btn btn-danger btn-sm"
+                                                    onclick={Callback::from(move |_| on_delete.emit(job_del.clone()))}>{"Delete"}</button>
+                                            }
+                                        </td>
+                                    </tr>
+                                }
+                            })}
+                        </tbody>
+                    </table>
+
+                    if let Some(view) = viewed {
+                        <h4>{format!("Routing for {}", view.job.customer)}</h4>
+                        <p>
+                            {format!("{} of {} tasks done. ", view.done, view.total)}
+                            {match view.projected_completion {
+                                Some(at) if view.late() => html! {
+                                    <span class="text-danger">
+                                        {format!("Projected to finish {}, {} after the due date.",
+                                            local_label(at), span_label((at - view.job.due_date).num_minutes()))}
+                                    </span>
+                                },
+                                Some(at) => html! {
+                                    <span class="text-success">{format!("Projected to finish {}, in time for the due date.", local_label(at))}</span>
+                                },
+                                None => html! {},
+                            }}
+                        </p>
+                        <table class="table table-sm">
+                            <thead>
+                                <tr>
+                                    <th>{"#"}</th>
+                                    <th>{"Operation"}</th>
+                                    <th>{"Worker"}</th>
+                                    <th>{"Machines"}</th>
+                                    <th>{"Planned"}</th>
+                                    <th>{"Status"}</th>
+                                </tr>
+                            </thead>
+                            <tbody>
+                                {for view.tasks.iter().enumerate().map(|(index, task)| {
+                                    let status = match task.status {
+                                        TaskStatus::Done => "badge bg-success",
+                                        TaskStatus::InProgress => "badge bg-primary",
+                                        TaskStatus::Cancelled => "badge bg-light text-dark",
+                                        TaskStatus::Planned => "badge bg-secondary",
+                                    };
+                                    html! {
+                                        <tr key={task.id.clone()}>
+                                            <td>{index + 1}</td>
+                                            <td>{&task.operation_id}</td>
+                                            <td>{&task.user_id}</td>
+                                            <td>{task.machine_ids.join(", ")}</td>
+                                            <td>{format!("{} – {}", local_label(task.start_time), task.end_time().with_timezone(&Local).format("%H:%M"))}</td>
+                                            <td><span class={status}>{task.status.label()}</span></td>
+                                        </tr>
+                                    }
+                                })}
+                            </tbody>
+                        </table>
+                    }
+                </div>
+                <div class="col-md-5">
+                    <div class="card p-3 mb-3">
+                        <h4>{if editing.is_some() { "Edit Job" } else { "Plan Job" }}</h4>
+                        <fieldset disabled={!can_edit}>
+                            <div class="mb-2">
+                                <input class={classes!("form-control", invalid("customer"))} placeholder="Customer"
+                                    value={(*form_customer).clone()} oninput={text_input(&form_customer)} />
+                                {feedback("customer")}
+                            </div>
+                            <div class="row mb-2">
+                                <div class="col">
+                                    <label class="form-label">{"Due"}</label>
+                                    <input type="datetime-local" class={classes!("form-control", invalid("due_date"))}
+                                        value={(*form_due).clone()} oninput={text_input(&form_due)} />
+                                    {feedback("due_date")}
+                                </div>
+                                <div class="col">
+                                    <label class="form-label">{"Priority"}</label>
+                                    <select class="form-select" onchange={on_priority}>
+                                        {for JobPriority::ALL.iter().enumerate().map(|(i, priority)| html! {
+                                            <option value={i.to_string()} selected={*priority == *form_priority}>{priority.label()}</option>
+                                        })}
+                                    </select>
+                                </div>
+                            </div>
+                            <div class="mb-2">
+                                <textarea class="form-control" placeholder="Notes" rows="2"
+                                    value={(*form_notes).clone()}
+                                    oninput={let form_notes = form_notes.clone(); Callback::from(move |e: InputEvent| {
+                                        form_notes.set(e.target_unchecked_into::<web_sys::HtmlTextAreaElement>().value())
+                                    })} />
+                            </div>
+
+                            if editing.is_none() {
+                                <div class="mb-2">
+                                    <label class="form-label">{"First step starts"}</label>
+                                    <input type="datetime-local" class={classes!("form-control", invalid("start_time"))}
+                                        value={(*form_start).clone()} oninput={text_input(&form_start)} />
+                                    {feedback("start_time")}
+                                </div>
+                                <h6>{"Routing"}</h6>
+                                {feedback("steps")}
+                                <RoutingEditor steps={(*form_steps).clone()} presets={(*presets).clone()} errors={(*field_errors).clone()}
+                                    on_change={let form_steps = form_steps.clone(); Callback::from(move |steps| form_steps.set(steps))} />
+                            }
+
+                            <div class="d-flex gap-2">
+                                <button class="btn btn-primary" onclick={on_save}>
+                                    {if editing.is_some() { "Save Job" } else { "Plan Job" }}
+                                </button>
+                                if editing.is_some() {
+                                    <button class="btn btn-secondary"
+                                        onclick={let load_form = load_form.clone(); Callback::from(move |_| load_form.emit(None))}>{"New Job"}</button>
+                                }
+                            </div>
+                        </fieldset>
+                    </div>
+                </div>
+            </div>
+            <HistoryPanel entity={AuditEntity::Job} entity_id={(*editing).clone()} />
+        </div>
+    }
+}
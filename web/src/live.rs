@@ -0,0 +1,169 @@
+// Live updates: pages subscribe to the server's change stream and patch their lists in place
+use futures::channel::oneshot;
+use futures::{FutureExt, StreamExt};
+use gloo::timers::future::TimeoutFuture;
+use gloo_net::websocket::futures::WebSocket;
+use gloo_net::websocket::Message;
+use serde::de::DeserializeOwned;
+use shared::{AuditEntry, InventoryItem, Machine, ServerEvent, Task, Worker};
+use std::ops::Deref;
+use std::rc::Rc;
+use yew::prelude::*;
+use crate::api;
+
+// Wait before reconnecting after the connection drops
+const RECONNECT_MILLIS: u32 = 3_000;
+
+// What change events identify a record by
+pub trait Keyed {
+    fn key(&self) -> &str;
+}
+
+impl Keyed for Task {
+    fn key(&self) -> &str {
+        &self.id
+    }
+}
+
+impl Keyed for InventoryItem {
+    fn key(&self) -> &str {
+        &self.name
+    }
+}
+
+impl Keyed for Worker {
+    fn key(&self) -> &str {
+        &self.id
+    }
+}
+
+impl Keyed for Machine {
+    fn key(&self) -> &str {
+        &self.id
+    }
+}
+
+pub enum ListAction<T> {
+    Replace(Vec<T>),
+    // The record stored under `key` is now `after`, or gone if that is None.
+    // `after` may carry a different key when the record was renamed.
+    Change { key: String, after: Option<T> },
+}
+
+impl<T: Keyed + DeserializeOwned> ListAction<T> {
+    // None if the entry's JSON does not fit `T`; the caller should reload instead
+    pub fn from_entry(entry: &AuditEntry) -> Option<Self> {
+        let after = match &entry.after {
+            Some(value) => Some(serde_json::from_value::<T>(value.clone()).ok()?),
+            None => None,
+        };
+        let key = entry.before.clone()
+            .and_then(|value| serde_json::from_value::<T>(value).ok())
+            .map(|before| before.key().to_string())
+            .unwrap_or_else(|| entry.entity_id.clone());
+        Some(ListAction::Change { key, after })
+    }
+}
+
+// A list held in a reducer so that changes arriving back to back all apply
+#[derive(PartialEq)]
+pub struct LiveList<T> {
+    items: Vec<T>,
+}
+
+impl<T> Default for LiveList<T> {
+    fn default() -> Self {
+        Self { items: Vec::new() }
+    }
+}
+
+impl<T> Deref for LiveList<T> {
+    type Target = Vec<T>;
+
+    fn deref(&self) -> &Vec<T> {
+        &self.items
+    }
+}
+
+impl<T: Keyed + Clone + 'static> Reducible for LiveList<T> {
+    type Action = ListAction<T>;
+
+    fn reduce(self: Rc<Self>, action: Self::Action) -> Rc<Self> {
+        match action {
+            ListAction::Replace(items) => Rc::new(Self { items }),
+            ListAction::Change { key, after } => {
+                // Keep the record where it was so lists don't jump around
+                let position = self.items.iter().position(|i| i.key() == key);
+                let mut items: Vec<T> = self.items.iter()
+                    .filter(|i| i.key() != key && after.as_ref().is_none_or(|a| i.key() != a.key()))
+                    .cloned()
+                    .collect();
+                if let Some(after) = after {
+                    let at = position.unwrap_or(items.len()).min(items.len());
+                    items.insert(at, after);
+                }
+                Rc::new(Self { items })
+            }
+        }
+    }
+}
+
+// One field of a record someone else saved while the user was editing it: the user's value if
+// they changed it from `base`, the `latest` stored one otherwise
+pub fn merge_field<T: PartialEq + Clone>(base: &T, mine: &T, latest: &T) -> T {
+    if mine != base { mine.clone() } else { latest.clone() }
+}
+
+// Asks what to do about a save refused because someone else saved first: true to apply the
+// user's changes on top of the latest version, false to drop them and load it
+pub fn confirm_merge(conflict: &str) -> bool {
+    gloo::dialogs::confirm(&format!(
+        "{}\n\nOK: apply your changes on top of the latest version\nCancel: discard your changes and load the latest version",
+        conflict,
+    ))
+}
+
+// Streams server events to `on_event` while the component is mounted, reconnecting when the
+// connection drops. Changes may have been missed by then, so a reconnect is reported as Resync.
+#[hook]
+pub fn use_server_events(on_event: Callback<ServerEvent>) {
+    use_effect_with_deps(move |_| {
+        let (stop, stopped) = oneshot::channel::<()>();
+        wasm_bindgen_futures::spawn_local(listen(on_event, stopped));
+        move || {
+            let _ = stop.send(());
+        }
+    }, ());
+}
+
+async fn listen(on_event: Callback<ServerEvent>, stopped: oneshot::Receiver<()>) {
+    let mut stopped = stopped.fuse();
+    let mut reconnecting = false;
+    // Ends on logout or expiry: without a session the server would refuse anyway
+    while let Some(session) = api::auth::session() {
+        if let Ok(mut socket) = WebSocket::open(&api::events_url(&session.token)) {
+            if reconnecting {
+                on_event.emit(ServerEvent::Resync);
+            }
+            loop {
+                futures::select! {
+                    _ = stopped => return,
+                    message = socket.next().fuse() => match message {
+                        Some(Ok(Message::Text(text))) => {
+                            if let Ok(event) = serde_json::from_str::<ServerEvent>(&text) {
+                                on_event.emit(event);
+                            }
+                        }
+                        Some(Ok(Message::Bytes(_))) => {}
+                        Some(Err(_)) | None => break,
+                    },
+                }
+            }
+        }
+        reconnecting = true;
+        futures::select! {
+            _ = stopped => return,
+            _ = TimeoutFuture::new(RECONNECT_MILLIS).fuse() => {}
+        }
+    }
+}
@@ -0,0 +1,77 @@
+use yew::prelude::*;
+use yew_router::prelude::*;
+use web_sys::{InputEvent, SubmitEvent};
+use crate::Route;
+use crate::api;
+use crate::toast::use_toaster;
+
+#[function_component(Login)]
+pub fn login_page() -> Html {
+    let username = use_state(|| "".to_string());
+    let password = use_state(|| "".to_string());
+    let busy = use_state(|| false);
+    let navigator = use_navigator().expect("Login outside router");
+    let toaster = use_toaster();
+
+    let on_submit = {
+        let username = username.clone();
+        let password = password.clone();
+        let busy = busy.clone();
+        Callback::from(move |e: SubmitEvent| {
+            e.prevent_default();
+            let username = (*username).clone();
+            let password = (*password).clone();
+            let busy = busy.clone();
+            let navigator = navigator.clone();
+            let toaster = toaster.clone();
+            busy.set(true);
+            wasm_bindgen_futures::spawn_local(async move {
+                let result = api::auth::login(username.trim(), &password).await;
+                busy.set(false);
+                if toaster.report(result).is_some() {
+                    navigator.push(&Route::Home);
+                }
+            });
+        })
+    };
+
+    let username_input = username.clone();
+    let password_input = password.clone();
+    html! {
+        <div class="container" style="max-width: 24rem">
+            <h2 class="mt-5 mb-3">{"Sign in"}</h2>
+            <form onsubmit={on_submit}>
+                <div class="mb-3">
+                    <label class="form-label">{"Username"}</label>
+                    <input class="form-control" autocomplete="username" value={(*username).clone()}
+                        oninput={Callback::from(move |e: InputEvent| username_input.set(e.target_unchecked_into::<web_sys::HtmlInputElement>().value()))} />
+                </div>
+                <div class="mb-3">
+                    <label class="form-label">{"Password"}</label>
+                    <input type="password" class="form-control" autocomplete="current-password" value={(*password).clone()}
+                        oninput={Callback::from(move |e: InputEvent| password_input.set(e.target_unchecked_into::<web_sys::HtmlInputElement>().value()))} />
+                </div>
+                <button type="submit" class="btn btn-primary w-100" disabled={*busy}>{"Sign in"}</button>
+            </form>
+        </div>
+    }
+}
+
+// Who is signed in, with a way out; sits at the end of each page's navigation row
+#[function_component(UserBar)]
+pub fn user_bar() -> Html {
+    let navigator = use_navigator().expect("UserBar outside router");
+    let Some(session) = api::auth::session() else {
+        return html! {};
+    };
+    let on_logout = Callback::from(move |_| {
+        api::auth::forget();
+        navigator.push(&Route::Login);
+    });
+    html! {
+        <span class="float-end">
+            <span class="me-2 text-muted">{format!("{} ({})", session.user.username, session.user.role.label())}</span>
+            <button class="btn btn-outline-secondary btn-sm" onclick={on_logout}>{"Log out"}</button>
+        </span>
+    }
+}
@@ -0,0 +1,253 @@
+use yew::prelude::*;
+use yew_router::prelude::*;
+use std::collections::HashMap;
+use web_sys::InputEvent;
+use shared::{AuditEntity, FieldError, Machine, ServerEvent, Shift};
+use crate::Route;
+use crate::history::HistoryPanel;
+use crate::live::{use_server_events, ListAction, LiveList};
+use crate::login::UserBar;
+use crate::shifts::{shift_label, ShiftEditor};
+use crate::api;
+use crate::toast::use_toaster;
+
+// The machine the form describes, or the inputs that need fixing first
+fn form_machine(id: &str, name: &str, availability: &[Shift]) -> Result<Machine, Vec<FieldError>> {
+    let machine = Machine {
+        id: id.trim().to_string(),
+        name: name.trim().to_string(),
+        availability: availability.to_vec(),
+    };
+    let errors = machine.validate();
+    if errors.is_empty() { Ok(machine) } else { Err(errors) }
+}
+
+#[function_component(MachinesPage)]
+pub fn machines_page() -> Html {
+    let machines = use_reducer(LiveList::<Machine>::default);
+    // The id of the machine being edited; None while adding a new one
+    let editing = use_state(|| None::<String>);
+    let form_id = use_state(String::new);
+    let form_name = use_state(String::new);
+    let form_availability = use_state(Vec::<Shift>::new);
+    let field_errors = use_state(HashMap::<String, String>::new);
+    let toaster = use_toaster();
+    // Everyone may look machines up; only admins change them
+    let can_edit = api::auth::session().is_some_and(|s| s.user.role.can_administer());
+
+    let fetch_machines = {
+        let machines = machines.clone();
+        let toaster = toaster.clone();
+        Callback::from(move |_| {
+            let machines = machines.clone();
+            let toaster = toaster.clone();
+            wasm_bindgen_futures::spawn_local(async move {
+                if let Some(fetched) = toaster.report(api::machines::list().await) {
+                    machines.dispatch(ListAction::Replace(fetched));
+                }
+            });
+        })
+    };
+
+    {
+        let fetch_machines = fetch_machines.clone();
+        use_effect_with_deps(move |_| {
+            fetch_machines.emit(());
+            || {}
+        }, ());
+    }
+
+    {
+        let machines = machines.dispatcher();
+        let fetch_machines = fetch_machines.clone();
+        use_server_events(Callback::from(move |event: ServerEvent| match event {
+            ServerEvent::Changed(entry) if entry.entity == AuditEntity::Machine => {
+                match ListAction::from_entry(&entry) {
+                    Some(action) => machines.dispatch(action),
+                    None => fetch_machines.emit(()),
+                }
+            }
+            ServerEvent::Changed(_) => {}
+            ServerEvent::Resync => fetch_machines.emit(()),
+        }));
+    }
+
+    // Fill the form from `machine`, or clear it for a new one
+    let load_form = {
+        let editing = editing.clone();
+        let form_id = form_id.clone();
+        let form_name = form_name.clone();
+        let form_availability = form_availability.clone();
+        let field_errors = field_errors.clone();
+        Callback::from(move |machine: Option<Machine>| {
+            field_errors.set(HashMap::new());
+            editing.set(machine.as_ref().map(|m| m.id.clone()));
+            let machine = machine.unwrap_or(Machine {
+                id: String::new(),
+                name: String::new(),
+                availability: Vec::new(),
+            });
+            form_id.set(machine.id);
+            form_name.set(machine.name);
+            form_availability.set(machine.availability);
+        })
+    };
+
+    let on_save = {
+        let editing = editing.clone();
+        let form_id = form_id.clone();
+        let form_name = form_name.clone();
+        let form_availability = form_availability.clone();
+        let field_errors = field_errors.clone();
+        let load_form = load_form.clone();
+        let toaster = toaster.clone();
+        Callback::from(move |_| {
+            let machine = match form_machine(&form_id, &form_name, &form_availability) {
+                Ok(machine) => machine,
+                Err(errors) => {
+                    field_errors.set(errors.iter().map(|e| (e.field.clone(), e.message.clone())).collect());
+                    return;
+                }
+            };
+            let creating = editing.is_none();
+            let field_errors = field_errors.clone();
+            let load_form = load_form.clone();
+            let toaster = toaster.clone();
+            wasm_bindgen_futures::spawn_local(async move {
+                let result = if creating {
+                    api::machines::create(&machine).await
+                } else {
+                    api::machines::update(&machine).await
+                };
+                if let Err(api::Error::Api(e)) = &result {
+                    field_errors.set(e.field_errors.iter().map(|e| (e.field.clone(), e.message.clone())).collect());
+                }
+                if let Some(saved) = toaster.report(result) {
+                    load_form.emit(Some(saved));
+                }
+            });
+        })
+    };
+
+    let on_delete = {
+        let editing = editing.clone();
+        let load_form = load_form.clone();
+        let toaster = toaster.clone();
+        Callback::from(move |id: String| {
+            if !gloo::dialogs::confirm(&format!("Delete machine '{}'?", id)) {
+                return;
+            }
+            if editing.as_deref() == Some(id.as_str()) {
+                load_form.emit(None);
+            }
+            let toaster = toaster.clone();
+            wasm_bindgen_futures::spawn_local(async move {
+                toaster.report(api::machines::delete(&id).await);
+            });
+        })
+    };
+
+    let invalid = |field: &str| if field_errors.contains_key(field) { "is-invalid" } else { "" };
+    let feedback = |field: &str| match field_errors.get(field) {
+        Some(message) => html! { <div class="invalid-feedback d-block">{message}</div> },
+        None => html! {},
+    };
+    let text_input = |handle: &UseStateHandle<String>| {
+        let handle = handle.clone();
+        Callback::from(move |e: InputEvent| handle.set(e.target_unchecked_into::<web_sys::HtmlInputElement>().value()))
+    };
+
+    html! {
+        <div class="container">
+            <div class="mb-3">
+                <Link<Route> to={Route::Home} classes="btn btn-outline-primary me-2">{"Gantt Chart"}</Link<Route>>
+                <Link<Route> to={Route::Inventory} classes="btn btn-outline-success me-2">{"Inventory"}</Link<Route>>
+                <Link<Route> to={Route::Presets} classes="btn btn-outline-info me-2">{"Task Presets"}</Link<Route>>
+                <Link<Route> to={Route::Workers} classes="btn btn-outline-dark me-2">{"Workers"}</Link<Route>>
+                <Link<Route> to={Route::Machines} classes="btn btn-outline-secondary me-2">{"Machines"}</Link<Route>>
+                <Link<Route> to={Route::Jobs} classes="btn btn-outline-warning">{"Jobs"}</Link<Route>>
+                <UserBar />
+            </div>
+            <h2>{"Machines"}</h2>
+            <div class="row">
+                <div class="col-md-7">
+                    <table class="table table-bordered table-sm">
+                        <thead>
+                            <tr>
+                                <th>{"ID"}</th>
+                                <th>{"Name"}</th>
+                                <th>{"Available (UTC)"}</th>
+                                <th></th>
+                            </tr>
+                        </thead>
+                        <tbody>
+                            {for machines.iter().map(|machine| {
+                                let selected = editing.as_deref() == Some(machine.id.as_str());
+                                let machine_edit = machine.clone();
+                                let id_del = machine.id.clone();
+                                let load_form = load_form.clone();
+                                let on_delete = on_delete.clone();
+                                html! {
+                                    <tr key={machine.id.clone()} class={classes!(selected.then_some("table-active"))}>
+                                        <td>{&machine.id}</td>
+                                        <td>{&machine.name}</td>
+                                        <td>
+                                            if machine.availability.is_empty() {
+                                                {"Always"}
+                                            }
+                                            {for machine.availability.iter().map(|s| html! {
+                                                <div>{shift_label(s)}</div>
+                                            })}
+                                        </td>
+                                        <td class="text-nowrap">
+                                            <button class="btn btn-outline-secondary btn-sm me-2"
+                                                onclick={Callback::from(move |_| load_form.emit(Some(machine_edit.clone())))}>
+                                                {if can_edit { "Edit" } else { "View" }}
+                                            </button>
+                                            if can_edit {
+                                                <button class="btn btn-danger btn-sm"
+                                                    onclick={Callback::from(move |_| on_delete.emit(id_del.clone()))}>{"Delete"}</button>
+                                            }
+                                        </td>
+                                    </tr>
+                                }
+                            })}
+                        </tbody>
+                    </table>
+                </div>
+                <div class="col-md-5">
+                    <div class="card p-3 mb-3">
+                        <h4>{if editing.is_some() { "Edit Machine" } else { "Add Machine" }}</h4>
+                        <fieldset disabled={!can_edit}>
+                            <div class="mb-2">
+                                <input class={classes!("form-control", invalid("id"))} placeholder="Machine ID"
+                                    disabled={editing.is_some()}
+                                    value={(*form_id).clone()} oninput={text_input(&form_id)} />
+                                {feedback("id")}
+                            </div>
+                            <div class="mb-2">
+                                <input class={classes!("form-control", invalid("name"))} placeholder="Name"
+                                    value={(*form_name).clone()} oninput={text_input(&form_name)} />
+                                {feedback("name")}
+                            </div>
+
+                            <h6>{"Weekly availability (UTC)"}</h6>
+                            <div class="form-text mb-1">{"Leave empty if the machine can run at any time."}</div>
+                            <ShiftEditor shifts={(*form_availability).clone()}
+                                on_change={let form_availability = form_availability.clone(); Callback::from(move |availability| form_availability.set(availability))} />
+
+                            <div class="d-flex gap-2">
+                                <button class="btn btn-primary" onclick={on_save}>{"Save Machine"}</button>
+                                if editing.is_some() {
+                                    <button class="btn btn-secondary"
+                                        onclick={let load_form = load_form.clone(); Callback::from(move |_| load_form.emit(None))}>{"New Machine"}</button>
+                                }
+                            </div>
+                        </fieldset>
+                    </div>
+                </div>
+            </div>
+            <HistoryPanel entity={AuditEntity::Machine} entity_id={(*editing).clone()} />
+        </div>
+    }
+}
@@ -1,13 +1,26 @@
+mod api;
+mod history;
 mod home;
 mod inventory;
+mod jobs;
+mod live;
+mod login;
+mod machines;
 mod presets;
-mod types;
+mod shifts;
+mod toast;
+mod workers;
 
 use yew::prelude::*;
 use yew_router::prelude::*;
 use home::Home;
 use inventory::Inventory;
+use login::Login;
+use jobs::JobsPage;
+use machines::MachinesPage;
 use presets::PresetsPage;
+use toast::ToastProvider;
+use workers::WorkersPage;
 
 #[derive(Clone, Routable, PartialEq)]
 pub enum Route {
@@ -17,25 +30,47 @@ pub enum Route {
     Inventory,
     #[at("/presets")]
     Presets,
+    #[at("/workers")]
+    Workers,
+    #[at("/machines")]
+    Machines,
+    #[at("/jobs")]
+    Jobs,
+    #[at("/login")]
+    Login,
 }
 
 fn switch(routes: Route) -> Html {
+    // The server would refuse every request anyway, so go straight to the login page
+    if routes != Route::Login && api::auth::session().is_none() {
+        return html! { <Redirect<Route> to={Route::Login} /> };
+    }
     match routes {
+        Route::Login => html! { <Login /> },
         Route::Home => html! { <Home /> },
         Route::Inventory => html! { <Inventory /> },
         Route::Presets => html! { <PresetsPage /> },
+        Route::Workers => html! { <WorkersPage /> },
+        Route::Machines => html! { <MachinesPage /> },
+        Route::Jobs => html! { <JobsPage /> },
     }
 }
 
 #[function_component(Main)]
 fn main_app() -> Html {
     html! {
-        <BrowserRouter>
-            <Switch<Route> render={switch} />
-        </BrowserRouter>
+        <ToastProvider>
+            <BrowserRouter>
+                <Switch<Route> render={switch} />
+            </BrowserRouter>
+        </ToastProvider>
     }
 }
 
 fn main() {
-    yew::Renderer::<Main>::new().render();
+    // The API base URL may come from config.json, so resolve it before anything fetches
+    wasm_bindgen_futures::spawn_local(async {
+        api::init().await;
+        yew::Renderer::<Main>::new().render();
+    });
 }
\ No newline at end of file
@@ -2,41 +2,127 @@ use yew::prelude::*;
 use yew_router::prelude::*;
 use std::collections::HashMap;
 use gloo::storage::{LocalStorage, Storage};
-use wasm_bindgen::JsCast;
-use web_sys::{HtmlInputElement, InputEvent};
+use web_sys::InputEvent;
 use crate::Route;
-use crate::types::TaskPreset;
+use crate::login::UserBar;
+use crate::api;
+use crate::toast::{use_toaster, Toaster};
+use shared::{Quantity, TaskPreset};
+
+// Where presets lived before they moved to the server
+const LEGACY_STORAGE_KEY: &str = "task_presets";
+
+// Comma separated input to a list, dropping blanks
+fn split_list(value: &str) -> Vec<String> {
+    value.split(',').map(str::trim).filter(|s| !s.is_empty()).map(str::to_string).collect()
+}
+
+// Loads presets from the server. An admin's browser first moves any presets left in its
+// LocalStorage over, once; other roles may not import and leave them for an admin.
+pub async fn load_presets(toaster: &Toaster) -> Result<HashMap<String, TaskPreset>, api::Error> {
+    let can_import = api::auth::session().is_some_and(|s| s.user.role.can_administer());
+    let legacy = LocalStorage::get::<HashMap<String, TaskPreset>>(LEGACY_STORAGE_KEY).ok().filter(|_| can_import);
+    if let Some(mut legacy) = legacy {
+        // The server refuses the whole import over one bad entry, and it could never be saved anyway
+        legacy.retain(|op_id, preset| !op_id.trim().is_empty() && preset.validate().is_empty());
+        // A failed import is retried on the next load; the catalog still loads meanwhile
+        if toaster.report(api::presets::import(&legacy).await).is_some() {
+            LocalStorage::delete(LEGACY_STORAGE_KEY);
+        }
+    }
+    api::presets::list().await
+}
 
 #[function_component(PresetsPage)]
 pub fn presets_page() -> Html {
-    let presets = use_state(|| {
-        let loaded: HashMap<String, TaskPreset> = LocalStorage::get("task_presets").unwrap_or_default();
-        loaded
-    });
+    let presets = use_state(HashMap::<String, TaskPreset>::new);
+    let toaster = use_toaster();
+    // Everyone may look up presets; only admins change them
+    let can_edit = api::auth::session().is_some_and(|s| s.user.role.can_administer());
 
     {
         let presets = presets.clone();
-        use_effect_with_deps(move |presets| {
-            LocalStorage::set("task_presets", &**presets).unwrap();
+        let toaster = toaster.clone();
+        use_effect_with_deps(move |_| {
+            wasm_bindgen_futures::spawn_local(async move {
+                if let Some(loaded) = toaster.report(load_presets(&toaster).await) {
+                    presets.set(loaded);
+                }
+            });
             || {}
-        }, presets);
+        }, ());
     }
 
+    // Every edit is written through to the server
+    let persist = {
+        let toaster = toaster.clone();
+        Callback::from(move |(op_id, preset): (String, TaskPreset)| {
+            let toaster = toaster.clone();
+            wasm_bindgen_futures::spawn_local(async move {
+                toaster.report(api::presets::save(&op_id, &preset).await);
+            });
+        })
+    };
+
     let update_duration = {
         let presets = presets.clone();
+        let persist = persist.clone();
         Callback::from(move |(op_id, val): (String, String)| {
             let mut current = (*presets).clone();
             if let Some(preset) = current.get_mut(&op_id) {
                 if let Ok(d) = val.parse::<i64>() {
                     preset.duration_minutes = d;
+                    persist.emit((op_id, preset.clone()));
+                    presets.set(current);
+                }
+            }
+        })
+    };
+
+    let update_setup = {
+        let presets = presets.clone();
+        let persist = persist.clone();
+        Callback::from(move |(op_id, val): (String, String)| {
+            let mut current = (*presets).clone();
+            if let Some(preset) = current.get_mut(&op_id) {
+                if let Ok(m) = val.parse::<i64>() {
+                    preset.setup_minutes = m;
+                    persist.emit((op_id, preset.clone()));
                     presets.set(current);
                 }
             }
         })
     };
 
+    let update_skills = {
+        let presets = presets.clone();
+        let persist = persist.clone();
+        Callback::from(move |(op_id, val): (String, String)| {
+            let mut current = (*presets).clone();
+            if let Some(preset) = current.get_mut(&op_id) {
+                preset.required_skills = split_list(&val);
+                persist.emit((op_id, preset.clone()));
+                presets.set(current);
+            }
+        })
+    };
+
+    let update_machines = {
+        let presets = presets.clone();
+        let persist = persist.clone();
+        Callback::from(move |(op_id, val): (String, String)| {
+            let mut current = (*presets).clone();
+            if let Some(preset) = current.get_mut(&op_id) {
+                preset.allowed_machines = split_list(&val);
+                persist.emit((op_id, preset.clone()));
+                presets.set(current);
+            }
+        })
+    };
+
     let add_material = {
         let presets = presets.clone();
+        let persist = persist.clone();
         Callback::from(move |op_id: String| {
             let mut current = (*presets).clone();
             if let Some(preset) = current.get_mut(&op_id) {
@@ -46,7 +132,8 @@ pub fn presets_page() -> Html {
                     name = format!("New Material {}", i);
                     i += 1;
                 }
-                preset.materials.insert(name, "0".to_string());
+                preset.materials.insert(name, Quantity::new(0.0, ""));
+                persist.emit((op_id, preset.clone()));
                 presets.set(current);
             }
         })
@@ -54,12 +141,14 @@ pub fn presets_page() -> Html {
 
     let update_material_key = {
         let presets = presets.clone();
+        let persist = persist.clone();
         Callback::from(move |(op_id, old_key, new_key): (String, String, String)| {
             let mut current = (*presets).clone();
             if let Some(preset) = current.get_mut(&op_id) {
                 if !preset.materials.contains_key(&new_key) {
                     if let Some(val) = preset.materials.remove(&old_key) {
                         preset.materials.insert(new_key, val);
+                        persist.emit((op_id, preset.clone()));
                         presets.set(current);
                     }
                 }
@@ -69,10 +158,20 @@ pub fn presets_page() -> Html {
 
     let update_material_val = {
         let presets = presets.clone();
+        let persist = persist.clone();
+        let toaster = toaster.clone();
         Callback::from(move |(op_id, key, val): (String, String, String)| {
+            let qty = match Quantity::parse(&val) {
+                Ok(qty) => qty,
+                Err(e) => {
+                    toaster.error(format!("Invalid quantity for {}: {}", key, e));
+                    return;
+                }
+            };
             let mut current = (*presets).clone();
             if let Some(preset) = current.get_mut(&op_id) {
-                preset.materials.insert(key, val);
+                preset.materials.insert(key, qty);
+                persist.emit((op_id, preset.clone()));
                 presets.set(current);
             }
         })
@@ -80,26 +179,33 @@ pub fn presets_page() -> Html {
 
     let delete_material = {
         let presets = presets.clone();
+        let persist = persist.clone();
         Callback::from(move |(op_id, key): (String, String)| {
             let mut current = (*presets).clone();
             if let Some(preset) = current.get_mut(&op_id) {
                 preset.materials.remove(&key);
+                persist.emit((op_id, preset.clone()));
                 presets.set(current);
             }
         })
     };
 
-    let delete_preset = {
+    let on_delete_preset = {
         let presets = presets.clone();
+        let toaster = toaster.clone();
         Callback::from(move |op_id: String| {
             let mut current = (*presets).clone();
             current.remove(&op_id);
             presets.set(current);
+            let toaster = toaster.clone();
+            wasm_bindgen_futures::spawn_local(async move {
+                toaster.report(api::presets::delete(&op_id).await);
+            });
         })
     };
 
-    let filter_op_name = use_state(|| String::new());
-    let filter_material = use_state(|| String::new());
+    let filter_op_name = use_state(String::new);
+    let filter_material = use_state(String::new);
 
     let filter_op = (*filter_op_name).to_lowercase();
     let filter_mat = (*filter_material).to_lowercase();
@@ -123,9 +229,13 @@ pub fn presets_page() -> Html {
             <div class="mb-3">
                 <Link<Route> to={Route::Home} classes="btn btn-outline-primary me-2">{"Gantt Chart"}</Link<Route>>
                 <Link<Route> to={Route::Inventory} classes="btn btn-outline-success me-2">{"Inventory"}</Link<Route>>
-                <Link<Route> to={Route::Presets} classes="btn btn-outline-info">{"Task Presets"}</Link<Route>>
+                <Link<Route> to={Route::Presets} classes="btn btn-outline-info me-2">{"Task Presets"}</Link<Route>>
+                <Link<Route> to={Route::Workers} classes="btn btn-outline-dark me-2">{"Workers"}</Link<Route>>
+                <Link<Route> to={Route::Machines} classes="btn btn-outline-secondary me-2">{"Machines"}</Link<Route>>
+                <Link<Route> to={Route::Jobs} classes="btn btn-outline-warning">{"Jobs"}</Link<Route>>
+                <UserBar />
             </div>
-            <h2>{"Operation Presets"}</h2>
+            <h2>{"Operation Catalog"}</h2>
             
             <div class="row mb-3">
                 <div class="col">
@@ -173,17 +283,19 @@ pub fn presets_page() -> Html {
                     <div class="card mb-3">
                         <div class="card-header d-flex justify-content-between align-items-center">
                             <h5 class="mb-0">{key}</h5>
-                            <button class="btn btn-danger btn-sm" onclick={
-                                let delete_preset = delete_preset.clone();
-                                let k = key_del_preset.clone();
-                                move |_| delete_preset.emit(k.clone())
-                            }>{"Delete Preset"}</button>
+                            if can_edit {
+                                <button class="btn btn-danger btn-sm" onclick={
+                                    let on_delete_preset = on_delete_preset.clone();
+                                    let k = key_del_preset.clone();
+                                    move |_| on_delete_preset.emit(k.clone())
+                                }>{"Delete Preset"}</button>
+                            }
                         </div>
                         <div class="card-body">
                             <div class="mb-3 row">
                                 <label class="col-sm-2 col-form-label">{"Duration (min)"}</label>
                                 <div class="col-sm-10">
-                                    <input type="number" class="form-control" 
+                                    <input type="number" class="form-control" disabled={!can_edit}
                                         value={preset.duration_minutes.to_string()} 
                                         oninput={
                                             let update_duration = update_duration.clone();
@@ -196,8 +308,56 @@ pub fn presets_page() -> Html {
                                     />
                                 </div>
                             </div>
+                            <div class="mb-3 row">
+                                <label class="col-sm-2 col-form-label">{"Setup (min)"}</label>
+                                <div class="col-sm-10">
+                                    <input type="number" min="0" class="form-control" disabled={!can_edit}
+                                        value={preset.setup_minutes.to_string()}
+                                        oninput={
+                                            let update_setup = update_setup.clone();
+                                            let k = key_c.clone();
+                                            Callback::from(move |e: InputEvent| {
+                                                let val = e.target_unchecked_into::<web_sys::HtmlInputElement>().value();
+                                                update_setup.emit((k.clone(), val));
+                                            })
+                                        }
+                                    />
+                                </div>
+                            </div>
+                            <div class="mb-3 row">
+                                <label class="col-sm-2 col-form-label">{"Required skills"}</label>
+                                <div class="col-sm-10">
+                                    <input class="form-control" disabled={!can_edit} placeholder="Comma separated"
+                                        value={preset.required_skills.join(", ")}
+                                        onchange={
+                                            let update_skills = update_skills.clone();
+                                            let k = key_c.clone();
+                                            Callback::from(move |e: Event| {
+                                                let val = e.target_unchecked_into::<web_sys::HtmlInputElement>().value();
+                                                update_skills.emit((k.clone(), val));
+                                            })
+                                        }
+                                    />
+                                </div>
+                            </div>
+                            <div class="mb-3 row">
+                                <label class="col-sm-2 col-form-label">{"Allowed machines"}</label>
+                                <div class="col-sm-10">
+                                    <input class="form-control" disabled={!can_edit} placeholder="Machine ids, comma separated; empty for none"
+                                        value={preset.allowed_machines.join(", ")}
+                                        onchange={
+                                            let update_machines = update_machines.clone();
+                                            let k = key_c.clone();
+                                            Callback::from(move |e: Event| {
+                                                let val = e.target_unchecked_into::<web_sys::HtmlInputElement>().value();
+                                                update_machines.emit((k.clone(), val));
+                                            })
+                                        }
+                                    />
+                                </div>
+                            </div>
                             
-                            <h6>{"Materials"}</h6>
+                            <h6>{"Default Materials"}</h6>
                             <table class="table table-bordered table-sm">
                                 <thead>
                                     <tr>
@@ -222,7 +382,7 @@ pub fn presets_page() -> Html {
                                             html! {
                                                 <tr key={m_name.clone()}>
                                                     <td>
-                                                        <input class="form-control form-control-sm" 
+                                                        <input class="form-control form-control-sm" disabled={!can_edit}
                                                             value={m_name.clone()} 
                                                             onchange={Callback::from(move |e: Event| {
                                                                 let val = e.target_unchecked_into::<web_sys::HtmlInputElement>().value();
@@ -231,18 +391,20 @@ pub fn presets_page() -> Html {
                                                         />
                                                     </td>
                                                     <td>
-                                                        <input class="form-control form-control-sm" 
-                                                            value={m_qty.clone()} 
-                                                            oninput={Callback::from(move |e: InputEvent| {
+                                                        <input class="form-control form-control-sm" disabled={!can_edit}
+                                                            value={m_qty.to_string()} 
+                                                            onchange={Callback::from(move |e: Event| {
                                                                 let val = e.target_unchecked_into::<web_sys::HtmlInputElement>().value();
                                                                 update_material_val.emit((k_val.clone(), m_name_val.clone(), val));
                                                             })}
                                                         />
                                                     </td>
                                                     <td>
-                                                        <button class="btn btn-danger btn-sm" onclick={
-                                                            Callback::from(move |_| delete_material.emit((k_del.clone(), m_name_del.clone())))
-                                                        }>{"X"}</button>
+                                                        if can_edit {
+                                                            <button class="btn btn-danger btn-sm" onclick={
+                                                                Callback::from(move |_| delete_material.emit((k_del.clone(), m_name_del.clone())))
+                                                            }>{"X"}</button>
+                                                        }
                                                     </td>
                                                 </tr>
                                             }
@@ -250,11 +412,13 @@ pub fn presets_page() -> Html {
                                     }
                                 </tbody>
                             </table>
-                            <button class="btn btn-secondary btn-sm" onclick={
-                                let add_material = add_material.clone();
-                                let k = key_add.clone();
-                                move |_| add_material.emit(k.clone())
-                            }>{"+ Add Material"}</button>
+                            if can_edit {
+                                <button class="btn btn-secondary btn-sm" onclick={
+                                    let add_material = add_material.clone();
+                                    let k = key_add.clone();
+                                    move |_| add_material.emit(k.clone())
+                                }>{"+ Add Material"}</button>
+                            }
                         </div>
                     </div>
                 }
@@ -0,0 +1,109 @@
+// Editing weekly time windows: worker shifts and machine availability
+use chrono::{NaiveTime, Weekday};
+use shared::Shift;
+use yew::prelude::*;
+
+const WEEKDAYS: [Weekday; 7] = [
+    Weekday::Mon, Weekday::Tue, Weekday::Wed, Weekday::Thu, Weekday::Fri, Weekday::Sat, Weekday::Sun,
+];
+
+fn parse_time(value: &str) -> Option<NaiveTime> {
+    NaiveTime::parse_from_str(value, "%H:%M").ok()
+}
+
+pub fn shift_label(shift: &Shift) -> String {
+    format!("{} {}–{}", shift.weekday, shift.start.format("%H:%M"), shift.end.format("%H:%M"))
+}
+
+#[derive(Properties, PartialEq)]
+pub struct ShiftEditorProps {
+    pub shifts: Vec<Shift>,
+    // Called with the whole list after every change
+    pub on_change: Callback<Vec<Shift>>,
+}
+
+// One row per window with its weekday and UTC start and end times
+#[function_component(ShiftEditor)]
+pub fn shift_editor(props: &ShiftEditorProps) -> Html {
+    let on_add = {
+        let shifts = props.shifts.clone();
+        let on_change = props.on_change.clone();
+        Callback::from(move |_| {
+            let mut shifts = shifts.clone();
+            // Start from the day after the last window, which is usually what comes next
+            let weekday = shifts.last().map_or(Weekday::Mon, |s| s.weekday.succ());
+            shifts.push(Shift {
+                weekday,
+                start: NaiveTime::from_hms_opt(9, 0, 0).unwrap(),
+                end: NaiveTime::from_hms_opt(17, 0, 0).unwrap(),
+            });
+            on_change.emit(shifts);
+        })
+    };
+
+    html! {
+        <>
+            <table class="table table-sm">
+                <tbody>
+                    {for props.shifts.iter().enumerate().map(|(index, shift)| {
+                        let on_day = {
+                            let on_change = props.on_change.clone();
+                            let shifts = props.shifts.clone();
+                            Callback::from(move |e: Event| {
+                                let value = e.target_unchecked_into::<web_sys::HtmlSelectElement>().value();
+                                let weekday = value.parse::<usize>().ok().and_then(|i| WEEKDAYS.get(i));
+                                let mut shifts = shifts.clone();
+                                if let (Some(&weekday), Some(shift)) = (weekday, shifts.get_mut(index)) {
+                                    shift.weekday = weekday;
+                                    on_change.emit(shifts);
+                                }
+                            })
+                        };
+                        let on_time = |end: bool| {
+                            let on_change = props.on_change.clone();
+                            let shifts = props.shifts.clone();
+                            Callback::from(move |e: Event| {
+                                let value = e.target_unchecked_into::<web_sys::HtmlInputElement>().value();
+                                let mut shifts = shifts.clone();
+                                if let (Some(time), Some(shift)) = (parse_time(&value), shifts.get_mut(index)) {
+                                    if end { shift.end = time } else { shift.start = time }
+                                    on_change.emit(shifts);
+                                }
+                            })
+                        };
+                        let on_remove = {
+                            let on_change = props.on_change.clone();
+                            let mut remaining = props.shifts.clone();
+                            remaining.remove(index);
+                            Callback::from(move |_| on_change.emit(remaining.clone()))
+                        };
+                        html! {
+                            <tr>
+                                <td>
+                                    <select class="form-select form-select-sm" onchange={on_day}>
+                                        {for WEEKDAYS.iter().enumerate().map(|(i, day)| html! {
+                                            <option value={i.to_string()} selected={*day == shift.weekday}>{day.to_string()}</option>
+                                        })}
+                                    </select>
+                                </td>
+                                <td>
+                                    <input type="time" class="form-control form-control-sm"
+                                        value={shift.start.format("%H:%M").to_string()} onchange={on_time(false)} />
+                                </td>
+                                <td>
+                                    <input type="time" class="form-control form-control-sm"
+                                        value={shift.end.format("%H:%M").to_string()} onchange={on_time(true)} />
+                                </td>
+                                <td>
+                                    <button class="btn btn-danger btn-sm" onclick={on_remove}>{"X"}</button>
+                                </td>
+                            </tr>
+                        }
+                    })}
+                </tbody>
+            </table>
+            <div class="form-text mb-2">{"Times are UTC. A window ending before it starts runs overnight."}</div>
+            <button class="btn btn-secondary btn-sm mb-3" onclick={on_add}>{"+ Add"}</button>
+        </>
+    }
+}
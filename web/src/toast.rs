@@ -0,0 +1,121 @@
+use gloo::timers::callback::Timeout;
+use std::cell::Cell;
+use std::fmt::Display;
+use std::rc::Rc;
+use yew::prelude::*;
+
+// How long a toast stays up unless it is closed first
+const TOAST_MILLIS: u32 = 6_000;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Level {
+    Error,
+    Success,
+}
+
+#[derive(Clone, PartialEq)]
+struct Toast {
+    id: u32,
+    level: Level,
+    message: String,
+}
+
+enum ToastAction {
+    Push(Toast),
+    Dismiss(u32),
+}
+
+#[derive(Default, PartialEq)]
+struct ToastList {
+    toasts: Vec<Toast>,
+}
+
+impl Reducible for ToastList {
+    type Action = ToastAction;
+
+    fn reduce(self: Rc<Self>, action: Self::Action) -> Rc<Self> {
+        let mut toasts = self.toasts.clone();
+        match action {
+            ToastAction::Push(toast) => toasts.push(toast),
+            ToastAction::Dismiss(id) => toasts.retain(|t| t.id != id),
+        }
+        Rc::new(Self { toasts })
+    }
+}
+
+// Handle pages use to surface messages; get it with `use_toaster()`
+#[derive(Clone)]
+pub struct Toaster {
+    dispatch: UseReducerDispatcher<ToastList>,
+    next_id: Rc<Cell<u32>>,
+}
+
+impl PartialEq for Toaster {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.next_id, &other.next_id)
+    }
+}
+
+impl Toaster {
+    pub fn error(&self, message: impl Display) {
+        self.push(Level::Error, message.to_string());
+    }
+
+    pub fn success(&self, message: impl Display) {
+        self.push(Level::Success, message.to_string());
+    }
+
+    // Shows the error, if any, and hands back the value otherwise
+    pub fn report<T, E: Display>(&self, result: Result<T, E>) -> Option<T> {
+        result.map_err(|e| self.error(e)).ok()
+    }
+
+    fn push(&self, level: Level, message: String) {
+        let id = self.next_id.get();
+        self.next_id.set(id + 1);
+        self.dispatch.dispatch(ToastAction::Push(Toast { id, level, message }));
+        let dispatch = self.dispatch.clone();
+        Timeout::new(TOAST_MILLIS, move || dispatch.dispatch(ToastAction::Dismiss(id))).forget();
+    }
+}
+
+#[hook]
+pub fn use_toaster() -> Toaster {
+    use_context::<Toaster>().expect("use_toaster outside ToastProvider")
+}
+
+#[derive(Properties, PartialEq)]
+pub struct ToastProviderProps {
+    pub children: Children,
+}
+
+#[function_component(ToastProvider)]
+pub fn toast_provider(props: &ToastProviderProps) -> Html {
+    let list = use_reducer(ToastList::default);
+    let next_id = use_memo(|_| Rc::new(Cell::new(0)), ());
+    let toaster = Toaster { dispatch: list.dispatcher(), next_id: (*next_id).clone() };
+
+    html! {
+        <ContextProvider<Toaster> context={toaster}>
+            {props.children.clone()}
+            <div class="toast-container position-fixed bottom-0 end-0 p-3" style="z-index: 1080">
+                {for list.toasts.iter().map(|toast| {
+                    let dispatch = list.dispatcher();
+                    let id = toast.id;
+                    let class = match toast.level {
+                        Level::Error => "alert alert-danger",
+                        Level::Success => "alert alert-success",
+                    };
+                    html! {
+                        <div key={id} class={classes!(class, "alert-dismissible", "shadow-sm", "mb-2")} role="alert"
+                            style="white-space: pre-line; min-width: 18rem">
+                            {&toast.message}
+                            <button type="button" class="btn-close"
+                                onclick={Callback::from(move |_| dispatch.dispatch(ToastAction::Dismiss(id)))} />
+                        </div>
+                    }
+                })}
+            </div>
+        </ContextProvider<Toaster>>
+    }
+}
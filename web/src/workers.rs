@@ -0,0 +1,280 @@
+use yew::prelude::*;
+use yew_router::prelude::*;
+use std::collections::HashMap;
+use web_sys::InputEvent;
+use shared::{AuditEntity, FieldError, ServerEvent, Shift, Worker};
+use crate::Route;
+use crate::history::HistoryPanel;
+use crate::live::{use_server_events, ListAction, LiveList};
+use crate::login::UserBar;
+use crate::shifts::{shift_label, ShiftEditor};
+use crate::api;
+use crate::toast::use_toaster;
+
+// The worker the form describes, or the inputs that need fixing first
+fn form_worker(id: &str, name: &str, skills: &str, max_concurrent: &str, shifts: &[Shift]) -> Result<Worker, Vec<FieldError>> {
+    let parsed_max = max_concurrent.trim().parse::<u32>();
+    let worker = Worker {
+        id: id.trim().to_string(),
+        display_name: name.trim().to_string(),
+        skills: skills.split(',').map(str::trim).filter(|s| !s.is_empty()).map(str::to_string).collect(),
+        shifts: shifts.to_vec(),
+        max_concurrent: parsed_max.clone().unwrap_or(1),
+    };
+    let mut errors = worker.validate();
+    if parsed_max.is_err() {
+        errors.push(FieldError::new("max_concurrent", "Max concurrent tasks must be a whole number"));
+    }
+    if errors.is_empty() { Ok(worker) } else { Err(errors) }
+}
+
+#[function_component(WorkersPage)]
+pub fn workers_page() -> Html {
+    let workers = use_reducer(LiveList::<Worker>::default);
+    // The id of the worker being edited; None while adding a new one
+    let editing = use_state(|| None::<String>);
+    let form_id = use_state(String::new);
+    let form_name = use_state(String::new);
+    let form_skills = use_state(String::new);
+    let form_max = use_state(|| "1".to_string());
+    let form_shifts = use_state(Vec::<Shift>::new);
+    let field_errors = use_state(HashMap::<String, String>::new);
+    let toaster = use_toaster();
+    // Everyone may look workers up; only admins change them
+    let can_edit = api::auth::session().is_some_and(|s| s.user.role.can_administer());
+
+    let fetch_workers = {
+        let workers = workers.clone();
+        let toaster = toaster.clone();
+        Callback::from(move |_| {
+            let workers = workers.clone();
+            let toaster = toaster.clone();
+            wasm_bindgen_futures::spawn_local(async move {
+                if let Some(fetched) = toaster.report(api::workers::list().await) {
+                    workers.dispatch(ListAction::Replace(fetched));
+                }
+            });
+        })
+    };
+
+    {
+        let fetch_workers = fetch_workers.clone();
+        use_effect_with_deps(move |_| {
+            fetch_workers.emit(());
+            || {}
+        }, ());
+    }
+
+    {
+        let workers = workers.dispatcher();
+        let fetch_workers = fetch_workers.clone();
+        use_server_events(Callback::from(move |event: ServerEvent| match event {
+            ServerEvent::Changed(entry) if entry.entity == AuditEntity::Worker => {
+                match ListAction::from_entry(&entry) {
+                    Some(action) => workers.dispatch(action),
+                    None => fetch_workers.emit(()),
+                }
+            }
+            ServerEvent::Changed(_) => {}
+            ServerEvent::Resync => fetch_workers.emit(()),
+        }));
+    }
+
+    // Fill the form from `worker`, or clear it for a new one
+    let load_form = {
+        let editing = editing.clone();
+        let form_id = form_id.clone();
+        let form_name = form_name.clone();
+        let form_skills = form_skills.clone();
+        let form_max = form_max.clone();
+        let form_shifts = form_shifts.clone();
+        let field_errors = field_errors.clone();
+        Callback::from(move |worker: Option<Worker>| {
+            field_errors.set(HashMap::new());
+            editing.set(worker.as_ref().map(|w| w.id.clone()));
+            let worker = worker.unwrap_or(Worker {
+                id: String::new(),
+                display_name: String::new(),
+                skills: Vec::new(),
+                shifts: Vec::new(),
+                max_concurrent: 1,
+            });
+            form_id.set(worker.id);
+            form_name.set(worker.display_name);
+            form_skills.set(worker.skills.join(", "));
+            form_max.set(worker.max_concurrent.to_string());
+            form_shifts.set(worker.shifts);
+        })
+    };
+
+    let on_save = {
+        let editing = editing.clone();
+        let form_id = form_id.clone();
+        let form_name = form_name.clone();
+        let form_skills = form_skills.clone();
+        let form_max = form_max.clone();
+        let form_shifts = form_shifts.clone();
+        let field_errors = field_errors.clone();
+        let load_form = load_form.clone();
+        let toaster = toaster.clone();
+        Callback::from(move |_| {
+            let worker = match form_worker(&form_id, &form_name, &form_skills, &form_max, &form_shifts) {
+                Ok(worker) => worker,
+                Err(errors) => {
+                    field_errors.set(errors.iter().map(|e| (e.field.clone(), e.message.clone())).collect());
+                    return;
+                }
+            };
+            let creating = editing.is_none();
+            let field_errors = field_errors.clone();
+            let load_form = load_form.clone();
+            let toaster = toaster.clone();
+            wasm_bindgen_futures::spawn_local(async move {
+                let result = if creating {
+                    api::workers::create(&worker).await
+                } else {
+                    api::workers::update(&worker).await
+                };
+                if let Err(api::Error::Api(e)) = &result {
+                    field_errors.set(e.field_errors.iter().map(|e| (e.field.clone(), e.message.clone())).collect());
+                }
+                if let Some(saved) = toaster.report(result) {
+                    load_form.emit(Some(saved));
+                }
+            });
+        })
+    };
+
+    let on_delete = {
+        let editing = editing.clone();
+        let load_form = load_form.clone();
+        let toaster = toaster.clone();
+        Callback::from(move |id: String| {
+            if !gloo::dialogs::confirm(&format!("Delete worker '{}'?", id)) {
+                return;
+            }
+            if editing.as_deref() == Some(id.as_str()) {
+                load_form.emit(None);
+            }
+            let toaster = toaster.clone();
+            wasm_bindgen_futures::spawn_local(async move {
+                toaster.report(api::workers::delete(&id).await);
+            });
+        })
+    };
+
+    let invalid = |field: &str| if field_errors.contains_key(field) { "is-invalid" } else { "" };
+    let feedback = |field: &str| match field_errors.get(field) {
+        Some(message) => html! { <div class="invalid-feedback d-block">{message}</div> },
+        None => html! {},
+    };
+    let text_input = |handle: &UseStateHandle<String>| {
+        let handle = handle.clone();
+        Callback::from(move |e: InputEvent| handle.set(e.target_unchecked_into::<web_sys::HtmlInputElement>().value()))
+    };
+
+    html! {
+        <div class="container">
+            <div class="mb-3">
+                <Link<Route> to={Route::Home} classes="btn btn-outline-primary me-2">{"Gantt Chart"}</Link<Route>>
+                <Link<Route> to={Route::Inventory} classes="btn btn-outline-success me-2">{"Inventory"}</Link<Route>>
+                <Link<Route> to={Route::Presets} classes="btn btn-outline-info me-2">{"Task Presets"}</Link<Route>>
+                <Link<Route> to={Route::Workers} classes="btn btn-outline-dark me-2">{"Workers"}</Link<Route>>
+                <Link<Route> to={Route::Machines} classes="btn btn-outline-secondary me-2">{"Machines"}</Link<Route>>
+                <Link<Route> to={Route::Jobs} classes="btn btn-outline-warning">{"Jobs"}</Link<Route>>
+                <UserBar />
+            </div>
+            <h2>{"Workers"}</h2>
+            <div class="row">
+                <div class="col-md-7">
+                    <table class="table table-bordered table-sm">
+                        <thead>
+                            <tr>
+                                <th>{"ID"}</th>
+                                <th>{"Name"}</th>
+                                <th>{"Skills"}</th>
+                                <th>{"Shifts (UTC)"}</th>
+                                <th>{"Max Concurrent"}</th>
+                                <th></th>
+                            </tr>
+                        </thead>
+                        <tbody>
+                            {for workers.iter().map(|worker| {
+                                let selected = editing.as_deref() == Some(worker.id.as_str());
+                                let worker_edit = worker.clone();
+                                let id_del = worker.id.clone();
+                                let load_form = load_form.clone();
+                                let on_delete = on_delete.clone();
+                                html! {
+                                    <tr key={worker.id.clone()} class={classes!(selected.then_some("table-active"))}>
+                                        <td>{&worker.id}</td>
+                                        <td>{&worker.display_name}</td>
+                                        <td>{worker.skills.join(", ")}</td>
+                                        <td>
+                                            {for worker.shifts.iter().map(|s| html! {
+                                                <div>{shift_label(s)}</div>
+                                            })}
+                                        </td>
+                                        <td>{worker.max_concurrent}</td>
+                                        <td class="text-nowrap">
+                                            <button class="btn btn-outline-secondary btn-sm me-2"
+                                                onclick={Callback::from(move |_| load_form.emit(Some(worker_edit.clone())))}>
+                                                {if can_edit { "Edit" } else { "View" }}
+                                            </button>
+                                            if can_edit {
+                                                <button class="btn btn-danger btn-sm"
+                                                    onclick={Callback::from(move |_| on_delete.emit(id_del.clone()))}>{"Delete"}</button>
+                                            }
+                                        </td>
+                                    </tr>
+                                }
+                            })}
+                        </tbody>
+                    </table>
+                </div>
+                <div class="col-md-5">
+                    <div class="card p-3 mb-3">
+                        <h4>{if editing.is_some() { "Edit Worker" } else { "Add Worker" }}</h4>
+                        <fieldset disabled={!can_edit}>
+                            <div class="mb-2">
+                                <input class={classes!("form-control", invalid("id"))} placeholder="Worker ID"
+                                    disabled={editing.is_some()}
+                                    value={(*form_id).clone()} oninput={text_input(&form_id)} />
+                                {feedback("id")}
+                            </div>
+                            <div class="mb-2">
+                                <input class={classes!("form-control", invalid("display_name"))} placeholder="Display Name"
+                                    value={(*form_name).clone()} oninput={text_input(&form_name)} />
+                                {feedback("display_name")}
+                            </div>
+                            <div class="mb-2">
+                                <input class={classes!("form-control", invalid("skills"))} placeholder="Skills, comma separated"
+                                    value={(*form_skills).clone()} oninput={text_input(&form_skills)} />
+                                {feedback("skills")}
+                            </div>
+                            <div class="mb-2">
+                                <label class="form-label">{"Max concurrent tasks"}</label>
+                                <input type="number" min="1" class={classes!("form-control", invalid("max_concurrent"))}
+                                    value={(*form_max).clone()} oninput={text_input(&form_max)} />
+                                {feedback("max_concurrent")}
+                            </div>
+
+                            <h6>{"Weekly shifts (UTC)"}</h6>
+                            <ShiftEditor shifts={(*form_shifts).clone()}
+                                on_change={let form_shifts = form_shifts.clone(); Callback::from(move |shifts| form_shifts.set(shifts))} />
+
+                            <div class="d-flex gap-2">
+                                <button class="btn btn-primary" onclick={on_save}>{"Save Worker"}</button>
+                                if editing.is_some() {
+                                    <button class="btn btn-secondary"
+                                        onclick={let load_form = load_form.clone(); Callback::from(move |_| load_form.emit(None))}>{"New Worker"}</button>
+                                }
+                            </div>
+                        </fieldset>
+                    </div>
+                </div>
+            </div>
+            <HistoryPanel entity={AuditEntity::Worker} entity_id={(*editing).clone()} />
+        </div>
+    }
+}